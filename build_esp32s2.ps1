@@ -183,6 +183,18 @@ if ($binSize -lt 32768) {
 
 Write-Host "Using application binary: $binaryPath (size: $binSize bytes)" -ForegroundColor Green
 
+# Report how much of the factory partition the application uses (partitions.csv)
+$factoryLine = Get-Content $partitionsCsv | Where-Object { $_ -match '^\s*factory\s*,' } | Select-Object -First 1
+if ($factoryLine -and $binaryPath -like '*.bin') {
+    $factorySize = [Convert]::ToInt64(($factoryLine -split ',')[4].Trim(), 16)
+    $headroom = $factorySize - $binSize
+    Write-Host ("Factory partition: {0} KB, application: {1} KB, headroom: {2} KB" -f ($factorySize / 1024), [math]::Ceiling($binSize / 1024), [math]::Floor($headroom / 1024)) -ForegroundColor Cyan
+    if ($headroom -lt 0) {
+        Write-Host "Error: application binary does not fit the factory partition" -ForegroundColor Red
+        exit 1
+    }
+}
+
 # Generate merged image (including bootloader, partition and application)
 Write-Host "Generating merged image: $binOutputPath" -ForegroundColor Cyan
 # Use absolute path for partitions.csv
@@ -86,6 +86,16 @@ pub struct DisplayConfig {
     ///
     #[serde(default = "default_brightness")]  // 如果配置文件中不存在此字段，使用default_brightness()函数提供默认值
     pub brightness: u8,
+    /// 画布分条渲染的条带高度(像素)，0 表示自动（每个条带约32KB）
+    /// 条带越高绘制越快，但占用内存越多
+    #[serde(default)]
+    pub render_band_height: u16,
+    /// 是否保存一份屏幕内容用于截图(/screenshot)，占用 宽x高x2 字节内存（分配在PSRAM中）
+    #[serde(default)]
+    pub shadow_framebuffer: bool,
+    /// 解码后图片的内存缓存预算(KB)，0 表示默认 1024KB，超出时淘汰最久未使用的图片
+    #[serde(default)]
+    pub image_cache_kb: u32,
 }
 
 impl DisplayConfig{
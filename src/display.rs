@@ -1,5 +1,8 @@
 use crate::canvas::draw_splash_with_error;
 use crate::config::DisplayConfig;
+use wifi_screen_core::device::Screen;
+use wifi_screen_core::framebuffer::FrameBuffer;
+use wifi_screen_core::rgb565::{rgb565_to_rgb888_adjusted, rgb888_to_rgb565, rgb_image_to_rgb565};
 use crate::with_context;
 use ab_glyph::FontRef;
 use anyhow::{anyhow, Result};
@@ -42,6 +45,8 @@ pub struct DisplayManager<'a> {
     pub display: DisplayInterface,
     pub display_config: DisplayConfig,
     pub font: FontRef<'a>,
+    /// 屏幕内容的副本，用于截图（display_config.shadow_framebuffer 为 true 时创建）
+    pub shadow: Option<FrameBuffer>,
 }
 
 impl <'a> DisplayManager<'a>{
@@ -74,6 +79,45 @@ impl <'a> DisplayManager<'a>{
     pub fn get_screen_height(&self) -> u16{
         self.get_screen_size().1
     }
+
+    /// 当前屏幕内容的PNG截图
+    pub fn screenshot_png(&self) -> Result<Vec<u8>> {
+        match self.shadow.as_ref() {
+            Some(shadow) => shadow.to_png(),
+            None => Err(anyhow!("截图功能未启用，请在屏幕参数中勾选“启用截图”")),
+        }
+    }
+}
+
+/// wifi-screen-core 中的消息处理通过 Screen 绘制到屏幕
+impl Screen for DisplayManager<'static> {
+    fn size(&self) -> (u16, u16) {
+        self.get_screen_size()
+    }
+
+    fn font(&self) -> FontRef<'static> {
+        self.font.clone()
+    }
+
+    fn render_band_height(&self) -> u16 {
+        self.display_config.render_band_height
+    }
+
+    fn draw_rgb565(&mut self, x: u16, y: u16, width: u16, height: u16, pixels: &[u16]) -> Result<()> {
+        draw_rgb565_fast(self, x, y, width, height, pixels)
+    }
+
+    fn draw_rgb565_u8array(&mut self, x: u16, y: u16, width: u16, height: u16, pixels: &[u8]) -> Result<()> {
+        draw_rgb565_u8array_fast(self, x, y, width, height, pixels)
+    }
+
+    fn draw_rgb_image(&mut self, x: u16, y: u16, image: &RgbImage) -> Result<()> {
+        draw_rgb_image_fast(self, x, y, image)
+    }
+
+    fn screenshot_png(&self) -> Result<Vec<u8>> {
+        DisplayManager::screenshot_png(self)
+    }
 }
 
 pub enum DisplayInterface {
@@ -284,10 +328,15 @@ pub fn init() -> Result<()> {
         info!("init display>07: Font loaded successfully");
 
         info!("init display>08: Creating DisplayManager...");
+        // 大块内存由 malloc 自动分配在 PSRAM 中 (CONFIG_SPIRAM_USE_MALLOC)
+        let (screen_width, screen_height) = display_config.get_screen_size();
+        let shadow = display_config.shadow_framebuffer.then(|| FrameBuffer::new(screen_width, screen_height));
+
         let display_manager = DisplayManager {
             display_config: display_config.clone(),
             display: display_interface,
             font,
+            shadow,
         };
 
         ctx.display.replace(display_manager);
@@ -457,28 +506,11 @@ pub fn draw_rgb_image_fast(
     let adj_g = display_manager.display_config.color_adjust_g;
     let adj_b = display_manager.display_config.color_adjust_b;
     
-    let mut pixels = Box::new(Vec::with_capacity(
-        image.width() as usize * image.height() as usize,
-    ));
-    
-    // 应用色调调整
-    if adj_r == 0 && adj_g == 0 && adj_b == 0 {
-        // 无需调整，直接转换
-        for pixel in image.pixels() {
-            pixels.push(rgb888_to_rgb565(
-                pixel[0], pixel[1], pixel[2],
-            ).to_be());
-        }
-    } else {
+    if adj_r != 0 || adj_g != 0 || adj_b != 0 {
         info!("[DRAW_IMG] color_adjust r={} g={} b={}", adj_r, adj_g, adj_b);
-        // 应用色调调整后转换
-        for pixel in image.pixels() {
-            let r = apply_color_adjust(pixel[0], adj_r);
-            let g = apply_color_adjust(pixel[1], adj_g);
-            let b = apply_color_adjust(pixel[2], adj_b);
-            pixels.push(rgb888_to_rgb565(r, g, b).to_be());
-        }
     }
+    // 转换为RGB565并应用色调调整
+    let mut pixels = Box::new(rgb_image_to_rgb565(image, adj_r, adj_g, adj_b));
 
     // mipidsi 库的 set_pixels_buffer_u16 始终使用 inclusive 结束坐标
     let (end_x, end_y) = (x + width - 1, y + height - 1);
@@ -502,6 +534,9 @@ pub fn draw_rgb_image_fast(
     match draw_result {
         Ok(_) => {
             info!("[DRAW_IMG_OK] {}x{} pixels in {}ms", width, height, elapsed_ms);
+            if let Some(shadow) = display_manager.shadow.as_mut() {
+                shadow.draw_rgb_image(x, y, image)?;
+            }
             Ok(())
         }
         Err(err) => {
@@ -539,6 +574,9 @@ pub fn draw_rgb565_fast(
             }
         }
         .map_err(|err| anyhow!("draw error:{err:?}"))?;
+        if let Some(shadow) = display_manager.shadow.as_mut() {
+            shadow.draw_rgb565(x, y, width, height, pixels)?;
+        }
         return Ok(());
     }
     
@@ -566,6 +604,10 @@ pub fn draw_rgb565_fast(
         }
     }
     .map_err(|err| anyhow!("draw error:{err:?}"))?;
+    // 截图保存调整前的颜色
+    if let Some(shadow) = display_manager.shadow.as_mut() {
+        shadow.draw_rgb565(x, y, width, height, pixels)?;
+    }
     Ok(())
 }
 
@@ -642,6 +684,9 @@ pub fn draw_rgb565_u8array_fast(
     match draw_result {
         Ok(_) => {
             // info!("[DRAW_OK] {}x{} pixels in {}ms", width, height, elapsed_ms);
+            if let Some(shadow) = display_manager.shadow.as_mut() {
+                shadow.draw_rgb565_u8array(x, y, width, height, pixels)?;
+            }
             Ok(())
         }
         Err(err) => {
@@ -650,77 +695,3 @@ pub fn draw_rgb565_u8array_fast(
         }
     }
 }
-
-// #[inline]
-// fn rgb888_to_rgb565(r: u8, g: u8, b: u8) -> u16 {
-//     // 缩放颜色分量到目标位数
-//     let r5 = (r as u16 * 31 / 255) << 11; // 5 bits for red, shift left by 11 bits
-//     let g6 = (g as u16 * 63 / 255) << 5;  // 6 bits for green, shift left by 5 bits
-//     let b5 = b as u16 * 31 / 255;         // 5 bits for blue
-
-//     // 组合成16位RGB565值
-//     (r5 | g6 | b5) as u16
-// }
-
-macro_rules! generate_lut {
-    ($name:ident, $factor:expr, $shift:expr) => {
-        const $name: [u16; 256] = {
-            let mut lut = [0u16; 256];
-            let mut i = 0;
-            while i < 256 {
-                lut[i] = ((i as u16 * $factor) / 255) << $shift;
-                i += 1;
-            }
-            lut
-        };
-    };
-}
-
-generate_lut!(RGB565_R_LUT, 31, 11); // 红色：5位，左移11位
-generate_lut!(RGB565_G_LUT, 63, 5); // 绿色：6位，左移5位
-generate_lut!(RGB565_B_LUT, 31, 0); // 蓝色：5位，不移位
-
-#[inline(always)]
-pub fn rgb888_to_rgb565(r: u8, g: u8, b: u8) -> u16 {
-    // 使用查找表获取缩放后的颜色分量
-    let r5 = RGB565_R_LUT[r as usize];
-    let g6 = RGB565_G_LUT[g as usize];
-    let b5 = RGB565_B_LUT[b as usize];
-
-    // 组合成16位RGB565值
-    r5 | g6 | b5
-}
-
-#[inline(always)]
-pub fn rgb565_to_rgb888(pixel: u16) -> (u8, u8, u8) {
-    // 分离颜色分量
-    let r = ((pixel >> 11) & 0x1F) as u8; // 5 bits for red
-    let g = ((pixel >> 5) & 0x3F) as u8; // 6 bits for green
-    let b = (pixel & 0x1F) as u8; // 5 bits for blue
-
-    // 扩展颜色分量到8位
-    let r8 = (r as u16 * 255 / 31) as u8;
-    let g8 = (g as u16 * 255 / 63) as u8;
-    let b8 = (b as u16 * 255 / 31) as u8;
-
-    (r8, g8, b8)
-}
-
-/// 应用色调调整
-/// adjust: -100 到 +100 的调整值
-#[inline(always)]
-pub fn apply_color_adjust(color: u8, adjust: i8) -> u8 {
-    let adjusted = color as i16 + (adjust as i16 * 255 / 100);
-    adjusted.clamp(0, 255) as u8
-}
-
-/// RGB565转RGB888并应用色调调整
-#[inline(always)]
-pub fn rgb565_to_rgb888_adjusted(pixel: u16, adj_r: i8, adj_g: i8, adj_b: i8) -> (u8, u8, u8) {
-    let (r, g, b) = rgb565_to_rgb888(pixel);
-    (
-        apply_color_adjust(r, adj_r),
-        apply_color_adjust(g, adj_g),
-        apply_color_adjust(b, adj_b),
-    )
-}
@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use wifi_screen_core::gif_player::PlayGif;
+
+use crate::{with_context, Context};
+
+// 设备端 GIF 播放：上传的 GIF 解码为 RGB565 帧后由播放线程按帧延时绘制
+// 播放线程每帧只在绘制时持有 Context 锁，其他绘制请求在帧之间执行；与动画区域重叠的绘制会停止播放（见 wifi_screen_core::device）
+
+/// 播放线程是否在运行，只在持有 Context 锁时修改
+static PLAYER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 播放已上传的 GIF，正在播放的动画会被替换
+pub fn play_gif(ctx: &mut Context, params: PlayGif) -> Result<()> {
+    let display_manager = match ctx.display.as_ref() {
+        None => return Err(anyhow!("请设置屏幕参数!")),
+        Some(v) => v,
+    };
+    ctx.device.play_gif(display_manager, params)?;
+    start_player(ctx)
+}
+
+pub fn stop_gif(ctx: &mut Context) {
+    ctx.device.stop_gif();
+}
+
+/// 有动画并且播放线程没有运行时启动播放线程
+pub fn start_player(ctx: &Context) -> Result<()> {
+    if ctx.device.gif_player.is_some() && !PLAYER_RUNNING.load(Ordering::SeqCst) {
+        std::thread::Builder::new()
+            .stack_size(8 * 1024)
+            .spawn(player_loop)?;
+        PLAYER_RUNNING.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+fn player_loop() {
+    loop {
+        let start = Instant::now();
+        let result = with_context(|ctx| {
+            let result = match ctx.display.as_mut() {
+                Some(display_manager) => ctx.device.draw_gif_frame(display_manager),
+                None => Err(anyhow!("请设置屏幕参数!")),
+            };
+            if !matches!(result, Ok(Some(_))) {
+                //出错或者播放结束，在锁内退出，避免与 play_gif 竞争
+                ctx.device.stop_gif();
+                PLAYER_RUNNING.store(false, Ordering::SeqCst);
+            }
+            result
+        });
+        match result {
+            Ok(Some(delay)) => std::thread::sleep(delay.saturating_sub(start.elapsed())),
+            Ok(None) => break,
+            Err(err) => {
+                error!("gif player: {err:?}");
+                break;
+            }
+        }
+    }
+    info!("gif player stopped");
+}
@@ -1,9 +1,12 @@
 use std::{collections::HashMap, num::NonZero, str, sync::{Arc, Mutex}, time::{Duration, Instant}};
 
 use anyhow::{anyhow, Result};
-use canvas::{
-    decode_jpg_to_rgb, draw_elements, draw_splash_with_error1, Element,
-};
+use canvas::draw_splash_with_error1;
+use wifi_screen_core::device::WsReply;
+use wifi_screen_core::gif_player::PlayGif;
+use wifi_screen_core::scene::ScenePatch;
+use wifi_screen_core::sprite::SpriteSheet;
+use wifi_screen_core::template::RenderTemplate;
 use embedded_svc::{
     http::{Headers, Method},
     io::{Read, Write},
@@ -17,149 +20,14 @@ use esp_idf_svc::{
     ws::FrameType,
 };
 
-use image::{codecs::png::PngEncoder, ImageEncoder};
 use log::*;
-use once_cell::sync::Lazy;
 use url::Url;
 
-use crate::{canvas, config, display::{self, check_screen_size}, with_context, with_context1, Context, ImageCache, MAX_HTTP_PAYLOAD_LEN, STACK_SIZE};
-
-// WiFi帧差分协议 Magic Numbers (8字节)
-const WIFI_KEY_MAGIC: &[u8; 8] = b"wflz4ke_"; // lz4压缩的关键帧(完整RGB565)
-const WIFI_DLT_MAGIC: &[u8; 8] = b"wflz4dl_"; // lz4压缩的差分帧(XOR差分数据)
-const WIFI_NOP_MAGIC: &[u8; 8] = b"wflz4no_"; // 无变化帧(屏幕静止，跳过绘制)
-
-// WiFi帧差分解码器 (全局单例，用于WebSocket接收)
-// 用于在ESP32端对接收的帧差分数据进行解码
-// 注意: 为了节省内存，解码后返回对内部缓冲区的引用，调用者需要在锁持有期间使用数据
-struct DeltaDecoder {
-    prev_frame: Vec<u8>,  // 上一帧RGB565数据 (存储在PSRAM)
-    error_count: u32,     // 错误计数(用于限制日志频率)
-    last_error: Option<&'static str>, // 上一次错误类型
-}
-
-impl DeltaDecoder {
-    fn new() -> Self {
-        Self {
-            prev_frame: Vec::new(),
-            error_count: 0,
-            last_error: None,
-        }
-    }
-
-    // 检查是否有参考帧
-    fn has_reference_frame(&self) -> bool {
-        !self.prev_frame.is_empty()
-    }
-
-    // 记录错误(限制日志频率)
-    fn log_error(&mut self, err: &'static str) {
-        // 只在错误类型变化或每100次时记录
-        if self.last_error != Some(err) || self.error_count % 100 == 0 {
-            if self.error_count > 1 && self.last_error == Some(err) {
-                warn!("wifi frame: {} (x{})", err, self.error_count);
-            } else {
-                warn!("wifi frame: {}", err);
-            }
-            self.error_count = 1;
-        } else {
-            self.error_count += 1;
-        }
-        self.last_error = Some(err);
-    }
-
-    // 重置错误计数
-    fn clear_error(&mut self) {
-        if self.error_count > 1 {
-            if let Some(err) = self.last_error {
-                info!("wifi frame: recovered after {} errors ({})", self.error_count, err);
-            }
-        }
-        self.error_count = 0;
-        self.last_error = None;
-    }
-
-    // lz4解压辅助函数 (比zstd快5-10倍)
-    fn lz4_decompress(lz4_data: &[u8]) -> Result<Vec<u8>, &'static str> {
-        lz4_flex::decompress_size_prepended(lz4_data)
-            .map_err(|_| "lz4 decompress failed")
-    }
-
-    // 解码关键帧 (lz4压缩的完整RGB565)
-    fn decode_key_frame(&mut self, lz4_data: &[u8]) -> Result<&[u8], &'static str> {
-        let decompressed = Self::lz4_decompress(lz4_data)?;
-        self.prev_frame = decompressed;
-        self.clear_error();
-        Ok(&self.prev_frame)
-    }
-
-    // 解码差分帧 (lz4压缩的XOR差分数据)
-    // 返回: (解码后数据引用, lz4解压耗时ms, xor耗时ms)
-    fn decode_delta_frame_timed(&mut self, lz4_data: &[u8]) -> Result<(&[u8], u128, u128), &'static str> {
-        if self.prev_frame.is_empty() {
-            return Err("no reference frame");
-        }
-        
-        // LZ4解压计时
-        let lz4_start = Instant::now();
-        let delta = Self::lz4_decompress(lz4_data)?;
-        let lz4_ms = lz4_start.elapsed().as_millis();
-        
-        if delta.len() != self.prev_frame.len() {
-            return Err("delta size mismatch");
-        }
-        
-        // XOR计时
-        let xor_start = Instant::now();
-        
-        // 使用u32批量XOR加速 (ESP32是32位CPU)
-        let len = self.prev_frame.len();
-        let chunks = len / 4;
-        let remainder = len % 4;
-        
-        // 批量处理4字节
-        let prev_u32: &mut [u32] = unsafe {
-            std::slice::from_raw_parts_mut(self.prev_frame.as_mut_ptr() as *mut u32, chunks)
-        };
-        let delta_u32: &[u32] = unsafe {
-            std::slice::from_raw_parts(delta.as_ptr() as *const u32, chunks)
-        };
-        for (p, d) in prev_u32.iter_mut().zip(delta_u32.iter()) {
-            *p ^= *d;
-        }
-        
-        // 处理剩余字节
-        if remainder > 0 {
-            let start = chunks * 4;
-            for i in 0..remainder {
-                self.prev_frame[start + i] ^= delta[start + i];
-            }
-        }
-        
-        let xor_ms = xor_start.elapsed().as_millis();
-        
-        self.clear_error();
-        Ok((&self.prev_frame, lz4_ms, xor_ms))
-    }
-    
-    // 解码差分帧 (兼容旧接口)
-    fn decode_delta_frame(&mut self, lz4_data: &[u8]) -> Result<&[u8], &'static str> {
-        self.decode_delta_frame_timed(lz4_data).map(|(data, _, _)| data)
-    }
-
-    // 重置解码器状态
-    fn reset(&mut self) {
-        self.prev_frame.clear();
-        self.prev_frame.shrink_to_fit();
-        self.error_count = 0;
-        self.last_error = None;
-    }
-}
+use crate::gif_player::{play_gif, stop_gif};
+use crate::mqtt_client::handle_mqtt_message;
+use crate::{canvas, config, display::{self, check_screen_size}, with_context, with_context1, Context, MAX_HTTP_PAYLOAD_LEN, STACK_SIZE};
+use wifi_screen_core::framebuffer::FrameBuffer;
 
-// 全局帧差分解码器实例
-static DELTA_DECODER: Lazy<Mutex<DeltaDecoder>> = Lazy::new(|| {
-    Mutex::new(DeltaDecoder::new())
-});
 
 pub fn start_http_server() -> Result<()>{
     let mut server = create_server()?;
@@ -815,9 +683,35 @@ pub fn start_http_server() -> Result<()>{
                 Some(v) => v,
                 None => return Err(anyhow!("缺少参数key")),
             };
-            ctx.image_cache.remove(key);
-            let keys: Vec<String> = ctx.image_cache.keys().map(|k| k.to_string()).collect();
-            Ok(keys)
+            ctx.device.delete_image(key)
+        }) {
+            Ok(keys) => req
+                .into_ok_response()?
+                .write_all(format!("{keys:?}").as_bytes())
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
+
+    // 固定/取消固定图片: /pin_image?key=xxx&pin=false
+    server.fn_handler("/pin_image", Method::Get, |req| {
+        let uri = req.uri().to_string();
+        match with_context(move |ctx| {
+            let url = Url::parse(&format!("http://localhost{uri}"))?;
+            let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            let key = match params.get("key") {
+                Some(v) => v,
+                None => return Err(anyhow!("缺少参数key")),
+            };
+            let pin = !matches!(params.get("pin").map(String::as_str), Some("false" | "0"));
+            ctx.device.pin_image(key, pin)
         }) {
             Ok(keys) => req
                 .into_ok_response()?
@@ -834,6 +728,45 @@ pub fn start_http_server() -> Result<()>{
         }
     })?;
 
+    // 播放已上传的GIF: /play_gif?key=xxx&x=0&y=0&loops=0 (loops为0时无限循环)
+    server.fn_handler("/play_gif", Method::Get, |req| {
+        let uri = req.uri().to_string();
+        match with_context(move |ctx| {
+            let url = Url::parse(&format!("http://localhost{uri}"))?;
+            let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            let key = match params.get("key") {
+                Some(v) => v.to_string(),
+                None => return Err(anyhow!("缺少参数key")),
+            };
+            let x = params.get("x").map(|v| v.parse()).transpose()?.unwrap_or(0);
+            let y = params.get("y").map(|v| v.parse()).transpose()?.unwrap_or(0);
+            let loops = params.get("loops").map(|v| v.parse()).transpose()?.unwrap_or(0);
+            play_gif(ctx, PlayGif { key, x, y, loops })
+        }) {
+            Ok(()) => req
+                .into_ok_response()?
+                .write_all(b"OK")
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
+
+    // 停止播放GIF，屏幕停留在当前帧
+    server.fn_handler("/stop_gif", Method::Get, |req| {
+        with_context1(|ctx| {
+            stop_gif(ctx);
+            Ok(())
+        })?;
+        req.into_ok_response()?.write_all(b"OK").map(|_| ())
+    })?;
+
     // 获取缓存的图片(返回png)
     server.fn_handler("/download_image", Method::Get, |req| {
         let uri = req.uri().to_string();
@@ -844,32 +777,78 @@ pub fn start_http_server() -> Result<()>{
                 Some(v) => v,
                 None => return Err(anyhow!("缺少参数key")),
             };
-            match ctx.image_cache.get(key) {
-                Some(img) => {
-                    let mut out = Box::new(vec![]);
-                    let encoder = PngEncoder::new(&mut out);
-                    match img {
-                        ImageCache::RgbImage(img) => {
-                            encoder.write_image(
-                                &img,
-                                img.width(),
-                                img.height(),
-                                image::ExtendedColorType::Rgb8,
-                            )?;
-                        }
-                        ImageCache::RgbaImage(img) => {
-                            encoder.write_image(
-                                &img,
-                                img.width(),
-                                img.height(),
-                                image::ExtendedColorType::Rgba8,
-                            )?;
-                        }
-                    }
-                    Ok(out)
-                }
-                None => Err(anyhow!("key not exist")),
-            }
+            ctx.device.download_image(key)
+        }) {
+            Ok(png) => req
+                .into_response(
+                    200,
+                    Some("OK"),
+                    &[
+                        ("Content-Type", "image/png"),
+                        ("Content-Length", &format!("{}", png.len())),
+                    ],
+                )?
+                .write_all(&png)
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
+
+    // 列出flash中保存的图片
+    server.fn_handler("/list_images", Method::Get, |req| {
+        match with_context(move |ctx| Ok(ctx.device.image_keys())) {
+            Ok(keys) => req
+                .into_ok_response()?
+                .write_all(format!("{keys:?}").as_bytes())
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
+
+    // 图片存储空间使用情况(返回json)
+    server.fn_handler("/image_quota", Method::Get, |req| {
+        match with_context(move |ctx| match ctx.device.image_store.as_ref() {
+            Some(store) => Ok(serde_json::to_string(&store.usage()?)?),
+            None => Err(anyhow!("图片存储未挂载")),
+        }) {
+            Ok(json) => req
+                .into_response(
+                    200,
+                    Some("OK"),
+                    &[("Content-Type", "application/json; charset=utf-8")],
+                )?
+                .write_all(json.as_bytes())
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
+
+    // 获取当前屏幕内容的截图(返回png)
+    server.fn_handler("/screenshot", Method::Get, |req| {
+        match with_context(move |ctx| match ctx.display.as_ref() {
+            Some(display_manager) => Ok(Box::new(display_manager.screenshot_png()?)),
+            None => Err(anyhow!("请设置屏幕参数!")),
         }) {
             Ok(png) => req
                 .into_response(
@@ -921,25 +900,141 @@ pub fn start_http_server() -> Result<()>{
                 None => return Err(anyhow!("缺少参数key")),
             };
 
-            //删除老的图片
-            drop(ctx.image_cache.remove(&key));
+            ctx.device.upload_image(key, &data)
+        }) {
+            Ok(keys) => req
+                .into_ok_response()?
+                .write_all(format!("{keys:?}").as_bytes())
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
 
-            if ctx.image_cache.len() >= 5 {
-                return Err(anyhow!("最多缓存5张图片"));
+    // HTTP POST 设置图片的精灵图定义(JSON)，请求体为 null 时删除
+    server.fn_handler("/sprite_sheet", Method::Post, |mut req| {
+        let uri = req.uri().to_string();
+
+        let len = req.content_len().unwrap_or(0) as usize;
+        let mut err = None;
+        let mut data = if len > MAX_HTTP_PAYLOAD_LEN {
+            err = Some(format!("精灵图定义不能超过{MAX_HTTP_PAYLOAD_LEN}字节"));
+            vec![]
+        } else {
+            vec![0; len]
+        };
+
+        if let Err(e) = req.read_exact(&mut data) {
+            err = Some(format!("read sprite sheet: {e:?}"));
+        }
+
+        match with_context(move |ctx| {
+            if let Some(err) = err {
+                return Err(anyhow!("{err}"));
             }
+            let url = Url::parse(&format!("http://localhost{uri}"))?;
+            let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            let key = match params.get("key") {
+                Some(v) => v.to_string(),
+                None => return Err(anyhow!("缺少参数key")),
+            };
+            let sheet: Option<SpriteSheet> = serde_json::from_slice(&data)?;
+            ctx.device.set_sprite_sheet(&key, sheet)
+        }) {
+            Ok(()) => req
+                .into_ok_response()?
+                .write_all(b"OK")
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
 
-            let mime = mimetype::detect(&data);
-            if mime.extension.ends_with("jpg") || mime.extension.ends_with("jpeg") {
-                //rgb565转rgb
-                let rgb = decode_jpg_to_rgb(data)?;
-                ctx.image_cache.insert(key, ImageCache::RgbImage(rgb));
-            } else {
-                let rgba = Box::new(image::load_from_memory(&data)?.to_rgba8());
-                ctx.image_cache.insert(key, ImageCache::RgbaImage(rgba));
+    // HTTP POST 上传并缓存一个字体(TTF/OTF)
+    server.fn_handler("/upload_font", Method::Post, |mut req| {
+        let uri = req.uri().to_string();
+
+        let len = req.content_len().unwrap_or(0) as usize;
+        let mut err = None;
+        let mut data = if len > MAX_HTTP_PAYLOAD_LEN {
+            err = Some(format!("http请求体不能超过{MAX_HTTP_PAYLOAD_LEN}字节"));
+            vec![]
+        } else {
+            vec![0; len]
+        };
+
+        if let Err(e) = req.read_exact(&mut data) {
+            err = Some(format!("http请求体读取失败{e:?}"));
+        }
+
+        match with_context(move |ctx| {
+            if let Some(err) = err {
+                return Err(anyhow!("{err}"));
+            }
+            let url = Url::parse(&format!("http://localhost{uri}"))?;
+            let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            let key = match params.get("key") {
+                Some(v) => v.to_string(),
+                None => return Err(anyhow!("缺少参数key")),
             };
+            ctx.device.upload_font(key, data)
+        }) {
+            Ok(keys) => req
+                .into_ok_response()?
+                .write_all(format!("{keys:?}").as_bytes())
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
 
-            let keys: Vec<String> = ctx.image_cache.keys().map(|k| k.to_string()).collect();
-            Ok(keys)
+    // 列出缓存的字体
+    server.fn_handler("/list_fonts", Method::Get, |req| {
+        match with_context(move |ctx| Ok(ctx.device.font_keys())) {
+            Ok(keys) => req
+                .into_ok_response()?
+                .write_all(format!("{keys:?}").as_bytes())
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
+
+    // 删除缓存的字体
+    server.fn_handler("/delete_font", Method::Get, |req| {
+        let uri = req.uri().to_string();
+        match with_context(move |ctx| {
+            let url = Url::parse(&format!("http://localhost{uri}"))?;
+            let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            let key = match params.get("key") {
+                Some(v) => v,
+                None => return Err(anyhow!("缺少参数key")),
+            };
+            Ok(ctx.device.delete_font(key))
         }) {
             Ok(keys) => req
                 .into_ok_response()?
@@ -986,6 +1081,150 @@ pub fn start_http_server() -> Result<()>{
         },
     )?;
 
+    // HTTP POST 场景补丁，只重绘变化的区域
+    server.fn_handler("/scene", Method::Post, |mut req| {
+        let len = req.content_len().unwrap_or(0) as usize;
+        let mut err = None;
+        let mut data = if len > MAX_HTTP_PAYLOAD_LEN {
+            err = Some(format!("http请求体不能超过{MAX_HTTP_PAYLOAD_LEN}字节"));
+            vec![]
+        } else {
+            vec![0; len]
+        };
+
+        if let Err(e) = req.read_exact(&mut data) {
+            err = Some(format!("http请求体读取失败{e:?}"));
+        }
+
+        match with_context(move |ctx| {
+            if let Some(err) = err {
+                return Err(anyhow!("{err}"));
+            }
+            let patches: Vec<ScenePatch> = serde_json::from_slice(&data)
+                .map_err(|err| anyhow!("parse scene patches {err:?}"))?;
+            let display_manager = ctx.display.as_mut().ok_or(anyhow!("请设置屏幕参数!"))?;
+            ctx.device.update_scene(display_manager, patches)?;
+            Ok(ctx.device.scene.ids())
+        }) {
+            Ok(ids) => req
+                .into_ok_response()?
+                .write_all(format!("{ids:?}").as_bytes())
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
+
+    // HTTP POST 保存模板(带{{变量}}的元素列表JSON)，请求体为 null 时删除
+    server.fn_handler("/template", Method::Post, |mut req| {
+        let uri = req.uri().to_string();
+
+        let len = req.content_len().unwrap_or(0) as usize;
+        let mut err = None;
+        let mut data = if len > MAX_HTTP_PAYLOAD_LEN {
+            err = Some(format!("http请求体不能超过{MAX_HTTP_PAYLOAD_LEN}字节"));
+            vec![]
+        } else {
+            vec![0; len]
+        };
+
+        if let Err(e) = req.read_exact(&mut data) {
+            err = Some(format!("read template: {e:?}"));
+        }
+
+        match with_context(move |ctx| {
+            if let Some(err) = err {
+                return Err(anyhow!("{err}"));
+            }
+            let url = Url::parse(&format!("http://localhost{uri}"))?;
+            let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            let key = match params.get("key") {
+                Some(v) => v.to_string(),
+                None => return Err(anyhow!("缺少参数key")),
+            };
+            let elements: Option<Vec<serde_json::Value>> = serde_json::from_slice(&data)
+                .map_err(|err| anyhow!("parse template {err:?}"))?;
+            ctx.device.templates.set(&key, elements)?;
+            Ok(ctx.device.templates.names())
+        }) {
+            Ok(keys) => req
+                .into_ok_response()?
+                .write_all(format!("{keys:?}").as_bytes())
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
+
+    // 列出保存的模板
+    server.fn_handler("/list_templates", Method::Get, |req| {
+        match with_context(move |ctx| Ok(ctx.device.templates.names())) {
+            Ok(keys) => req
+                .into_ok_response()?
+                .write_all(format!("{keys:?}").as_bytes())
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
+
+    // HTTP POST 绘制模板 {"template":"xxx","vars":{...}}
+    server.fn_handler("/render", Method::Post, |mut req| {
+        let len = req.content_len().unwrap_or(0) as usize;
+        let mut err = None;
+        let mut data = if len > MAX_HTTP_PAYLOAD_LEN {
+            err = Some(format!("http请求体不能超过{MAX_HTTP_PAYLOAD_LEN}字节"));
+            vec![]
+        } else {
+            vec![0; len]
+        };
+
+        if let Err(e) = req.read_exact(&mut data) {
+            err = Some(format!("http请求体读取失败{e:?}"));
+        }
+
+        match with_context(move |ctx| {
+            if let Some(err) = err {
+                return Err(anyhow!("{err}"));
+            }
+            let params: RenderTemplate = serde_json::from_slice(&data)
+                .map_err(|err| anyhow!("parse render params {err:?}"))?;
+            let display_manager = ctx.display.as_mut().ok_or(anyhow!("请设置屏幕参数!"))?;
+            ctx.device.render_template(display_manager, &params)
+        }) {
+            Ok(()) => req
+                .into_ok_response()?
+                .write_all(b"OK")
+                .map(|_| ()),
+            Err(err) => req
+                .into_response(
+                    200,
+                    Some("Error"),
+                    &[("Content-Type", "text/plain; charset=utf-8")],
+                )?
+                .write_all(format!("{err:?}").as_bytes())
+                .map(|_| ()),
+        }
+    })?;
+
     // HTTP POST 绘制GIF/png/jpg图片
     server.fn_handler(
         "/draw_image",
@@ -1076,9 +1315,7 @@ pub fn start_http_server() -> Result<()>{
                 info!("New WebSocket session... (free_heap: {} bytes)", free_heap);
                 
                 // 新连接时重置帧差分解码器，等待客户端发送关键帧
-                if let Ok(mut decoder) = DELTA_DECODER.lock() {
-                    decoder.reset();
-                }
+                ctx.device.reset_delta_decoder();
                 
                 // 内存低时拒绝新连接
                 if free_heap < MIN_SAFE_HEAP {
@@ -1092,9 +1329,7 @@ pub fn start_http_server() -> Result<()>{
                 return Ok(());
             } else if ws.is_closed() {
                 // 连接关闭时也重置解码器
-                if let Ok(mut decoder) = DELTA_DECODER.lock() {
-                    decoder.reset();
-                }
+                ctx.device.reset_delta_decoder();
                 return Ok(());
             }
     
@@ -1138,7 +1373,13 @@ pub fn start_http_server() -> Result<()>{
                     let data_len = data.len();
                     
                     let json = unsafe{ str::from_boxed_utf8_unchecked(data.into()) };
-                    if let Err(err) = draw_json_elements(ctx, &*json) {
+                    // JSON对象为与MQTT相同格式的消息（如场景补丁 {"Scene":[...]}），数组为绘制元素列表
+                    let result = if json.trim_start().starts_with('{') {
+                        handle_mqtt_message(ctx, Box::new(json.to_string()))
+                    } else {
+                        draw_json_elements(ctx, &*json)
+                    };
+                    if let Err(err) = result {
                         info!("draw json error:{err:?}");
                         let _ = ws.send(
                             FrameType::Text(false),
@@ -1151,140 +1392,15 @@ pub fn start_http_server() -> Result<()>{
                     }
                 }
                 FrameType::Binary(_) => {
-                    // Check for echo test prefix - echo back the data
-                    const ECHO_TEST_PREFIX: &[u8] = b"ECHO_TEST:";
-                    if data.starts_with(ECHO_TEST_PREFIX) {
-                        let payload = &data[ECHO_TEST_PREFIX.len()..];
-                        // Echo back the payload as binary
-                        let _ = ws.send(FrameType::Binary(false), payload);
-                        return Ok(());
-                    }
-                    
-                    // Check for speed test prefix (legacy)
-                    const SPEED_TEST_PREFIX: &[u8] = b"SPEED_TEST:";
-                    if data.starts_with(SPEED_TEST_PREFIX) {
-                        let payload_len = data.len() - SPEED_TEST_PREFIX.len();
-                        let result = format!("OK:{} bytes", payload_len);
-                        let _ = ws.send(FrameType::Text(false), result.as_bytes());
-                        return Ok(());
-                    }
-                    
-                    //判断图片类型
-                    let mime = mimetype::detect(data.as_ref());
-                    // info!("mime:{mime:?}");
-                    match ctx.display.as_mut() {
-                        None => error!("Display not configured!"),
-                        Some(display_manager) => {
-                            // info!("mime:{mime:?}");
-                            if mime.extension.ends_with("jpg") || mime.extension.ends_with("jpeg") {
-                                if let Ok(rgb565_data) = canvas::decode_jpeg_to_rgb565(&data) {
-                                    let _ = display::draw_rgb565_fast(display_manager, 0, 0, rgb565_data.0, rgb565_data.1, &rgb565_data.2);
-                                } else {
-                                    error!("jpg decode error!");
-                                }
-                            } else if mime.extension.ends_with("gif") || mime.extension.ends_with("png") {
-                                if let Ok(image) = image::load_from_memory(&data){
-                                    let image = image.to_rgb8();
-                                    let _ = display::draw_rgb_image_fast(display_manager, 0, 0, &image);
-                                }else{
-                                    error!("image decode error!");
-                                }
-                            }else {
-                                if data.as_ref().starts_with(b"RGB565"){
-                                    // 未压缩的RGB565数据(带RGB565前缀)
-                                    let rgb565 = &data.as_ref()[6..];
-                                    display::draw_rgb565_u8array_fast(display_manager, 0, 0, display_manager.get_screen_width(), display_manager.get_screen_height(), rgb565)?;
-                                } else if data.as_ref().starts_with(WIFI_NOP_MAGIC) {
-                                    // 无变化帧：画面静止，跳过解码和绘制，直接返回ACK
-                                    // 这样上位机可以立即发送下一帧，大幅提升静止画面的响应速度
-                                    let _ = ws.send(FrameType::Text(false), b"ACK");
-                                } else if data.as_ref().starts_with(WIFI_KEY_MAGIC) || data.as_ref().starts_with(WIFI_DLT_MAGIC) {
-                                    // WiFi帧差分协议处理 (带ACK确认机制)
-                                    let is_key_frame = data.as_ref().starts_with(WIFI_KEY_MAGIC);
-                                    let frame_type = if is_key_frame { "KEY" } else { "DLT" };
-                                    
-                                    if data.len() >= 12 {
-                                        let width = u16::from_be_bytes([data[8], data[9]]);
-                                        let height = u16::from_be_bytes([data[10], data[11]]);
-                                        let lz4_data = &data[12..];
-                                        
-                                        if let Ok(mut decoder) = DELTA_DECODER.lock() {
-                                            // 差分帧但没有参考帧时，等待关键帧
-                                            if !is_key_frame && !decoder.has_reference_frame() {
-                                                decoder.log_error("waiting for key frame");
-                                                // 发送NACK让客户端发送关键帧
-                                                let _ = ws.send(FrameType::Text(false), b"NACK");
-                                            } else {
-                                                // 解码计时
-                                                let decode_start = Instant::now();
-                                                
-                                                // 使用带计时的解码函数
-                                                let (decode_result, lz4_ms, xor_ms) = if is_key_frame {
-                                                    match decoder.decode_key_frame(lz4_data) {
-                                                        Ok(data) => (Ok(data), 0u128, 0u128),
-                                                        Err(e) => (Err(e), 0, 0),
-                                                    }
-                                                } else {
-                                                    match decoder.decode_delta_frame_timed(lz4_data) {
-                                                        Ok((data, lz4, xor)) => (Ok(data), lz4, xor),
-                                                        Err(e) => (Err(e), 0, 0),
-                                                    }
-                                                };
-                                                
-                                                let decode_ms = decode_start.elapsed().as_millis();
-                                                
-                                                match decode_result {
-                                                    Ok(rgb565) => {
-                                                        let expected_size = width as usize * height as usize * 2;
-                                                        if rgb565.len() >= expected_size {
-                                                            // 绘制计时
-                                                            let draw_start = Instant::now();
-                                                            let _ = display::draw_rgb565_u8array_fast(
-                                                                display_manager, 0, 0, width, height, 
-                                                                &rgb565[0..expected_size]
-                                                            );
-                                                            let draw_ms = draw_start.elapsed().as_millis();
-                                                            
-                                                            // 打印性能信息 (包含lz4和xor细分)
-                                                            // if is_key_frame {
-                                                            //     info!("[WIFI_FRAME] type={} {}x{} compressed={}bytes decode={}ms draw={}ms total={}ms",
-                                                            //         frame_type, width, height, lz4_data.len(),
-                                                            //         decode_ms, draw_ms, decode_ms + draw_ms);
-                                                            // } else {
-                                                            //     info!("[WIFI_FRAME] type={} {}x{} compressed={}bytes decode={}ms(lz4={}ms,xor={}ms) draw={}ms total={}ms",
-                                                            //         frame_type, width, height, lz4_data.len(),
-                                                            //         decode_ms, lz4_ms, xor_ms, draw_ms, decode_ms + draw_ms);
-                                                            // }
-                                                            
-                                                            // 发送ACK确认，客户端收到后才发送下一帧
-                                                            let _ = ws.send(FrameType::Text(false), b"ACK");
-                                                        }
-                                                    }
-                                                    Err(e) => {
-                                                        decoder.log_error(e);
-                                                        decoder.reset();
-                                                        // 发送NACK让客户端发送关键帧
-                                                        let _ = ws.send(FrameType::Text(false), b"NACK");
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    // 兼容旧协议: lz4压缩数据
-                                    match lz4_flex::decompress_size_prepended(&data){
-                                        Ok(rgb565) => {
-                                            let rgb565 = &rgb565[0..display_manager.get_screen_width() as usize
-                                            * display_manager.get_screen_height() as usize * 2];
-                                            display::draw_rgb565_u8array_fast(display_manager, 0, 0, display_manager.get_screen_width(), display_manager.get_screen_height(), rgb565)?;
-                                        }
-                                        Err(err) => {
-                                            error!("lz4 decode:{err:?}");
-                                        }
-                                    }
-                                }
-                            }
+                    // 测试、上传字体、图片和RGB565帧，帧差分协议回复ACK/NACK
+                    match ctx.device.handle_ws_binary(ctx.display.as_mut(), data) {
+                        Some(WsReply::Text(text)) => {
+                            let _ = ws.send(FrameType::Text(false), text.as_bytes());
+                        }
+                        Some(WsReply::Binary(data)) => {
+                            let _ = ws.send(FrameType::Binary(false), &data);
                         }
+                        None => (),
                     }
                 }
                 FrameType::Ping => {
@@ -1414,8 +1530,8 @@ fn handle_draw_canvas(
         // 由于优化了直接绘制路径，不再需要450KB的画布内存
         len + 150 * 1024
     } else {
-        // 小请求：需要画布内存（取决于屏幕大小）+ 100KB安全余量
-        200 * 1024
+        // 小请求：分条渲染只需要条带缓冲（默认约32KB RGB888 + 21KB RGB565）+ 100KB安全余量
+        160 * 1024
     };
     
     if free_heap < min_required {
@@ -1447,14 +1563,7 @@ pub fn draw_json_elements(ctx: &mut Context, json: &str) -> Result<()> {
         None => return Err(anyhow!("请设置屏幕参数!")),
         Some(v) => v,
     };
-
-    let elements: Box<Vec<Element>> = Box::new(serde_json::from_str(json)
-        .map_err(|err| anyhow!("parse elements {err:?} json:`{json}`"))?);
-    // info!("Elements:{}", elements.len());
-
-    draw_elements(display_manager, &ctx.image_cache, &elements)
-        .map_err(|err| anyhow!("draw elements: {err:?}"))?;
-    Ok(())
+    ctx.device.draw_json_elements(display_manager, json)
 }
 
 fn handle_display_image(
@@ -1469,29 +1578,15 @@ fn handle_display_image(
     let mut data = Box::new(vec![0; len]);
     req.read_exact(&mut data)?;
     let recv_ms = t1.elapsed().as_millis();
-    // info!("handle_display_image recv {}ms", t1.elapsed().as_millis());
     let t1 = Instant::now();
 
-    let mime = mimetype::detect(&data);
     let display_manager = match ctx.display.as_mut() {
         None => return Err(anyhow!("display not init!")),
         Some(v) => v,
     };
-    if mime.extension.ends_with("jpg") || mime.extension.ends_with("jpeg") {
-        let (w, h, data) = canvas::decode_jpeg_to_rgb565(&data)?;
-        let decode_ms = t1.elapsed().as_millis();
-        let t1 = Instant::now();
-        display::draw_rgb565_fast(display_manager, 0, 0, w, h, &data)?;
-        let draw_ms = t1.elapsed().as_millis();
-        Ok((w, h, format!("recv:{recv_ms}ms, decode:{decode_ms}ms, draw:{draw_ms}ms")))
-    } else {
-        let image = image::load_from_memory(&data)?.to_rgb8();
-        let decode_ms = t1.elapsed().as_millis();
-        let t1 = Instant::now();
-        display::draw_rgb_image_fast(display_manager, 0, 0, &image)?;
-        let draw_ms = t1.elapsed().as_millis();
-        Ok((image.width() as u16, image.height() as u16, format!("recv:{recv_ms}ms, decode:{decode_ms}ms, draw:{draw_ms}ms")))
-    }
+    let (w, h) = ctx.device.draw_image(display_manager, &data)?;
+    let draw_ms = t1.elapsed().as_millis();
+    Ok((w, h, format!("recv:{recv_ms}ms, decode+draw:{draw_ms}ms")))
 }
 
 fn handle_display_rotation(
@@ -1560,6 +1655,12 @@ fn handle_display_rotation(
                 .map_err(|e| anyhow!("ST7796 set_orientation failed: {:?}", e))?;
             }
         }
+
+        // 旋转后宽高可能对调，截图缓冲区按新的尺寸重新创建
+        if display_manager.shadow.is_some() {
+            let (w, h) = display_manager.get_screen_size();
+            display_manager.shadow = Some(FrameBuffer::new(w, h));
+        }
     }
     
     // 保存到NVS
@@ -1720,13 +1821,10 @@ fn handle_display_rgb565(
         Some(v) => v,
     };
 
-    let rgb565 = &data[0..display_manager.get_screen_width() as usize
-    * display_manager.get_screen_height() as usize * 2];
-
     let t1 = Instant::now();
-    display::draw_rgb565_u8array_fast(display_manager, 0, 0, display_manager.get_screen_width(), display_manager.get_screen_height(), &rgb565)?;
+    let (w, h) = ctx.device.draw_rgb565_fullscreen(display_manager, &data)?;
     let draw_ms = t1.elapsed().as_millis();
-    Ok((display_manager.get_screen_width(), display_manager.get_screen_height(), format!("recv:{len}bytes {recv_ms}ms, draw:{draw_ms}ms")))
+    Ok((w, h, format!("recv:{len}bytes {recv_ms}ms, draw:{draw_ms}ms")))
 }
 
 fn handle_color_adjust(
@@ -1904,21 +2002,13 @@ fn handle_display_rgb565_lz4(
     let recv_ms = t1.elapsed().as_millis();
     let t1 = Instant::now();
 
-    let rgb565 = lz4_flex::decompress_size_prepended(&data)?;
-
     let display_manager = match ctx.display.as_mut() {
         None => return Err(anyhow!("display not init!")),
         Some(v) => v,
     };
-
-    let rgb565 = &rgb565[0..display_manager.get_screen_width() as usize
-    * display_manager.get_screen_height() as usize * 2];
-
-    let decode_ms = t1.elapsed().as_millis();
-    let t1 = Instant::now();
-    display::draw_rgb565_u8array_fast(display_manager, 0, 0, display_manager.get_screen_width(), display_manager.get_screen_height(), &rgb565)?;
+    let (w, h) = ctx.device.draw_rgb565_lz4(display_manager, &data)?;
     let draw_ms = t1.elapsed().as_millis();
-    Ok((display_manager.get_screen_width(), display_manager.get_screen_height(), format!("recv:{len}bytes {recv_ms}ms, decode:{decode_ms}ms, draw:{draw_ms}ms")))
+    Ok((w, h, format!("recv:{len}bytes {recv_ms}ms, decode+draw:{draw_ms}ms")))
 }
 
 fn handle_display_config(
@@ -1967,6 +2057,8 @@ fn create_server() -> anyhow::Result<EspHttpServer<'static>> {
         lru_purge_enable: true,
         // Reduce session timeout for faster connection recycling (5 minutes)
         session_timeout: std::time::Duration::from_secs(5 * 60),
+        // 默认最多32个接口，新增接口时注意不要超过这个数量
+        max_uri_handlers: 48,
         ..Default::default()
     };
 
@@ -1,5 +1,5 @@
 use core::convert::TryInto;
-use std::{collections::HashMap, net::Ipv4Addr, num::NonZero, sync::Mutex, time::{Duration, Instant}};
+use std::{net::Ipv4Addr, num::NonZero, sync::Mutex, time::{Duration, Instant}};
  
 
 use anyhow::{anyhow, Result};
@@ -23,20 +23,19 @@ use esp_idf_svc::{
 use esp_idf_hal::ledc::LedcDriver;
 
 use http_server::print_memory;
-use image::{RgbImage, RgbaImage};
 use log::*;
 use std::io::Write;
 use once_cell::sync::Lazy;
+use wifi_screen_core::{device::Device, image_cache::{LruImageCache, DEFAULT_IMAGE_CACHE_BUDGET}, utils};
 use serde::Serialize;
-mod utils;
 mod canvas;
 mod config;
 mod display;
+mod gif_player;
 mod usb_reader;
-#[allow(unused)]
-mod imageproc;
 mod mqtt_client;
 mod http_server;
+mod storage;
 
 // Need lots of stack to parse JSON
 // With CONFIG_SPIRAM_ALLOW_STACK_EXTERNAL_MEMORY, stacks can use PSRAM
@@ -46,11 +45,6 @@ pub const WIFI_AP_SSID: &str = "ESP32-WiFiScreen";
 
 const MAX_HTTP_PAYLOAD_LEN: usize = 1024 * 512;
 
-pub enum ImageCache {
-    RgbImage(Box<RgbImage>),
-    RgbaImage(Box<RgbaImage>),
-}
-
 #[derive(Serialize)]
 pub struct Context {
     #[serde(skip)]
@@ -64,9 +58,11 @@ pub struct Context {
     wifi: BlockingWifi<EspWifi<'static>>,
     #[serde(skip)]
     display: Option<DisplayManager<'static>>,
-    //存放上传的图片
-    #[serde(skip)]
-    image_cache: HashMap<String, ImageCache>,
+    //图片、字体、场景、模板和GIF等与屏幕无关的状态，/status 中显示图片缓存统计和GIF播放状态
+    #[serde(flatten)]
+    device: Device,
+    //图片存储挂载失败的原因，/status 中显示
+    image_store_error: Option<String>,
     //记录最后一次访问配置页面的时间，用于防止配置期间自动重启
     //如果超过10分钟没有访问配置，则认为用户已离开，允许自动重启
     #[serde(skip)]
@@ -224,6 +220,24 @@ fn main() -> anyhow::Result<()> {
             rst: peripherals.pins.gpio8,
             bl: peripherals.pins.gpio13,
         };
+        let (image_store, image_store_error) = match storage::mount_image_store() {
+            Ok(store) => (Some(store), None),
+            Err(err) => {
+                error!("mount image store failed: {err:?}");
+                (None, Some(format!("{err}")))
+            }
+        };
+        let budget = match config.display_config.as_ref().map(|c| c.image_cache_kb) {
+            Some(kb) if kb > 0 => kb as usize * 1024,
+            _ => DEFAULT_IMAGE_CACHE_BUDGET,
+        };
+        let mut image_cache = LruImageCache::new(budget);
+        //恢复固定的图片，绘制时加载后不会被淘汰
+        if let Some(store) = image_store.as_ref() {
+            for key in store.pinned().unwrap_or_default() {
+                image_cache.set_pinned(&key, true);
+            }
+        }
         let mut ctx = CONTEXT.lock().map_err(|err| anyhow!("{err:?}"))?;
         ctx.replace(Box::new(Context {
             display: None,
@@ -233,7 +247,8 @@ fn main() -> anyhow::Result<()> {
             free_heap: 0,
             free_internal_heap: 0,
             wifi,
-            image_cache: HashMap::new(),
+            device: Device::new(image_cache, image_store),
+            image_store_error,
             last_config_time: None,
             backlight_driver: None,
         }));
@@ -349,7 +364,7 @@ fn main() -> anyhow::Result<()> {
             // flush immediately to ensure host receives them
             let l = line.trim_end().to_string();
             if l.starts_with("ESP32-WIFI-SCREEN") || l.starts_with("SPEEDRESULT") || 
-               l.starts_with("BOOTED") || l.starts_with("READY") {
+               l.starts_with("BOOTED") || l.starts_with("READY") || l.starts_with("SCREENSHOT") {
                 let _ = out.flush();
             }
             
@@ -363,7 +378,8 @@ fn main() -> anyhow::Result<()> {
                       l.starts_with("DECOMPRESSED") || l.starts_with("FRAME_START") || 
                       l.starts_with("FRAME_END") || l.starts_with("BUSY") || 
                       l.starts_with("SPEEDCANCELLED") || l.starts_with("SPEEDTIMEOUT") ||
-                      l.starts_with("ESP32-WIFI-SCREEN") || l.starts_with("BOOTED") {
+                      l.starts_with("ESP32-WIFI-SCREEN") || l.starts_with("BOOTED") ||
+                      l.starts_with("SCREENSHOT") {
                 // these are protocol messages already written to stdout; don't duplicate
             } else {
                 log::info!("{}", l);
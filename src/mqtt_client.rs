@@ -1,24 +1,26 @@
 use esp_idf_svc::mqtt::client::{EspMqttClient, EspMqttEvent, EventPayload, MqttClientConfiguration};
 
 use log::{error, info};
-use serde::Deserialize;
 
 use std::str;
+use std::sync::mpsc::{channel, Sender};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
 
-use crate::canvas::{decode_jpg_to_rgb, draw_elements, Element};
-use crate::utils::decode_base64;
-use crate::{with_context, Context, ImageCache};
-
-///接收到的mqtt消息
-#[derive(Clone, Deserialize)]
-pub enum TextMessage{
-    //绘制消息
-    Draw(Vec<Element>),
-    //上传图片消息 (key, base64文件数据)
-    Upload((String, String))
+use crate::gif_player::start_player;
+use crate::{with_context, Context};
+
+/// 待发布的消息 (topic, 数据)，由mqtt线程发送
+static PUBLISH_SENDER: Lazy<Mutex<Option<Sender<(String, Vec<u8>)>>>> = Lazy::new(|| Mutex::new(None));
+
+fn publish(topic: String, data: Vec<u8>) -> Result<()> {
+    let sender = PUBLISH_SENDER.lock().map_err(|err| anyhow!("{err:?}"))?;
+    match sender.as_ref() {
+        Some(sender) => sender.send((topic, data)).map_err(|err| anyhow!("mqtt publish: {err:?}")),
+        None => Err(anyhow!("mqtt未启动")),
+    }
 }
 
 pub fn listen_config() -> Result<()> {
@@ -68,6 +70,9 @@ pub fn listen_config() -> Result<()> {
 
     info!("mqtt client created...");
 
+    let (publish_sender, publish_receiver) = channel::<(String, Vec<u8>)>();
+    PUBLISH_SENDER.lock().map_err(|err| anyhow!("{err:?}"))?.replace(publish_sender);
+
     std::thread::spawn(move || {
         let mut topic_subscribe_ok = false;
 
@@ -103,14 +108,13 @@ pub fn listen_config() -> Result<()> {
             // let payload = "Hello from esp-mqtt-demo!";
 
             loop {
-                // client.enqueue(topic, QoS::AtMostOnce, false, payload.as_bytes())?;
-
-                // info!("Published \"{payload}\" to topic \"{topic}\"");
-
-                let sleep_secs = 2;
-
-                // info!("Now sleeping for {sleep_secs}s...");
-                std::thread::sleep(Duration::from_secs(sleep_secs));
+                // 发布截图等回复消息
+                if let Ok((publish_topic, data)) = publish_receiver.recv_timeout(Duration::from_secs(2)) {
+                    match client.enqueue(&publish_topic, config.mqtt_qos.clone(), false, &data) {
+                        Ok(_) => info!("mqtt published topic:{publish_topic} len:{}", data.len()),
+                        Err(err) => error!("mqtt publish fail:{err:?}"),
+                    }
+                }
             }
         }
     });
@@ -188,36 +192,17 @@ fn parse_event<'a>(event: &EspMqttEvent<'a>, text_cache:Arc<Mutex<Box<String>>>)
     Ok(())
 }
 
+/// 处理 JSON 格式的消息，格式见 wifi_screen_core::device::TextMessage
 pub fn handle_mqtt_message(ctx: &mut Context, json: Box<String>) -> Result<()> {
     let display_manager = match ctx.display.as_mut() {
         None => return Err(anyhow!("请设置屏幕参数!")),
         Some(v) => v,
     };
-    let msg: Box<TextMessage> = Box::new(serde_json::from_str(&json)
-        .map_err(|err| anyhow!("parse message {err:?} json:`{json}`"))?);
-
-    match msg.as_ref(){
-        TextMessage::Draw(elements) => {
-            draw_elements(display_manager, &ctx.image_cache, &elements)
-                .map_err(|err| anyhow!("draw elements: {err:?}"))?;
-        }
-        TextMessage::Upload((key, base64)) => {
-            //删除老的图片
-            drop(ctx.image_cache.remove(key));
-            if ctx.image_cache.len() >= 5 {
-                return Err(anyhow!("最多缓存5张图片"));
-            }
-            let data = decode_base64(&base64)?;
-            let mime = mimetype::detect(&data);
-            if mime.extension.ends_with("jpg") || mime.extension.ends_with("jpeg") {
-                //rgb565转rgb
-                let rgb = decode_jpg_to_rgb(data)?;
-                ctx.image_cache.insert(key.to_string(), ImageCache::RgbImage(rgb));
-            } else {
-                let rgba = Box::new(image::load_from_memory(&data)?.to_rgba8());
-                ctx.image_cache.insert(key.to_string(), ImageCache::RgbaImage(rgba));
-            };
-        }
+    let screenshot = ctx.device.handle_text_message(display_manager, &json)?;
+    //PlayGif 消息需要启动播放线程
+    start_player(ctx)?;
+    if let Some((topic, png)) = screenshot {
+        publish(topic, png)?;
     }
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,49 @@
+use std::ffi::CString;
+
+use anyhow::{anyhow, Result};
+use esp_idf_svc::sys::{
+    esp, esp_partition_find_first, esp_partition_subtype_t_ESP_PARTITION_SUBTYPE_DATA_SPIFFS,
+    esp_partition_type_t_ESP_PARTITION_TYPE_DATA, esp_spiffs_info, esp_vfs_spiffs_conf_t, esp_vfs_spiffs_register,
+};
+use log::info;
+use wifi_screen_core::image_store::ImageStore;
+
+// flash 文件系统：partitions.csv 中的 storage 分区(SPIFFS)，用于保存上传的图片
+
+const BASE_PATH: &str = "/spiffs";
+const PARTITION_LABEL: &str = "storage";
+
+/// 挂载 SPIFFS 分区，第一次挂载失败时会自动格式化
+/// 旧版本的分区表没有 storage 分区，此时返回错误，图片只保存在内存中
+pub fn mount_image_store() -> Result<ImageStore> {
+    let label = CString::new(PARTITION_LABEL)?;
+    let partition = unsafe {
+        esp_partition_find_first(
+            esp_partition_type_t_ESP_PARTITION_TYPE_DATA,
+            esp_partition_subtype_t_ESP_PARTITION_SUBTYPE_DATA_SPIFFS,
+            label.as_ptr(),
+        )
+    };
+    if partition.is_null() {
+        return Err(anyhow!("没有找到{PARTITION_LABEL}分区，请使用新的分区表重新烧录整个flash"));
+    }
+
+    // esp_vfs_spiffs_register 会保存这两个指针，所以不能释放
+    let base_path = CString::new(BASE_PATH)?.into_raw();
+    let label = label.into_raw();
+    let conf = esp_vfs_spiffs_conf_t {
+        base_path,
+        partition_label: label,
+        max_files: 4,
+        format_if_mount_failed: true,
+    };
+    esp!(unsafe { esp_vfs_spiffs_register(&conf) })?;
+
+    let (mut total, mut used) = (0usize, 0usize);
+    esp!(unsafe { esp_spiffs_info(label, &mut total, &mut used) })?;
+    info!("spiffs mounted: total={total} used={used}");
+
+    // SPIFFS 需要预留部分空间用于垃圾回收，文件总大小不能用满整个分区
+    let capacity = total as u64 * 3 / 4;
+    Ok(ImageStore::new(BASE_PATH, capacity))
+}
@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ab_glyph::FontRef;
+use anyhow::{anyhow, Result};
+use image::RgbImage;
+use log::{error, warn};
+
+use wifi_screen_core::device::{Device, Screen};
+use wifi_screen_core::framebuffer::FrameBuffer;
+use wifi_screen_core::image_cache::LruImageCache;
+use wifi_screen_core::image_store::ImageStore;
+
+/// 模拟屏幕，对应固件中的 DisplayManager
+pub struct FrameBufferScreen {
+    pub framebuffer: FrameBuffer,
+    pub font: FontRef<'static>,
+    /// 每次绘制后将屏幕内容写入此PNG文件
+    pub png_path: Option<PathBuf>,
+    /// 对应 DisplayConfig.render_band_height，0 表示自动
+    pub render_band_height: u16,
+}
+
+impl Screen for FrameBufferScreen {
+    fn size(&self) -> (u16, u16) {
+        self.framebuffer.get_screen_size()
+    }
+
+    fn font(&self) -> FontRef<'static> {
+        self.font.clone()
+    }
+
+    fn render_band_height(&self) -> u16 {
+        self.render_band_height
+    }
+
+    fn draw_rgb565(&mut self, x: u16, y: u16, width: u16, height: u16, pixels: &[u16]) -> Result<()> {
+        self.framebuffer.draw_rgb565(x, y, width, height, pixels)
+    }
+
+    fn draw_rgb565_u8array(&mut self, x: u16, y: u16, width: u16, height: u16, pixels: &[u8]) -> Result<()> {
+        self.framebuffer.draw_rgb565_u8array(x, y, width, height, pixels)
+    }
+
+    fn draw_rgb_image(&mut self, x: u16, y: u16, image: &RgbImage) -> Result<()> {
+        self.framebuffer.draw_rgb_image(x, y, image)
+    }
+
+    fn screenshot_png(&self) -> Result<Vec<u8>> {
+        self.framebuffer.to_png()
+    }
+
+    /// 屏幕内容更新后写出PNG文件
+    fn flush(&mut self) {
+        if let Some(path) = self.png_path.as_ref() {
+            match self.framebuffer.to_png() {
+                Ok(png) => {
+                    if let Err(err) = std::fs::write(path, png) {
+                        warn!("write {path:?}: {err:?}");
+                    }
+                }
+                Err(err) => warn!("encode png: {err:?}"),
+            }
+        }
+    }
+}
+
+/// 模拟设备，对应固件中的 Context，消息处理使用 wifi-screen-core 中与固件相同的代码
+pub struct Emulator {
+    pub screen: FrameBufferScreen,
+    pub device: Device,
+}
+
+pub type SharedEmulator = Arc<Mutex<Emulator>>;
+
+impl Emulator {
+    pub fn new(width: u16, height: u16, png_path: Option<PathBuf>, image_store: ImageStore) -> Result<Self> {
+        let font = FontRef::try_from_slice(include_bytes!("../../../VonwaonBitmap-12pxLite.otf"))
+            .map_err(|err| anyhow!("load font: {err:?}"))?;
+        let mut image_cache = LruImageCache::default();
+        for key in image_store.pinned()? {
+            image_cache.set_pinned(&key, true);
+        }
+        Ok(Self {
+            screen: FrameBufferScreen { framebuffer: FrameBuffer::new(width, height), font, png_path, render_band_height: 0 },
+            device: Device::new(image_cache, Some(image_store)),
+        })
+    }
+}
+
+/// 对应固件的 with_context
+pub fn with_device<F, T>(emulator: &SharedEmulator, f: F) -> Result<T>
+where
+    F: FnOnce(&mut Device, &mut FrameBufferScreen) -> Result<T>,
+{
+    let mut emulator = emulator.lock().map_err(|err| anyhow!("{err:?}"))?;
+    let Emulator { screen, device } = &mut *emulator;
+    f(device, screen)
+}
+
+/// GIF 播放线程，对应 gif_player::player_loop（模拟器中常驻运行，没有动画时空闲等待）
+pub fn start_gif_player(emulator: SharedEmulator) {
+    std::thread::spawn(move || loop {
+        let start = Instant::now();
+        let delay = match with_device(&emulator, |device, screen| device.draw_gif_frame(screen)) {
+            Ok(Some(delay)) => delay,
+            Ok(None) => Duration::from_millis(20),
+            Err(err) => {
+                error!("gif player: {err:?}");
+                let _ = with_device(&emulator, |device, _| {
+                    device.stop_gif();
+                    Ok(())
+                });
+                Duration::from_millis(20)
+            }
+        };
+        std::thread::sleep(delay.saturating_sub(start.elapsed()));
+    });
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// 测试用的 32x16 模拟设备，图片保存在临时目录中
+    pub(crate) fn emulator(name: &str) -> SharedEmulator {
+        let dir = std::env::temp_dir().join(format!("wifi-screen-emulator-test-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Arc::new(Mutex::new(Emulator::new(32, 16, None, ImageStore::new(dir, 64 * 1024)).unwrap()))
+    }
+
+    pub(crate) fn pixel(emulator: &SharedEmulator, x: u32, y: u32) -> [u8; 3] {
+        emulator.lock().unwrap().screen.framebuffer.to_rgb_image().get_pixel(x, y).0
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message, WebSocket};
+use url::Url;
+use wifi_screen_core::gif_player::PlayGif;
+
+use wifi_screen_core::device::{Screen, WsReply};
+
+use crate::device::{with_device, SharedEmulator};
+
+const MAX_HTTP_PAYLOAD_LEN: usize = 1024 * 512;
+
+/// 启动HTTP服务器（阻塞），接口与固件 http_server.rs 保持一致
+pub fn start_http_server(addr: &str, emulator: SharedEmulator) -> Result<()> {
+    let server = Server::http(addr).map_err(|err| anyhow!("http server {addr}: {err:?}"))?;
+    info!("HTTP server: http://{addr}");
+    serve(server, emulator);
+    Ok(())
+}
+
+fn serve(server: Server, emulator: SharedEmulator) {
+    for req in server.incoming_requests() {
+        let emulator = emulator.clone();
+        // WebSocket 连接是长连接，每个请求单独一个线程
+        std::thread::spawn(move || {
+            if let Err(err) = handle_request(req, emulator) {
+                error!("http request: {err:?}");
+            }
+        });
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn text_response(text: impl Into<String>) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(text).with_header(header("Content-Type", "text/plain; charset=utf-8"))
+}
+
+fn query_params(req: &Request) -> Result<HashMap<String, String>> {
+    let url = Url::parse(&format!("http://localhost{}", req.url()))?;
+    Ok(url.query_pairs().into_owned().collect())
+}
+
+fn query_key(req: &Request) -> Result<String> {
+    query_params(req)?.remove("key").ok_or(anyhow!("缺少参数key"))
+}
+
+fn read_body(req: &mut Request) -> Result<Vec<u8>> {
+    let len = req.body_length().unwrap_or(0);
+    if len > MAX_HTTP_PAYLOAD_LEN {
+        return Err(anyhow!("http请求体不能超过{MAX_HTTP_PAYLOAD_LEN}字节"));
+    }
+    let mut data = Vec::with_capacity(len);
+    req.as_reader().read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn handle_request(mut req: Request, emulator: SharedEmulator) -> Result<()> {
+    let path = req.url().split('?').next().unwrap_or("/").to_string();
+    let method = req.method().clone();
+
+    if path == "/ws" {
+        return handle_websocket(req, emulator);
+    }
+
+    let result: Result<Response<std::io::Cursor<Vec<u8>>>> = match (&method, path.as_str()) {
+        (Method::Get, "/") => Ok(Response::from_data(include_bytes!("../../../html/index.html").to_vec())
+            .with_header(header("Content-Type", "text/html; charset=utf-8"))),
+        (Method::Get, "/example") => Ok(Response::from_data(include_bytes!("../../../html/example.html").to_vec())
+            .with_header(header("Content-Type", "text/html; charset=utf-8"))),
+        (Method::Get, "/status") => with_device(&emulator, |device, _| {
+            let json = serde_json::json!({
+                "emulator": true,
+                "free_heap": 0,
+                "free_internal_heap": 0,
+                "images": device.image_keys(),
+                "image_cache": device.image_cache,
+                "font_cache": device.font_keys(),
+                "gif_player": device.gif_player,
+            });
+            Ok(Response::from_string(json.to_string())
+                .with_header(header("Content-Type", "application/json; charset=utf-8")))
+        }),
+        (Method::Get, "/display_config") => with_device(&emulator, |_, screen| {
+            let (w, h) = screen.size();
+            let json = serde_json::json!({
+                "display_type": "ST7789",
+                "width": w,
+                "height": h,
+                "rotated_width": w,
+                "rotated_height": h,
+                "rotation": "Deg0",
+                "color_adjust_r": 0,
+                "color_adjust_g": 0,
+                "color_adjust_b": 0,
+                "brightness": 100,
+                "render_band_height": screen.render_band_height,
+                "shadow_framebuffer": true,
+            });
+            Ok(Response::from_string(json.to_string())
+                .with_header(header("Content-Type", "application/json; charset=utf-8")))
+        }),
+        (Method::Get, "/framebuffer.png") | (Method::Get, "/screenshot") => with_device(&emulator, |_, screen| {
+            let png = screen.screenshot_png()?;
+            Ok(Response::from_data(png).with_header(header("Content-Type", "image/png")))
+        }),
+        (Method::Get, "/draw_canvas") => Ok(text_response("调用draw_canvas请使用Post请求！")),
+        (Method::Post, "/draw_canvas") => read_body(&mut req).and_then(|data| {
+            let json = String::from_utf8(data)?;
+            with_device(&emulator, |device, screen| device.draw_json_elements(screen, &json))?;
+            Ok(text_response("OK"))
+        }),
+        (Method::Post, "/scene") => read_body(&mut req).and_then(|data| {
+            let patches = serde_json::from_slice(&data).map_err(|err| anyhow!("parse scene patches {err:?}"))?;
+            let ids = with_device(&emulator, |device, screen| {
+                device.update_scene(screen, patches)?;
+                Ok(device.scene.ids())
+            })?;
+            Ok(text_response(format!("{ids:?}")))
+        }),
+        (Method::Post, "/upload_image") => query_key(&req).and_then(|key| {
+            let data = read_body(&mut req)?;
+            let keys = with_device(&emulator, |device, _| device.upload_image(key, &data))?;
+            Ok(text_response(format!("{keys:?}")))
+        }),
+        (Method::Get, "/delete_image") => query_key(&req).and_then(|key| {
+            let keys = with_device(&emulator, |device, _| device.delete_image(&key))?;
+            Ok(text_response(format!("{keys:?}")))
+        }),
+        (Method::Get, "/pin_image") => query_params(&req).and_then(|mut params| {
+            let key = params.remove("key").ok_or(anyhow!("缺少参数key"))?;
+            let pin = !matches!(params.get("pin").map(String::as_str), Some("false" | "0"));
+            let keys = with_device(&emulator, |device, _| device.pin_image(&key, pin))?;
+            Ok(text_response(format!("{keys:?}")))
+        }),
+        (Method::Post, "/sprite_sheet") => query_key(&req).and_then(|key| {
+            let data = read_body(&mut req)?;
+            let sheet = serde_json::from_slice(&data).map_err(|err| anyhow!("parse sprite sheet {err:?}"))?;
+            with_device(&emulator, |device, _| device.set_sprite_sheet(&key, sheet))?;
+            Ok(text_response("OK"))
+        }),
+        (Method::Post, "/template") => query_key(&req).and_then(|key| {
+            let data = read_body(&mut req)?;
+            let elements = serde_json::from_slice(&data).map_err(|err| anyhow!("parse template {err:?}"))?;
+            let names = with_device(&emulator, |device, _| {
+                device.templates.set(&key, elements)?;
+                Ok(device.templates.names())
+            })?;
+            Ok(text_response(format!("{names:?}")))
+        }),
+        (Method::Get, "/list_templates") => with_device(&emulator, |device, _| Ok(text_response(format!("{:?}", device.templates.names())))),
+        (Method::Post, "/render") => read_body(&mut req).and_then(|data| {
+            let params = serde_json::from_slice(&data).map_err(|err| anyhow!("parse render params {err:?}"))?;
+            with_device(&emulator, |device, screen| device.render_template(screen, &params))?;
+            Ok(text_response("OK"))
+        }),
+        (Method::Get, "/play_gif") => query_params(&req).and_then(|mut params| {
+            let key = params.remove("key").ok_or(anyhow!("缺少参数key"))?;
+            let x = params.get("x").map(|v| v.parse()).transpose()?.unwrap_or(0);
+            let y = params.get("y").map(|v| v.parse()).transpose()?.unwrap_or(0);
+            let loops = params.get("loops").map(|v| v.parse()).transpose()?.unwrap_or(0);
+            with_device(&emulator, |device, screen| device.play_gif(screen, PlayGif { key, x, y, loops }))?;
+            Ok(text_response("OK"))
+        }),
+        (Method::Get, "/stop_gif") => with_device(&emulator, |device, _| {
+            device.stop_gif();
+            Ok(text_response("OK"))
+        }),
+        (Method::Get, "/list_images") => with_device(&emulator, |device, _| Ok(text_response(format!("{:?}", device.image_keys())))),
+        (Method::Get, "/image_quota") => with_device(&emulator, |device, _| {
+            let usage = device.image_store.as_ref().ok_or(anyhow!("图片存储未挂载"))?.usage()?;
+            Ok(Response::from_string(serde_json::to_string(&usage)?)
+                .with_header(header("Content-Type", "application/json; charset=utf-8")))
+        }),
+        (Method::Get, "/download_image") => query_key(&req).and_then(|key| {
+            let png = with_device(&emulator, |device, _| device.download_image(&key))?;
+            Ok(Response::from_data(png).with_header(header("Content-Type", "image/png")))
+        }),
+        (Method::Post, "/upload_font") => query_key(&req).and_then(|key| {
+            let data = read_body(&mut req)?;
+            let keys = with_device(&emulator, |device, _| device.upload_font(key, data))?;
+            Ok(text_response(format!("{keys:?}")))
+        }),
+        (Method::Get, "/list_fonts") => with_device(&emulator, |device, _| Ok(text_response(format!("{:?}", device.font_keys())))),
+        (Method::Get, "/delete_font") => query_key(&req).and_then(|key| {
+            let keys = with_device(&emulator, |device, _| Ok(device.delete_font(&key)))?;
+            Ok(text_response(format!("{keys:?}")))
+        }),
+        (Method::Post, "/draw_image") => read_body(&mut req).and_then(|data| {
+            let (w, h) = with_device(&emulator, |device, screen| device.draw_image(screen, &data))?;
+            Ok(text_response(format!("{w}x{h} emulator")))
+        }),
+        (Method::Post, "/draw_rgb565") => read_body(&mut req).and_then(|data| {
+            let (w, h) = with_device(&emulator, |device, screen| device.draw_rgb565_fullscreen(screen, &data))?;
+            Ok(text_response(format!("{w}x{h} emulator")))
+        }),
+        (Method::Post, "/draw_rgb565_lz4") => read_body(&mut req).and_then(|data| {
+            let (w, h) = with_device(&emulator, |device, screen| device.draw_rgb565_lz4(screen, &data))?;
+            Ok(text_response(format!("{w}x{h} emulator")))
+        }),
+        _ => {
+            return req
+                .respond(text_response("Not Found").with_status_code(StatusCode(404)))
+                .map_err(|err| anyhow!("{err:?}"));
+        }
+    };
+
+    // 与固件一致：出错时仍返回200，响应体为错误信息
+    let resp = result.unwrap_or_else(|err| text_response(format!("{err:?}")));
+    req.respond(resp).map_err(|err| anyhow!("{err:?}"))
+}
+
+fn handle_websocket(req: Request, emulator: SharedEmulator) -> Result<()> {
+    let key = req
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Sec-WebSocket-Key"))
+        .map(|h| h.value.to_string())
+        .ok_or(anyhow!("missing Sec-WebSocket-Key"))?;
+    let resp = Response::empty(StatusCode(101))
+        .with_header(header("Upgrade", "websocket"))
+        .with_header(header("Connection", "Upgrade"))
+        .with_header(header("Sec-WebSocket-Accept", &derive_accept_key(key.as_bytes())));
+    let stream = req.upgrade("websocket", resp);
+    let mut ws = WebSocket::from_raw_socket(stream, Role::Server, None);
+
+    info!("New WebSocket session...");
+    // 新连接时重置帧差分解码器，等待客户端发送关键帧
+    emulator.lock().unwrap().device.reset_delta_decoder();
+    ws.send(Message::text("Welcome"))?;
+
+    loop {
+        let msg = match ws.read() {
+            Ok(msg) => msg,
+            Err(_) => break,
+        };
+        let reply = match msg {
+            Message::Text(text) => {
+                let json = text.as_str().trim_end_matches('\0');
+                // JSON对象为与MQTT相同格式的消息（如场景补丁 {"Scene":[...]}），数组为绘制元素列表
+                let result = with_device(&emulator, |device, screen| {
+                    if json.trim_start().starts_with('{') {
+                        device.handle_text_message(screen, json)
+                    } else {
+                        device.draw_json_elements(screen, json).map(|_| None)
+                    }
+                });
+                match result {
+                    // 模拟器没有MQTT，截图以二进制消息回复
+                    Ok(Some((_topic, png))) => Some(WsReply::Binary(png)),
+                    Ok(None) => None,
+                    Err(err) => {
+                        info!("draw json error:{err:?}");
+                        Some(WsReply::Text(format!(
+                            "draw json error:{err:?} byteLen={} String len={}",
+                            json.len(),
+                            json.chars().count()
+                        )))
+                    }
+                }
+            }
+            Message::Binary(data) => with_device(&emulator, |device, screen| Ok(device.handle_ws_binary(Some(screen), &data)))?,
+            Message::Close(_) => break,
+            _ => None,
+        };
+        match reply {
+            Some(WsReply::Text(text)) => ws.send(Message::text(text))?,
+            Some(WsReply::Binary(data)) => ws.send(Message::binary(data))?,
+            None => (),
+        }
+    }
+
+    // 连接关闭时也重置解码器
+    emulator.lock().unwrap().device.reset_delta_decoder();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use wifi_screen_core::delta_decoder::WIFI_KEY_MAGIC;
+
+    use super::*;
+    use crate::device::tests::{emulator, pixel};
+
+    /// 在随机端口上启动HTTP服务器，返回地址
+    fn start(emulator: SharedEmulator) -> String {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap().to_string();
+        std::thread::spawn(move || serve(server, emulator));
+        addr
+    }
+
+    /// 发送POST请求，返回响应体
+    fn post(addr: &str, path: &str, body: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "POST {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len()).unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).unwrap();
+        resp.split_once("\r\n\r\n").map(|(_, body)| body.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_http_draw_canvas() {
+        let emulator = emulator("http");
+        let addr = start(emulator.clone());
+        let json = r##"[{"Rectangle":{"left":0,"top":0,"width":32,"height":16,"stroke_width":0,"fill_color":"#00f"}}]"##;
+        assert_eq!(post(&addr, "/draw_canvas", json), "OK");
+        assert_eq!(pixel(&emulator, 5, 5), [0, 0, 255]);
+        // 与固件一致，出错时返回错误信息
+        assert!(post(&addr, "/draw_canvas", "[").contains("parse elements"));
+    }
+
+    #[test]
+    fn test_ws_frames() {
+        let emulator = emulator("ws");
+        let addr = start(emulator.clone());
+        let (mut ws, _) = tungstenite::connect(format!("ws://{addr}/ws")).unwrap();
+        assert_eq!(ws.read().unwrap(), Message::text("Welcome"));
+
+        // 关键帧：全屏红色
+        let mut frame = WIFI_KEY_MAGIC.to_vec();
+        frame.extend_from_slice(&[0, 32, 0, 16]);
+        frame.extend_from_slice(&lz4_flex::compress_prepend_size(&[0xf8, 0x00].repeat(32 * 16)));
+        ws.send(Message::binary(frame)).unwrap();
+        assert_eq!(ws.read().unwrap(), Message::text("ACK"));
+        assert_eq!(pixel(&emulator, 31, 15), [255, 0, 0]);
+
+        // 截图消息以二进制消息回复PNG
+        ws.send(Message::text(r#"{"Screenshot":"screen"}"#)).unwrap();
+        let png = match ws.read().unwrap() {
+            Message::Binary(png) => png,
+            msg => panic!("unexpected reply {msg:?}"),
+        };
+        let framebuffer = emulator.lock().unwrap().screen.framebuffer.to_rgb_image();
+        assert_eq!(image::load_from_memory(&png).unwrap().to_rgb8(), framebuffer);
+    }
+}
@@ -0,0 +1,102 @@
+// ESP32-WiFi-Screen 设备模拟器
+// 在PC上运行与固件相同的渲染和协议解析代码，将屏幕内容保存为PNG，
+// 用于在没有硬件的情况下调试上位机、API和画布JSON
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use device::Emulator;
+use wifi_screen_core::image_store::ImageStore;
+
+mod device;
+mod http;
+mod usb;
+
+const USAGE: &str = "用法: wifi-screen-emulator [--width 320] [--height 240] [--http 127.0.0.1:8080] [--usb-tcp 127.0.0.1:8081] [--usb-pty] [--png screen.png] [--band-height 0] [--image-dir DIR] [--image-cache-kb 1024]";
+
+/// 图片存储容量，与固件 storage 分区(0xB0000)可用的大小一致
+const IMAGE_STORE_CAPACITY: u64 = 0xB0000 * 3 / 4;
+
+struct Args {
+    width: u16,
+    height: u16,
+    http: String,
+    usb_tcp: Option<String>,
+    usb_pty: bool,
+    png: Option<PathBuf>,
+    band_height: u16,
+    image_dir: PathBuf,
+    image_cache_kb: u32,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut args = Args {
+        width: 320,
+        height: 240,
+        http: "127.0.0.1:8080".to_string(),
+        usb_tcp: None,
+        usb_pty: false,
+        png: None,
+        band_height: 0,
+        image_dir: std::env::temp_dir().join("wifi-screen-emulator-images"),
+        image_cache_kb: 0,
+    };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(anyhow!("{arg} 缺少参数值\n{USAGE}"));
+        match arg.as_str() {
+            "--width" => args.width = value()?.parse()?,
+            "--height" => args.height = value()?.parse()?,
+            "--http" => args.http = value()?,
+            "--usb-tcp" => args.usb_tcp = Some(value()?),
+            "--usb-pty" => args.usb_pty = true,
+            "--png" => args.png = Some(PathBuf::from(value()?)),
+            "--band-height" => args.band_height = value()?.parse()?,
+            "--image-dir" => args.image_dir = PathBuf::from(value()?),
+            "--image-cache-kb" => args.image_cache_kb = value()?.parse()?,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            _ => return Err(anyhow!("未知参数: {arg}\n{USAGE}")),
+        }
+    }
+    if args.width == 0 || args.height == 0 {
+        return Err(anyhow!("屏幕宽高不能为0"));
+    }
+    Ok(args)
+}
+
+fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let args = parse_args()?;
+    println!("模拟屏幕: {}x{}", args.width, args.height);
+
+    std::fs::create_dir_all(&args.image_dir)?;
+    println!("图片存储目录: {}", args.image_dir.display());
+    let image_store = ImageStore::new(args.image_dir, IMAGE_STORE_CAPACITY);
+
+    let mut emulator = Emulator::new(args.width, args.height, args.png, image_store)?;
+    emulator.screen.render_band_height = args.band_height;
+    if args.image_cache_kb > 0 {
+        emulator.device.image_cache.set_budget(args.image_cache_kb as usize * 1024);
+    }
+    let emulator = Arc::new(Mutex::new(emulator));
+    device::start_gif_player(emulator.clone());
+
+    if let Some(addr) = args.usb_tcp.as_ref() {
+        usb::start_usb_tcp(addr, emulator.clone())?;
+    }
+
+    if args.usb_pty {
+        #[cfg(unix)]
+        println!("USB串口: {}", usb::start_usb_pty(emulator.clone())?);
+        #[cfg(not(unix))]
+        return Err(anyhow!("--usb-pty 仅支持 Linux/macOS，Windows 请使用 --usb-tcp"));
+    }
+
+    println!("屏幕截图: http://{}/framebuffer.png", args.http);
+    http::start_http_server(&args.http, emulator)
+}
@@ -0,0 +1,168 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{error, info};
+
+use wifi_screen_core::device::Screen;
+use wifi_screen_core::usb_protocol::{screenshot_response, UsbEvent, UsbFrameParser};
+
+use crate::device::{with_device, SharedEmulator};
+
+/// 处理一个USB串口会话，协议与固件 usb_reader.rs 一致
+/// stream 需要设置读超时，超时时检查帧接收超时
+pub fn serve_usb_session<S: Read + Write>(stream: &mut S, emulator: &SharedEmulator) -> Result<()> {
+    let mut read_buf = [0u8; 4096];
+    let mut parser = UsbFrameParser::new();
+    loop {
+        let n = match stream.read(&mut read_buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(err) if matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted) => {
+                parser.check_timeout();
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut responses: Vec<String> = vec![];
+        parser.feed(&read_buf[..n], |event| match event {
+            UsbEvent::Image { x, y, width, height, rgb565, .. } => {
+                if let Err(err) = with_device(emulator, |device, screen| device.draw_rgb565_area(screen, x, y, width, height, &rgb565)) {
+                    responses.push(format!("ERROR:DRAW_FAIL;error={:?}\n", err));
+                }
+            }
+            UsbEvent::ReadInfo => {
+                let (w, h) = emulator.lock().unwrap().screen.size();
+                responses.push(format!("ESP32-WIFI-SCREEN;{};{};PROTO:USB-SCREEN\n", w, h));
+            }
+            UsbEvent::Boot => responses.push("BOOTED\n".to_string()),
+            UsbEvent::Screenshot => {
+                let screen = &emulator.lock().unwrap().screen;
+                let (w, h) = screen.size();
+                match screen.screenshot_png() {
+                    Ok(png) => responses.push(screenshot_response(w, h, &png)),
+                    Err(err) => responses.push(format!("ERROR:SCREENSHOT_FAIL;error={:?}\n", err)),
+                }
+            }
+            UsbEvent::SpeedResult { bytes, ms } => {
+                // 与固件一致，测速结果发送两次
+                responses.push(format!("SPEEDRESULT;{};{}\n", bytes, ms));
+                responses.push(format!("SPEEDRESULT;{};{}\n", bytes, ms));
+            }
+            UsbEvent::Error(msg) => responses.push(format!("ERROR:{}\n", msg)),
+        });
+        for resp in responses {
+            stream.write_all(resp.as_bytes())?;
+            stream.flush()?;
+        }
+    }
+}
+
+/// 在TCP端口上模拟USB串口（每个连接相当于一次串口打开）
+pub fn start_usb_tcp(addr: &str, emulator: SharedEmulator) -> Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    info!("USB serial over TCP: {addr}");
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(s) => s,
+                Err(err) => {
+                    error!("usb tcp accept: {err:?}");
+                    continue;
+                }
+            };
+            let emulator = emulator.clone();
+            std::thread::spawn(move || {
+                let _ = stream.set_read_timeout(Some(Duration::from_millis(100)));
+                if let Err(err) = serve_usb_session(&mut stream, &emulator) {
+                    error!("usb tcp session: {err:?}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// 创建一对伪终端模拟USB串口，上位机打开返回的串口名即可
+#[cfg(unix)]
+pub fn start_usb_pty(emulator: SharedEmulator) -> Result<String> {
+    use serialport::{SerialPort, TTYPort};
+
+    let (mut master, slave) = TTYPort::pair()?;
+    master.set_timeout(Duration::from_millis(100))?;
+    let name = slave.name().unwrap_or_default();
+    info!("USB serial over PTY: {name}");
+    std::thread::spawn(move || {
+        // 保持 slave 打开，否则上位机关闭串口后 master 读取会出错
+        let _slave = slave;
+        loop {
+            if let Err(err) = serve_usb_session(&mut master, &emulator) {
+                error!("usb pty session: {err:?}");
+                std::thread::sleep(Duration::from_millis(200));
+            }
+        }
+    });
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use wifi_screen_core::usb_protocol::{IMAGE_AA, IMAGE_BB, SCREENSHOT_BYTES};
+
+    use super::*;
+    use crate::device::tests::{emulator, pixel};
+
+    /// 内存中的串口，每次读取返回一段数据，读完时会话结束
+    struct MockSerial {
+        reads: VecDeque<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl Read for MockSerial {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let data = self.reads.pop_front().unwrap_or_default();
+            buf[..data.len()].copy_from_slice(&data);
+            Ok(data.len())
+        }
+    }
+
+    impl Write for MockSerial {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_usb_frames() {
+        let emulator = emulator("usb");
+        // (3,4) 处 2x1 的绿色图像帧
+        let mut frame = IMAGE_AA.to_be_bytes().to_vec();
+        for v in [2u16, 1, 3, 4] {
+            frame.extend_from_slice(&v.to_be_bytes());
+        }
+        frame.extend_from_slice(&lz4_flex::compress_prepend_size(&[0x07, 0xe0].repeat(2)));
+        frame.extend_from_slice(&IMAGE_BB.to_be_bytes());
+        let mut serial = MockSerial {
+            reads: VecDeque::from([b"ReadInfo".to_vec(), frame, SCREENSHOT_BYTES.to_vec()]),
+            written: vec![],
+        };
+        serve_usb_session(&mut serial, &emulator).unwrap();
+
+        assert_eq!(pixel(&emulator, 4, 4), [0, 255, 0]);
+        assert_eq!(pixel(&emulator, 5, 4), [0, 0, 0]);
+        let written = String::from_utf8(serial.written).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+        assert_eq!(lines[0], "ESP32-WIFI-SCREEN;32;16;PROTO:USB-SCREEN");
+        let png = emulator.lock().unwrap().screen.screenshot_png().unwrap();
+        assert_eq!(lines[1], screenshot_response(32, 16, &png).trim_end());
+        assert_eq!(lines.len(), 2);
+    }
+}
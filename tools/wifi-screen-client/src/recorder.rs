@@ -13,11 +13,9 @@ use xcap::Monitor;
 
 use crate::{rgb565::rgb888_to_rgb565_be, show_alert_async, usb_serial, DisplayConfig};
 
-// WiFi帧差分协议 Magic Numbers (8字节)
+// WiFi帧差分协议 Magic Numbers (8字节)，与固件共用 wifi-screen-core 中的定义
 // 格式: MAGIC(8) + WIDTH(2) + HEIGHT(2) + LZ4_COMPRESSED_DATA
-const WIFI_KEY_MAGIC: &[u8; 8] = b"wflz4ke_"; // lz4压缩的关键帧(完整RGB565)
-const WIFI_DLT_MAGIC: &[u8; 8] = b"wflz4dl_"; // lz4压缩的差分帧(XOR差分数据)
-const WIFI_NOP_MAGIC: &[u8; 8] = b"wflz4no_"; // 无变化帧(屏幕静止，跳过绘制)
+use wifi_screen_core::delta_decoder::{WIFI_DLT_MAGIC, WIFI_KEY_MAGIC, WIFI_NOP_MAGIC};
 
 // 无变化帧阈值：压缩后小于此大小认为画面没变化
 const NO_CHANGE_THRESHOLD: usize = 200;
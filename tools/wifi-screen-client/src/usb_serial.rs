@@ -4,10 +4,8 @@ use std::time::{Duration, Instant};
 use anyhow::{anyhow, Result};
 use serialport::{SerialPort, SerialPortInfo, SerialPortType};
 
-// Must match device (`scr/src/usb_reader.rs`)
-const READ_INFO_MAGIC: u64 = 0x52656164496e666f; // "ReadInfo"
-const IMAGE_AA: u64 = 0x696d6167655f6161; // "image_aa"
-const IMAGE_BB: u64 = 0x696d6167655f6262; // "image_bb"
+// 协议常量与固件共用 (`wifi-screen-core/src/usb_protocol.rs`)
+use wifi_screen_core::usb_protocol::{IMAGE_AA, IMAGE_BB, READ_INF as READ_INFO_MAGIC};
 
 pub const DEFAULT_BAUD: u32 = 2_000_000;
 
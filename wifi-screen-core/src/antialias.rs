@@ -0,0 +1,250 @@
+use image::{GrayImage, Luma, RgbImage};
+
+use crate::imageproc::drawing::{
+    draw_antialiased_line_segment_mut, draw_antialiased_polygon_mut, draw_line_segment_mut, draw_polygon_mut,
+};
+use crate::imageproc::point::Point;
+use crate::path::SubPath;
+use crate::render::{blend_pixel, Paint};
+
+// 按轮廓绘制的图形（抗锯齿的基本图形、多边形、路径）：先把图形的覆盖率画到灰度蒙版上，再按颜色（或渐变）一次混合到画布
+// 不抗锯齿时蒙版中只有 0 和 255
+// 线段之间重叠的部分取覆盖率的最大值，半透明时不会重复混合
+// 坐标为像素中心，宽度为 d 的图形两侧的点相距 d - 1
+
+/// 曲线分割为折线时每段的长度(像素)
+const SEGMENT_LENGTH: f32 = 2.0;
+
+/// 覆盖率取最大值
+fn max_coverage(line: Luma<u8>, original: Luma<u8>, weight: f32) -> Luma<u8> {
+    Luma([original[0].max((line[0] as f32 * weight.clamp(0.0, 1.0)).round() as u8)])
+}
+
+/// 椭圆弧上的点，角度为度，0 为右侧，正数为顺时针
+pub fn ellipse_points(center: (f32, f32), (rx, ry): (f32, f32), angle_start: f32, angle_sweep: f32) -> Vec<(f32, f32)> {
+    let sweep = angle_sweep.clamp(-360.0, 360.0);
+    let length = std::f32::consts::PI * (rx + ry) * sweep.abs() / 360.0;
+    let segments = ((length / SEGMENT_LENGTH).ceil() as usize).max(8);
+    (0..=segments)
+        .map(|i| {
+            let angle = (angle_start + sweep * i as f32 / segments as f32).to_radians();
+            (center.0 + rx * angle.cos(), center.1 + ry * angle.sin())
+        })
+        .collect()
+}
+
+/// 覆盖率蒙版，对应画布上以 (left, top) 为左上角的区域
+pub struct CoverageMask {
+    left: i32,
+    top: i32,
+    mask: GrayImage,
+    antialias: bool,
+}
+
+impl CoverageMask {
+    /// 包含所有点并向外扩展 margin 像素的蒙版，超出画布的部分不绘制
+    pub fn new(canvas: &RgbImage, points: &[(f32, f32)], margin: f32, antialias: bool) -> Self {
+        let min = |f: fn(&(f32, f32)) -> f32| points.iter().map(f).fold(f32::MAX, f32::min);
+        let max = |f: fn(&(f32, f32)) -> f32| points.iter().map(f).fold(f32::MIN, f32::max);
+        let margin = margin + 1.0;
+        let clamp = |v: f32, size: u32| (v.max(0.0) as i64).min(size as i64) as i32;
+        let left = clamp((min(|p| p.0) - margin).floor(), canvas.width());
+        let top = clamp((min(|p| p.1) - margin).floor(), canvas.height());
+        let right = clamp((max(|p| p.0) + margin).ceil() + 1.0, canvas.width()).max(left);
+        let bottom = clamp((max(|p| p.1) + margin).ceil() + 1.0, canvas.height()).max(top);
+        Self { left, top, mask: GrayImage::new((right - left) as u32, (bottom - top) as u32), antialias }
+    }
+
+    /// 取最近的像素，先取整再平移，分条渲染时每个条带的结果相同
+    /// 取整前先对齐到 1/256 像素，平移后坐标的浮点误差不会影响结果
+    fn local(&self, (x, y): (f32, f32)) -> Point<i32> {
+        let round = |v: f32| ((v * 256.0).round() / 256.0 + 0.5).floor() as i32;
+        Point::new(round(x) - self.left, round(y) - self.top)
+    }
+
+    /// 填充多边形
+    pub fn fill_polygon(&mut self, points: &[(f32, f32)]) {
+        if points.len() < 3 || self.mask.is_empty() {
+            return;
+        }
+        let poly: Vec<Point<i32>> = points.iter().map(|p| self.local(*p)).collect();
+        if self.antialias {
+            draw_antialiased_polygon_mut(&mut self.mask, &poly, Luma([255]), max_coverage);
+        } else {
+            draw_polygon_mut(&mut self.mask, &poly, Luma([255]));
+        }
+    }
+
+    /// 填充多个子路径组成的图形，按奇偶规则填充，子路径之间可以形成镂空
+    pub fn fill_paths(&mut self, paths: &[SubPath]) {
+        if let [path] = paths {
+            return self.fill_polygon(&path.points);
+        }
+        if self.mask.is_empty() {
+            return;
+        }
+        let polys: Vec<Vec<Point<i32>>> = paths
+            .iter()
+            .filter(|path| path.points.len() >= 3)
+            .map(|path| path.points.iter().map(|p| self.local(*p)).collect())
+            .collect();
+        let edges: Vec<(Point<i32>, Point<i32>)> = polys
+            .iter()
+            .flat_map(|poly| poly.iter().zip(poly.iter().cycle().skip(1)).map(|(a, b)| (*a, *b)))
+            .collect();
+        let (width, height) = self.mask.dimensions();
+        let mut intersections = vec![];
+        for y in 0..height as i32 {
+            // 每条边包含上端点不包含下端点，顶点不会被计算两次
+            for (p0, p1) in edges.iter().filter(|(p0, p1)| p0.y.min(p1.y) <= y && y < p0.y.max(p1.y)) {
+                let fraction = (y - p0.y) as f32 / (p1.y - p0.y) as f32;
+                intersections.push((p0.x as f32 + fraction * (p1.x - p0.x) as f32).round() as i32);
+            }
+            intersections.sort_unstable();
+            for range in intersections.chunks_exact(2) {
+                for x in range[0].max(0)..=range[1].min(width as i32 - 1) {
+                    self.mask.put_pixel(x as u32, y as u32, Luma([255]));
+                }
+            }
+            intersections.clear();
+        }
+        for (p0, p1) in edges {
+            self.plot_line(p0, p1);
+        }
+    }
+
+    /// 宽度为 1 的线段
+    fn plot_line(&mut self, start: Point<i32>, end: Point<i32>) {
+        if self.antialias {
+            draw_antialiased_line_segment_mut(&mut self.mask, (start.x, start.y), (end.x, end.y), Luma([255]), max_coverage);
+        } else {
+            draw_line_segment_mut(&mut self.mask, (start.x as f32, start.y as f32), (end.x as f32, end.y as f32), Luma([255]));
+        }
+    }
+
+    /// 线段，宽度不超过 1 时使用 Wu 算法（不抗锯齿时使用 Bresenham 算法），更宽时填充为矩形
+    pub fn stroke_line(&mut self, start: (f32, f32), end: (f32, f32), width: f32) {
+        if self.mask.is_empty() {
+            return;
+        }
+        if width <= 1.0 {
+            return self.plot_line(self.local(start), self.local(end));
+        }
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 {
+            return;
+        }
+        let half = (width - 1.0) / 2.0;
+        let (nx, ny) = (-dy / length * half, dx / length * half);
+        self.fill_polygon(&[
+            (start.0 + nx, start.1 + ny),
+            (end.0 + nx, end.1 + ny),
+            (end.0 - nx, end.1 - ny),
+            (start.0 - nx, start.1 - ny),
+        ]);
+    }
+
+    /// 折线，粗线在转折处补上圆形连接（转角很小、缝隙不到半个像素时不补）
+    pub fn stroke_polyline(&mut self, points: &[(f32, f32)], width: f32, closed: bool) {
+        for pair in points.windows(2) {
+            self.stroke_line(pair[0], pair[1], width);
+        }
+        let n = points.len();
+        if closed && n > 2 {
+            self.stroke_line(points[n - 1], points[0], width);
+        }
+        if width <= 2.0 || n < 3 {
+            return;
+        }
+        let radius = (width - 1.0) / 2.0;
+        let joins = if closed { 0..n } else { 1..n - 1 };
+        for i in joins {
+            let (prev, p, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+            let (ax, ay, bx, by) = (p.0 - prev.0, p.1 - prev.1, next.0 - p.0, next.1 - p.1);
+            let sin = (ax * by - ay * bx).abs() / ((ax * ax + ay * ay).sqrt() * (bx * bx + by * by).sqrt()).max(f32::EPSILON);
+            if sin * radius >= 0.5 || ax * bx + ay * by < 0.0 {
+                self.fill_polygon(&ellipse_points(p, (radius, radius), 0.0, 360.0));
+            }
+        }
+    }
+
+    /// 沿曲线（近似为折线）描边：每个点沿法线向两侧偏移，相邻两点组成四边形，接缝处没有缝隙
+    pub fn stroke_curve(&mut self, points: &[(f32, f32)], width: f32, closed: bool) {
+        if width <= 1.0 || points.len() < 2 {
+            return self.stroke_polyline(points, width, closed);
+        }
+        let half = (width - 1.0) / 2.0;
+        let n = points.len();
+        let offsets: Vec<(f32, f32)> = (0..n)
+            .map(|i| {
+                let prev = if i > 0 { points[i - 1] } else if closed { points[n - 1] } else { points[i] };
+                let next = if i + 1 < n { points[i + 1] } else if closed { points[0] } else { points[i] };
+                let (dx, dy) = (next.0 - prev.0, next.1 - prev.1);
+                let length = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+                (-dy / length * half, dx / length * half)
+            })
+            .collect();
+        let quad = |i: usize, j: usize| {
+            let ((a, b), (oa, ob)) = ((points[i], points[j]), (offsets[i], offsets[j]));
+            [(a.0 + oa.0, a.1 + oa.1), (b.0 + ob.0, b.1 + ob.1), (b.0 - ob.0, b.1 - ob.1), (a.0 - oa.0, a.1 - oa.1)]
+        };
+        for i in 0..n - 1 {
+            self.fill_polygon(&quad(i, i + 1));
+        }
+        if closed {
+            self.fill_polygon(&quad(n - 1, 0));
+        }
+    }
+
+    /// 按覆盖率把颜色混合到画布上
+    pub(crate) fn blend(&self, canvas: &mut RgbImage, paint: Paint) {
+        for (x, y, coverage) in self.mask.enumerate_pixels() {
+            if coverage[0] == 0 {
+                continue;
+            }
+            let (cx, cy) = (self.left + x as i32, self.top + y as i32);
+            let mut color = paint.color(cx, cy);
+            color[3] = (color[3] as u16 * coverage[0] as u16 / 255) as u8;
+            blend_pixel(canvas.get_pixel_mut(cx as u32, cy as u32), color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_mask() {
+        let canvas = RgbImage::new(20, 20);
+        // 超出画布的部分被裁掉
+        let mut mask = CoverageMask::new(&canvas, &[(-10.0, 5.0), (10.0, 30.0)], 2.0, true);
+        assert_eq!((mask.left, mask.top, mask.mask.dimensions()), (0, 2, (14, 18)));
+        mask.stroke_line((0.0, 10.0), (12.0, 10.0), 3.0);
+        assert_eq!(mask.mask.get_pixel(5, 7)[0], 255);
+        assert_eq!(mask.mask.get_pixel(5, 9)[0], 255);
+        assert_eq!(mask.mask.get_pixel(5, 10)[0], 0);
+
+        let mask = CoverageMask::new(&canvas, &[(30.0, 30.0)], 1.0, true);
+        assert!(mask.mask.is_empty());
+
+        let points = ellipse_points((10.0, 10.0), (5.0, 5.0), -90.0, 180.0);
+        let (first, last) = (points[0], points[points.len() - 1]);
+        assert!((first.0 - 10.0).abs() < 0.01 && (first.1 - 5.0).abs() < 0.01);
+        assert!((last.0 - 10.0).abs() < 0.01 && (last.1 - 15.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_fill_paths() {
+        let canvas = RgbImage::new(20, 20);
+        let outer = SubPath::new(vec![(2.0, 2.0), (17.0, 2.0), (17.0, 17.0), (2.0, 17.0)], true);
+        let inner = SubPath::new(vec![(6.0, 6.0), (13.0, 6.0), (13.0, 13.0), (6.0, 13.0)], true);
+        let mut mask = CoverageMask::new(&canvas, &outer.points, 0.0, false);
+        mask.fill_paths(&[outer, inner]);
+        let at = |x: i32, y: i32| mask.mask.get_pixel((x - mask.left) as u32, (y - mask.top) as u32)[0];
+        // 内外轮廓都被填充，中间镂空
+        assert_eq!((at(2, 2), at(4, 10), at(6, 10), at(13, 10)), (255, 255, 255, 255));
+        assert_eq!((at(10, 10), at(1, 1)), (0, 0));
+    }
+}
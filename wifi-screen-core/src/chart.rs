@@ -0,0 +1,332 @@
+use serde::Deserialize;
+
+use crate::gradient::Fill;
+use crate::render::{CSSColor, Element, Line, Polygon, Polyline, Rectangle, Text};
+use crate::text_layout::TextVAlign;
+
+// 图表：折线图、柱状图、迷你折线图
+// 只传数据，在设备上展开为 Rectangle/Line/Polyline/Polygon/Text 等基本元素绘制
+
+/// 网格默认分为几格
+const DEFAULT_GRID_LINES: u32 = 4;
+/// 纵轴标签的字号
+const LABEL_SIZE: f32 = 12.0;
+/// 柱之间的默认间距(像素)
+const DEFAULT_BAR_GAP: u32 = 2;
+
+/// 折线图
+#[derive(Clone, Deserialize)]
+pub struct LineChart {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// 数据，从左到右均匀分布
+    pub values: Vec<f32>,
+    /// 纵轴范围，默认为数据的最小值和最大值
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub color: CSSColor,
+    /// 线宽，默认 1
+    pub stroke_width: Option<u32>,
+    /// 折线下方区域的填充颜色或渐变
+    pub fill: Option<Fill>,
+    /// 背景色
+    pub background: Option<CSSColor>,
+    /// 坐标轴（左侧和 0 值处）的颜色，不设置时不绘制
+    pub axis_color: Option<CSSColor>,
+    /// 水平网格线的颜色，不设置时不绘制
+    pub grid_color: Option<CSSColor>,
+    /// 网格分为几格，默认 4
+    pub grid_lines: Option<u32>,
+    /// 纵轴最大值和最小值标签的颜色，不设置时不绘制
+    pub label_color: Option<CSSColor>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+/// 柱状图
+#[derive(Clone, Deserialize)]
+pub struct BarChart {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// 数据，每个值一根柱，负数向下
+    pub values: Vec<f32>,
+    /// 纵轴范围，默认包含 0 和所有数据
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    /// 柱的颜色或渐变
+    pub color: Fill,
+    /// 每根柱的颜色，数量不够时其余的柱使用 color
+    #[serde(default)]
+    pub colors: Vec<CSSColor>,
+    /// 柱之间的间距(像素)，默认 2
+    pub bar_gap: Option<u32>,
+    /// 背景色
+    pub background: Option<CSSColor>,
+    /// 坐标轴（左侧和 0 值处）的颜色，不设置时不绘制
+    pub axis_color: Option<CSSColor>,
+    /// 水平网格线的颜色，不设置时不绘制
+    pub grid_color: Option<CSSColor>,
+    /// 网格分为几格，默认 4
+    pub grid_lines: Option<u32>,
+    /// 纵轴最大值和最小值标签的颜色，不设置时不绘制
+    pub label_color: Option<CSSColor>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+}
+
+/// 迷你折线图：没有坐标轴和网格，适合放在文字旁边显示趋势
+#[derive(Clone, Deserialize)]
+pub struct Sparkline {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<f32>,
+    /// 纵轴范围，默认为数据的最小值和最大值
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub color: CSSColor,
+    /// 线宽，默认 1
+    pub stroke_width: Option<u32>,
+    /// 折线下方区域的填充颜色或渐变
+    pub fill: Option<Fill>,
+    /// 最后一个点的颜色，不设置时不标记
+    pub last_point_color: Option<CSSColor>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+/// 图表的绘图区域和纵轴范围
+struct Plot {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    min: f32,
+    max: f32,
+}
+
+impl Plot {
+    /// include_zero 为 true 时自动范围包含 0（柱状图）
+    fn new((x, y, width, height): (i32, i32, u32, u32), values: &[f32], min: Option<f32>, max: Option<f32>, include_zero: bool) -> Self {
+        let zero = if include_zero { Some(0.0) } else { None };
+        let data_min = values.iter().copied().chain(zero).fold(f32::MAX, f32::min);
+        let data_max = values.iter().copied().chain(zero).fold(f32::MIN, f32::max);
+        let min = min.unwrap_or(if data_min <= data_max { data_min } else { 0.0 });
+        let mut max = max.unwrap_or(if data_min <= data_max { data_max } else { 1.0 });
+        if max <= min {
+            max = min + 1.0;
+        }
+        Self { x, y, width: width.max(1), height: height.max(1), min, max }
+    }
+
+    fn right(&self) -> i32 {
+        self.x + self.width as i32 - 1
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y + self.height as i32 - 1
+    }
+
+    /// 值对应的纵坐标，超出范围的值画在边上
+    fn value_y(&self, value: f32) -> i32 {
+        let t = ((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0);
+        self.y + ((1.0 - t) * (self.height - 1) as f32).round() as i32
+    }
+
+    /// 0 值（超出范围时为靠近 0 的边）的纵坐标，柱从这里开始画
+    fn baseline(&self) -> i32 {
+        self.value_y(0.0)
+    }
+
+    /// 第 i 个点（共 n 个）的横坐标
+    fn index_x(&self, i: usize, n: usize) -> i32 {
+        if n < 2 {
+            return self.x;
+        }
+        self.x + (i as f32 * (self.width - 1) as f32 / (n - 1) as f32).round() as i32
+    }
+
+    /// 折线的点
+    fn points(&self, values: &[f32]) -> Vec<(i32, i32)> {
+        values.iter().enumerate().map(|(i, v)| (self.index_x(i, values.len()), self.value_y(*v))).collect()
+    }
+}
+
+fn line(start: (i32, i32), end: (i32, i32), color: &CSSColor) -> Element {
+    Element::Line(Line { start, end, stroke_width: 1, color: color.clone(), opacity: None, antialias: false })
+}
+
+fn rectangle((left, top): (i32, i32), (width, height): (u32, u32), fill: Fill) -> Element {
+    Element::Rectangle(Rectangle { left, top, width, height, stroke_width: 0, fill_color: Some(fill), stroke_color: None, opacity: None })
+}
+
+/// 数值标签，最多保留两位小数
+pub(crate) fn label(value: f32) -> String {
+    let text = format!("{value:.2}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// 折线和下方的填充区域
+fn line_elements(plot: &Plot, values: &[f32], color: &CSSColor, stroke_width: Option<u32>, fill: Option<&Fill>, antialias: bool) -> Vec<Element> {
+    let points = plot.points(values);
+    let mut elements = vec![];
+    if let (Some(fill), Some(first), Some(last)) = (fill, points.first(), points.last()) {
+        let mut area = points.clone();
+        area.extend([(last.0, plot.bottom()), (first.0, plot.bottom())]);
+        elements.push(Element::Polygon(Polygon {
+            points: area,
+            stroke_width: 0,
+            fill_color: Some(fill.clone()),
+            stroke_color: None,
+            opacity: None,
+            antialias,
+        }));
+    }
+    elements.push(Element::Polyline(Polyline {
+        points,
+        stroke_width: stroke_width.unwrap_or(1),
+        color: color.clone(),
+        opacity: None,
+        antialias,
+    }));
+    elements
+}
+
+/// 坐标轴、网格和标签
+struct Decorations<'a> {
+    background: Option<&'a CSSColor>,
+    axis_color: Option<&'a CSSColor>,
+    grid_color: Option<&'a CSSColor>,
+    grid_lines: Option<u32>,
+    label_color: Option<&'a CSSColor>,
+}
+
+impl Decorations<'_> {
+    /// 数据下方的背景和网格
+    fn below(&self, plot: &Plot) -> Vec<Element> {
+        let mut elements = vec![];
+        if let Some(background) = self.background {
+            elements.push(rectangle((plot.x, plot.y), (plot.width, plot.height), background.clone().into()));
+        }
+        if let Some(color) = self.grid_color {
+            let lines = self.grid_lines.unwrap_or(DEFAULT_GRID_LINES).max(1);
+            for i in 0..=lines {
+                let y = plot.y + (i as f32 * (plot.height - 1) as f32 / lines as f32).round() as i32;
+                elements.push(line((plot.x, y), (plot.right(), y), color));
+            }
+        }
+        elements
+    }
+
+    /// 数据上方的坐标轴和标签
+    fn above(&self, plot: &Plot) -> Vec<Element> {
+        let mut elements = vec![];
+        if let Some(color) = self.axis_color {
+            elements.push(line((plot.x, plot.y), (plot.x, plot.bottom()), color));
+            elements.push(line((plot.x, plot.baseline()), (plot.right(), plot.baseline()), color));
+        }
+        if let Some(color) = self.label_color {
+            let text = |y: i32, value: f32, valign: TextVAlign| {
+                Element::Text(Text { x: plot.x + 2, y, text: label(value), size: LABEL_SIZE, color: color.clone(), valign, ..Default::default() })
+            };
+            elements.push(text(plot.y, plot.max, TextVAlign::Top));
+            elements.push(text(plot.bottom(), plot.min, TextVAlign::Bottom));
+        }
+        elements
+    }
+}
+
+impl LineChart {
+    /// 展开为基本元素
+    pub fn elements(&self) -> Vec<Element> {
+        let plot = Plot::new((self.x, self.y, self.width, self.height), &self.values, self.min, self.max, false);
+        let decorations = Decorations {
+            background: self.background.as_ref(),
+            axis_color: self.axis_color.as_ref(),
+            grid_color: self.grid_color.as_ref(),
+            grid_lines: self.grid_lines,
+            label_color: self.label_color.as_ref(),
+        };
+        let mut elements = decorations.below(&plot);
+        elements.extend(line_elements(&plot, &self.values, &self.color, self.stroke_width, self.fill.as_ref(), self.antialias));
+        elements.extend(decorations.above(&plot));
+        elements
+    }
+}
+
+impl BarChart {
+    /// 展开为基本元素
+    pub fn elements(&self) -> Vec<Element> {
+        let plot = Plot::new((self.x, self.y, self.width, self.height), &self.values, self.min, self.max, true);
+        let decorations = Decorations {
+            background: self.background.as_ref(),
+            axis_color: self.axis_color.as_ref(),
+            grid_color: self.grid_color.as_ref(),
+            grid_lines: self.grid_lines,
+            label_color: self.label_color.as_ref(),
+        };
+        let mut elements = decorations.below(&plot);
+        let slot = plot.width as f32 / self.values.len().max(1) as f32;
+        let gap = self.bar_gap.unwrap_or(DEFAULT_BAR_GAP) as i32;
+        for (i, value) in self.values.iter().enumerate() {
+            let left = plot.x + (i as f32 * slot).round() as i32;
+            let right = plot.x + ((i + 1) as f32 * slot).round() as i32 - gap;
+            let (y0, y1) = (plot.value_y(*value), plot.baseline());
+            let fill = self.colors.get(i).map_or_else(|| self.color.clone(), |color| color.clone().into());
+            let size = ((right - left).max(1) as u32, y0.abs_diff(y1) + 1);
+            elements.push(rectangle((left, y0.min(y1)), size, fill));
+        }
+        elements.extend(decorations.above(&plot));
+        elements
+    }
+}
+
+impl Sparkline {
+    /// 展开为基本元素
+    pub fn elements(&self) -> Vec<Element> {
+        let plot = Plot::new((self.x, self.y, self.width, self.height), &self.values, self.min, self.max, false);
+        let mut elements = line_elements(&plot, &self.values, &self.color, self.stroke_width, self.fill.as_ref(), self.antialias);
+        if let (Some(color), Some(last)) = (&self.last_point_color, plot.points(&self.values).last()) {
+            let size = self.stroke_width.unwrap_or(1) + 2;
+            let top_left = (last.0 - size as i32 / 2, last.1 - size as i32 / 2);
+            elements.push(rectangle(top_left, (size, size), color.clone().into()));
+        }
+        elements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plot() {
+        let plot = Plot::new((10, 20, 101, 11), &[5.0, 15.0, 10.0], None, None, false);
+        assert_eq!((plot.min, plot.max), (5.0, 15.0));
+        assert_eq!(plot.points(&[5.0, 15.0, 10.0]), vec![(10, 30), (60, 20), (110, 25)]);
+        // 超出范围的值画在边上
+        assert_eq!((plot.value_y(100.0), plot.value_y(-100.0)), (20, 30));
+        assert_eq!(plot.baseline(), 30);
+
+        // 柱状图的范围包含 0
+        let plot = Plot::new((0, 0, 10, 11), &[-5.0, 5.0], None, None, true);
+        assert_eq!((plot.min, plot.max, plot.baseline()), (-5.0, 5.0, 5));
+        let plot = Plot::new((0, 0, 10, 11), &[], None, None, false);
+        assert_eq!((plot.min, plot.max), (0.0, 1.0));
+        let plot = Plot::new((0, 0, 10, 11), &[3.0, 3.0], None, None, false);
+        assert_eq!((plot.min, plot.max), (3.0, 4.0));
+
+        assert_eq!((label(12.0), label(0.5), label(-1.256)), ("12".to_string(), "0.5".to_string(), "-1.26".to_string()));
+    }
+}
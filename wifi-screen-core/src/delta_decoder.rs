@@ -0,0 +1,141 @@
+use std::time::Instant;
+
+use log::{info, warn};
+
+// WiFi帧差分协议 Magic Numbers (8字节)
+pub const WIFI_KEY_MAGIC: &[u8; 8] = b"wflz4ke_"; // lz4压缩的关键帧(完整RGB565)
+pub const WIFI_DLT_MAGIC: &[u8; 8] = b"wflz4dl_"; // lz4压缩的差分帧(XOR差分数据)
+pub const WIFI_NOP_MAGIC: &[u8; 8] = b"wflz4no_"; // 无变化帧(屏幕静止，跳过绘制)
+
+// WiFi帧差分解码器 (固件中为全局单例，用于WebSocket接收)
+// 用于在ESP32端对接收的帧差分数据进行解码
+// 注意: 为了节省内存，解码后返回对内部缓冲区的引用，调用者需要在锁持有期间使用数据
+pub struct DeltaDecoder {
+    prev_frame: Vec<u8>,  // 上一帧RGB565数据 (存储在PSRAM)
+    error_count: u32,     // 错误计数(用于限制日志频率)
+    last_error: Option<&'static str>, // 上一次错误类型
+}
+
+impl Default for DeltaDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeltaDecoder {
+    pub fn new() -> Self {
+        Self {
+            prev_frame: Vec::new(),
+            error_count: 0,
+            last_error: None,
+        }
+    }
+
+    // 检查是否有参考帧
+    pub fn has_reference_frame(&self) -> bool {
+        !self.prev_frame.is_empty()
+    }
+
+    // 记录错误(限制日志频率)
+    pub fn log_error(&mut self, err: &'static str) {
+        // 只在错误类型变化或每100次时记录
+        if self.last_error != Some(err) || self.error_count % 100 == 0 {
+            if self.error_count > 1 && self.last_error == Some(err) {
+                warn!("wifi frame: {} (x{})", err, self.error_count);
+            } else {
+                warn!("wifi frame: {}", err);
+            }
+            self.error_count = 1;
+        } else {
+            self.error_count += 1;
+        }
+        self.last_error = Some(err);
+    }
+
+    // 重置错误计数
+    fn clear_error(&mut self) {
+        if self.error_count > 1 {
+            if let Some(err) = self.last_error {
+                info!("wifi frame: recovered after {} errors ({})", self.error_count, err);
+            }
+        }
+        self.error_count = 0;
+        self.last_error = None;
+    }
+
+    // lz4解压辅助函数 (比zstd快5-10倍)
+    fn lz4_decompress(lz4_data: &[u8]) -> Result<Vec<u8>, &'static str> {
+        lz4_flex::decompress_size_prepended(lz4_data)
+            .map_err(|_| "lz4 decompress failed")
+    }
+
+    // 解码关键帧 (lz4压缩的完整RGB565)
+    pub fn decode_key_frame(&mut self, lz4_data: &[u8]) -> Result<&[u8], &'static str> {
+        let decompressed = Self::lz4_decompress(lz4_data)?;
+        self.prev_frame = decompressed;
+        self.clear_error();
+        Ok(&self.prev_frame)
+    }
+
+    // 解码差分帧 (lz4压缩的XOR差分数据)
+    // 返回: (解码后数据引用, lz4解压耗时ms, xor耗时ms)
+    pub fn decode_delta_frame_timed(&mut self, lz4_data: &[u8]) -> Result<(&[u8], u128, u128), &'static str> {
+        if self.prev_frame.is_empty() {
+            return Err("no reference frame");
+        }
+        
+        // LZ4解压计时
+        let lz4_start = Instant::now();
+        let delta = Self::lz4_decompress(lz4_data)?;
+        let lz4_ms = lz4_start.elapsed().as_millis();
+        
+        if delta.len() != self.prev_frame.len() {
+            return Err("delta size mismatch");
+        }
+        
+        // XOR计时
+        let xor_start = Instant::now();
+        
+        // 使用u32批量XOR加速 (ESP32是32位CPU)
+        let len = self.prev_frame.len();
+        let chunks = len / 4;
+        let remainder = len % 4;
+        
+        // 批量处理4字节
+        let prev_u32: &mut [u32] = unsafe {
+            std::slice::from_raw_parts_mut(self.prev_frame.as_mut_ptr() as *mut u32, chunks)
+        };
+        let delta_u32: &[u32] = unsafe {
+            std::slice::from_raw_parts(delta.as_ptr() as *const u32, chunks)
+        };
+        for (p, d) in prev_u32.iter_mut().zip(delta_u32.iter()) {
+            *p ^= *d;
+        }
+        
+        // 处理剩余字节
+        if remainder > 0 {
+            let start = chunks * 4;
+            for i in 0..remainder {
+                self.prev_frame[start + i] ^= delta[start + i];
+            }
+        }
+        
+        let xor_ms = xor_start.elapsed().as_millis();
+        
+        self.clear_error();
+        Ok((&self.prev_frame, lz4_ms, xor_ms))
+    }
+    
+    // 解码差分帧 (兼容旧接口)
+    pub fn decode_delta_frame(&mut self, lz4_data: &[u8]) -> Result<&[u8], &'static str> {
+        self.decode_delta_frame_timed(lz4_data).map(|(data, _, _)| data)
+    }
+
+    // 重置解码器状态
+    pub fn reset(&mut self) {
+        self.prev_frame.clear();
+        self.prev_frame.shrink_to_fit();
+        self.error_count = 0;
+        self.last_error = None;
+    }
+}
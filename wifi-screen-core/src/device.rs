@@ -0,0 +1,775 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use ab_glyph::{FontRef, FontVec};
+use anyhow::{anyhow, Result};
+use image::codecs::png::PngEncoder;
+use image::{ImageEncoder, RgbImage};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::delta_decoder::{DeltaDecoder, WIFI_DLT_MAGIC, WIFI_KEY_MAGIC, WIFI_NOP_MAGIC};
+use crate::font::{load_font, MAX_FONT_CACHE};
+use crate::gif_player::{decode_gif, GifPlayer, PlayGif, MAX_GIF_SIZE};
+use crate::image_cache::{image_size, with_sprite_sheet, LruImageCache};
+use crate::image_store::ImageStore;
+use crate::render::{band_height, decode_image_rgb565, decode_jpeg_to_rgb565, render_elements_banded, Element, Image, ImageCache};
+use crate::rgb565::rgb_image_to_rgb565;
+use crate::scene::{Bounds, Scene, ScenePatch};
+use crate::sprite::SpriteSheet;
+use crate::template::{RenderTemplate, Templates};
+use crate::utils::decode_base64;
+
+// 设备的消息处理：HTTP、WebSocket、MQTT 收到的绘图/图片/字体/场景/模板/GIF 请求
+// 固件(DisplayManager)和设备模拟器(FrameBuffer)实现 Screen，共用这里的处理逻辑
+
+/// 屏幕，绘制的像素都是大端序 RGB565
+pub trait Screen {
+    /// 旋转后的宽高
+    fn size(&self) -> (u16, u16);
+    /// 默认字体
+    fn font(&self) -> FontRef<'static>;
+    /// 分条渲染的条带高度，0 表示自动
+    fn render_band_height(&self) -> u16;
+    fn draw_rgb565(&mut self, x: u16, y: u16, width: u16, height: u16, pixels: &[u16]) -> Result<()>;
+    fn draw_rgb565_u8array(&mut self, x: u16, y: u16, width: u16, height: u16, pixels: &[u8]) -> Result<()>;
+    fn draw_rgb_image(&mut self, x: u16, y: u16, image: &RgbImage) -> Result<()>;
+    /// 当前屏幕内容的PNG截图
+    fn screenshot_png(&self) -> Result<Vec<u8>>;
+    /// 一次请求绘制完成后调用
+    fn flush(&mut self) {}
+}
+
+///接收到的文本消息（MQTT 和 WebSocket 中的 JSON 对象）
+#[derive(Clone, Deserialize)]
+pub enum TextMessage {
+    //绘制消息
+    Draw(Vec<Element>),
+    //上传图片消息 (key, base64文件数据)，图片保存在flash中
+    Upload((String, String)),
+    //删除图片消息 (key)
+    DeleteImage(String),
+    //固定图片消息 (key)，固定的图片常驻内存缓存
+    PinImage(String),
+    //取消固定图片消息 (key)
+    UnpinImage(String),
+    //设置精灵图消息 (key, 精灵图定义)，定义为null时删除
+    SpriteSheet((String, Option<SpriteSheet>)),
+    //上传字体消息 (key, base64字体文件数据)
+    UploadFont((String, String)),
+    //删除字体消息 (key)
+    DeleteFont(String),
+    //场景补丁消息，只重绘变化的区域
+    Scene(Vec<ScenePatch>),
+    //设置模板消息 (名称, 带{{变量}}的元素列表)，元素列表为null时删除
+    Template((String, Option<Vec<Value>>)),
+    //绘制模板消息 {"template":"xxx","vars":{...}}
+    Render(RenderTemplate),
+    //播放已上传的GIF {"key":"xxx","x":0,"y":0,"loops":0}
+    PlayGif(PlayGif),
+    //停止播放GIF
+    StopGif,
+    //截图消息 (回复的topic)，屏幕截图以PNG格式发布到该topic
+    Screenshot(String),
+}
+
+/// WebSocket 二进制消息的回复
+#[derive(Debug, PartialEq)]
+pub enum WsReply {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// 与屏幕无关的设备状态，固件中放在 Context 里，/status 中显示图片缓存和GIF播放状态
+#[derive(Default, Serialize)]
+pub struct Device {
+    //解码后的图片缓存，图片文件保存在 image_store 中，绘制时按需加载
+    pub image_cache: LruImageCache,
+    //flash中的图片存储，挂载失败时为None（图片只保存在内存中）
+    #[serde(skip)]
+    pub image_store: Option<ImageStore>,
+    //存放上传的字体
+    #[serde(skip)]
+    pub font_cache: HashMap<String, FontVec>,
+    //保留模式场景，收到场景补丁时只重绘变化的区域
+    #[serde(skip)]
+    pub scene: Scene,
+    //保存的模板，收到 Render 消息时替换变量后绘制
+    #[serde(skip)]
+    pub templates: Templates,
+    //正在播放的GIF动画
+    pub gif_player: Option<GifPlayer>,
+    //WiFi帧差分解码器，WebSocket 新连接和连接关闭时重置
+    #[serde(skip)]
+    pub delta_decoder: DeltaDecoder,
+}
+
+/// 尝试直接将图像绘制到屏幕，跳过画布创建
+/// 这是一个内存优化函数，用于处理单一全屏图像的场景
+/// 可节省约450KB内存（320x480 RGB888画布）
+///
+/// 返回值：
+/// - Some(Ok(())) 表示绘制成功
+/// - Some(Err(e)) 表示绘制失败
+/// - None 表示不适合直接绘制，需要走正常画布流程
+fn try_draw_image_direct(screen: &mut impl Screen, image_cache: &HashMap<String, ImageCache>, image: &Image) -> Option<Result<()>> {
+    // 处理缓存的图像
+    if let Some(key) = &image.key {
+        return match image_cache.get(key).map(ImageCache::image) {
+            Some(ImageCache::RgbImage(img)) => Some(screen.draw_rgb_image(0, 0, img)),
+            // 不透明的RGB565图像无需转换，直接发送到屏幕
+            Some(ImageCache::Rgb565(img)) if !img.has_alpha() => {
+                Some(screen.draw_rgb565(0, 0, img.width() as u16, img.height() as u16, img.pixels()))
+            }
+            // 带透明通道的图像需要与背景混合，走正常流程
+            Some(_) => None,
+            None => Some(Err(anyhow!("image key not exist:{key}"))),
+        };
+    }
+
+    // 处理base64编码的图像
+    if let Some(b64) = &image.base64 {
+        let image_data = match decode_base64(b64.as_str()) {
+            Ok(d) => d,
+            Err(e) => return Some(Err(e)),
+        };
+        let mime = mimetype::detect(&image_data);
+        if mime.extension.ends_with("jpg") || mime.extension.ends_with("jpeg") {
+            // JPEG直接解码为RGB565并绘制，节省大量内存
+            return match decode_jpeg_to_rgb565(&image_data) {
+                Ok((w, h, rgb565_data)) => Some(screen.draw_rgb565(0, 0, w, h, &rgb565_data)),
+                Err(e) => {
+                    log::warn!("[DIRECT_DRAW] JPEG decode failed, falling back to canvas: {:?}", e);
+                    None
+                }
+            };
+        }
+        // PNG/GIF等格式，解码后直接绘制
+        return match image::load_from_memory(&image_data) {
+            Ok(img) => Some(screen.draw_rgb_image(0, 0, &img.to_rgb8())),
+            Err(e) => Some(Err(anyhow!("decode image: {:?}", e))),
+        };
+    }
+
+    // 没有key也没有base64，返回错误
+    Some(Err(anyhow!("请填写图像的\"key\"或者\"base64\"字符串")))
+}
+
+/// 绘制元素列表，单张全屏图像直接绘制，其他情况分条渲染
+pub fn draw_elements(
+    screen: &mut impl Screen,
+    font_cache: &HashMap<String, FontVec>,
+    image_cache: &HashMap<String, ImageCache>,
+    elements: &[Element],
+) -> Result<()> {
+    // 优化：检测是否是单一全屏图像，如果是则直接绘制到屏幕，跳过画布创建
+    if elements.len() == 1 {
+        if let Element::Image(image) = &elements[0] {
+            if image.x == 0 && image.y == 0 && !image.has_transform() && elements[0].opacity() >= 1.0 {
+                if let Some(result) = try_draw_image_direct(screen, image_cache, image) {
+                    return result;
+                }
+            }
+        }
+    }
+
+    // 分条渲染，不创建全屏画布（320x480 RGB888画布约450KB）
+    let (width, height) = screen.size();
+    let (width, height) = (width as u32, height as u32);
+    let band_height = band_height(width, screen.render_band_height() as u32);
+    let font = screen.font();
+    let screen_area = Bounds::new(0, 0, width, height);
+    render_elements_banded(screen_area, band_height, &font, font_cache, image_cache, elements, |x, y, band| {
+        let pixels = rgb_image_to_rgb565(band, 0, 0, 0);
+        screen.draw_rgb565(x, y, band.width() as u16, band.height() as u16, &pixels)
+    })
+}
+
+impl Device {
+    pub fn new(image_cache: LruImageCache, image_store: Option<ImageStore>) -> Self {
+        Self { image_cache, image_store, ..Default::default() }
+    }
+
+    /// 加载元素中用到的图片后绘制
+    pub fn draw_elements(&mut self, screen: &mut impl Screen, elements: &[Element]) -> Result<()> {
+        //画布覆盖整个屏幕，停止GIF播放
+        self.stop_gif();
+        if let Some(store) = self.image_store.as_ref() {
+            self.image_cache.load_images(store, elements.iter())?;
+        }
+        draw_elements(screen, &self.font_cache, self.image_cache.images(), elements)
+            .map_err(|err| anyhow!("draw elements: {err:?}"))?;
+        screen.flush();
+        Ok(())
+    }
+
+    /// 绘制 JSON 格式的元素列表
+    pub fn draw_json_elements(&mut self, screen: &mut impl Screen, json: &str) -> Result<()> {
+        let elements: Box<Vec<Element>> = Box::new(serde_json::from_str(json)
+            .map_err(|err| anyhow!("parse elements {err:?} json:`{json}`"))?);
+        self.draw_elements(screen, &elements)
+    }
+
+    /// 替换模板中的变量后绘制
+    pub fn render_template(&mut self, screen: &mut impl Screen, params: &RenderTemplate) -> Result<()> {
+        let elements = self.templates.render(params)?;
+        self.draw_elements(screen, &elements)
+    }
+
+    /// 应用场景补丁，只重绘变化的区域
+    pub fn update_scene(&mut self, screen: &mut impl Screen, patches: Vec<ScenePatch>) -> Result<()> {
+        for patch in patches {
+            self.scene.apply(patch)?;
+        }
+        if let Some(store) = self.image_store.as_ref() {
+            self.image_cache.load_images(store, self.scene.elements())?;
+        }
+        let (width, height) = screen.size();
+        let band_height = band_height(width as u32, screen.render_band_height() as u32);
+        let font = screen.font();
+        //只有重绘的区域与GIF重叠时才停止播放
+        let mut overlaps_gif = false;
+        let gif_player = self.gif_player.as_ref();
+        let size = (width as u32, height as u32);
+        self.scene.render(size, band_height, &font, &self.font_cache, self.image_cache.images(), |x, y, image| {
+            let (w, h) = (image.width() as u16, image.height() as u16);
+            overlaps_gif |= gif_player.is_some_and(|player| player.overlaps(x, y, w, h));
+            let pixels = rgb_image_to_rgb565(image, 0, 0, 0);
+            screen.draw_rgb565(x, y, w, h, &pixels)
+        })?;
+        if overlaps_gif {
+            self.stop_gif();
+        }
+        screen.flush();
+        Ok(())
+    }
+
+    /// 处理 JSON 格式的文本消息，截图消息返回 (回复的topic, PNG数据)，由调用者发布
+    pub fn handle_text_message(&mut self, screen: &mut impl Screen, json: &str) -> Result<Option<(String, Vec<u8>)>> {
+        let msg: Box<TextMessage> = Box::new(serde_json::from_str(json)
+            .map_err(|err| anyhow!("parse message {err:?} json:`{json}`"))?);
+
+        match *msg {
+            TextMessage::Draw(elements) => {
+                self.draw_elements(screen, &elements)?;
+            }
+            TextMessage::Upload((key, base64)) => {
+                self.upload_image(key, &decode_base64(&base64)?)?;
+            }
+            TextMessage::DeleteImage(key) => {
+                self.delete_image(&key)?;
+            }
+            TextMessage::PinImage(key) => {
+                self.pin_image(&key, true)?;
+            }
+            TextMessage::UnpinImage(key) => {
+                self.pin_image(&key, false)?;
+            }
+            TextMessage::SpriteSheet((key, sheet)) => {
+                self.set_sprite_sheet(&key, sheet)?;
+            }
+            TextMessage::UploadFont((key, base64)) => {
+                self.upload_font(key, *decode_base64(&base64)?)?;
+            }
+            TextMessage::DeleteFont(key) => {
+                self.delete_font(&key);
+            }
+            TextMessage::Scene(patches) => {
+                self.update_scene(screen, patches)?;
+            }
+            TextMessage::Template((name, elements)) => {
+                self.templates.set(&name, elements)?;
+            }
+            TextMessage::Render(params) => {
+                self.render_template(screen, &params)?;
+            }
+            TextMessage::PlayGif(params) => {
+                self.play_gif(screen, params)?;
+            }
+            TextMessage::StopGif => {
+                self.stop_gif();
+            }
+            TextMessage::Screenshot(topic) => {
+                return Ok(Some((topic, screen.screenshot_png()?)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 所有图片的key，flash未挂载时为内存缓存中的图片
+    pub fn image_keys(&self) -> Vec<String> {
+        match self.image_store.as_ref().map(|store| store.list()) {
+            Some(Ok(images)) => images.into_iter().map(|image| image.key).collect(),
+            _ => self.image_cache.keys(),
+        }
+    }
+
+    /// 保存上传的图片到flash并解码放入缓存，返回所有图片的key
+    pub fn upload_image(&mut self, key: String, data: &[u8]) -> Result<Vec<String>> {
+        //先解码，确认是有效的图片，缓存中保存为RGB565
+        let mut image = decode_image_rgb565(data)?;
+
+        //先保存到flash，保存失败时缓存中的老图片仍然可用
+        match self.image_store.as_ref() {
+            Some(store) => {
+                store.save(&key, data)?;
+                //重新上传时保留原来的精灵图定义
+                image = with_sprite_sheet(image, store.sprite_sheet(&key)?);
+            }
+            None => {
+                //没有flash存储时被淘汰的图片无法重新加载，缓存放不下时拒绝上传
+                let stats = self.image_cache.stats();
+                let old = self.image_cache.get(&key).map_or(0, image_size);
+                if stats.used + stats.reserved - old + image_size(&image) > stats.budget {
+                    return Err(anyhow!("图片存储未挂载，图片缓存已满: 预算{}字节, 已用{}字节", stats.budget, stats.used));
+                }
+            }
+        }
+
+        //删除老的图片
+        drop(self.image_cache.remove(&key));
+
+        //超出缓存预算时淘汰最久未使用的图片，绘制需要时再从flash中加载
+        self.image_cache.insert(key, image);
+        Ok(self.image_keys())
+    }
+
+    pub fn delete_image(&mut self, key: &str) -> Result<Vec<String>> {
+        self.image_cache.remove(key);
+        if self.image_cache.pinned().iter().any(|k| k == key) {
+            self.pin_image(key, false)?;
+        }
+        if let Some(store) = self.image_store.as_ref() {
+            store.remove(key)?;
+        }
+        Ok(self.image_keys())
+    }
+
+    /// 设置图片的精灵图定义，None 时删除
+    pub fn set_sprite_sheet(&mut self, key: &str, sheet: Option<SpriteSheet>) -> Result<()> {
+        if let Some(sheet) = sheet.as_ref() {
+            sheet.check()?;
+        }
+        match self.image_store.as_ref() {
+            Some(store) => {
+                store.save_sprite_sheet(key, sheet.as_ref())?;
+                self.image_cache.set_sprite_sheet(key, sheet);
+            }
+            None => {
+                if !self.image_cache.set_sprite_sheet(key, sheet) {
+                    return Err(anyhow!("image key not exist:{key}"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 固定或取消固定图片，固定的图片常驻缓存不会被淘汰，返回所有固定的图片key
+    pub fn pin_image(&mut self, key: &str, pin: bool) -> Result<Vec<String>> {
+        match self.image_store.as_ref() {
+            Some(store) => {
+                if pin {
+                    if !store.contains(key) {
+                        return Err(anyhow!("image key not exist:{key}"));
+                    }
+                    self.image_cache.load(store, &[key])?;
+                }
+                self.image_cache.set_pinned(key, pin);
+                store.save_pinned(&self.image_cache.pinned())?;
+            }
+            None => {
+                if pin && !self.image_cache.contains_key(key) {
+                    return Err(anyhow!("image key not exist:{key}"));
+                }
+                self.image_cache.set_pinned(key, pin);
+            }
+        }
+        Ok(self.image_cache.pinned())
+    }
+
+    /// 图片编码为PNG
+    pub fn download_image(&self, key: &str) -> Result<Vec<u8>> {
+        //不在内存缓存中的图片从flash中解码，不放入缓存
+        let loaded;
+        let image = match self.image_cache.get(key) {
+            Some(img) => img,
+            None => match self.image_store.as_ref() {
+                Some(store) if store.contains(key) => {
+                    loaded = decode_image_rgb565(&store.load(key)?)?;
+                    &loaded
+                }
+                _ => return Err(anyhow!("key not exist")),
+            },
+        };
+        let mut out = vec![];
+        let encoder = PngEncoder::new(&mut out);
+        match image.image() {
+            ImageCache::RgbImage(img) => {
+                encoder.write_image(img, img.width(), img.height(), image::ExtendedColorType::Rgb8)?;
+            }
+            ImageCache::RgbaImage(img) => {
+                encoder.write_image(img, img.width(), img.height(), image::ExtendedColorType::Rgba8)?;
+            }
+            img => {
+                let img = img.to_rgba_image();
+                encoder.write_image(&img, img.width(), img.height(), image::ExtendedColorType::Rgba8)?;
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn font_keys(&self) -> Vec<String> {
+        self.font_cache.keys().map(|k| k.to_string()).collect()
+    }
+
+    /// 上传并缓存一个 TTF/OTF 字体，返回缓存中所有字体的key
+    pub fn upload_font(&mut self, key: String, data: Vec<u8>) -> Result<Vec<String>> {
+        //删除老的字体
+        drop(self.font_cache.remove(&key));
+
+        if self.font_cache.len() >= MAX_FONT_CACHE {
+            return Err(anyhow!("最多缓存{MAX_FONT_CACHE}个字体"));
+        }
+
+        self.font_cache.insert(key, load_font(data)?);
+        Ok(self.font_keys())
+    }
+
+    pub fn delete_font(&mut self, key: &str) -> Vec<String> {
+        self.font_cache.remove(key);
+        self.font_keys()
+    }
+
+    /// 解码已上传的 GIF 并开始播放，正在播放的动画会被替换
+    /// 只设置播放状态，由调用者的播放线程调用 draw_gif_frame 绘制
+    pub fn play_gif(&mut self, screen: &impl Screen, params: PlayGif) -> Result<()> {
+        let (width, height) = screen.size();
+        if params.x >= width || params.y >= height {
+            return Err(anyhow!("GIF位置超出屏幕: ({},{})", params.x, params.y));
+        }
+        let store = self.image_store.as_ref().ok_or(anyhow!("图片存储未挂载，无法播放GIF"))?;
+        let data = store.load(&params.key)?;
+
+        //先停止原来的动画，释放帧占用的内存
+        self.stop_gif();
+        //帧计入图片缓存的预算，最多淘汰所有未固定的图片
+        let max_size = MAX_GIF_SIZE.min(self.image_cache.reservable());
+        let visible = ((width - params.x) as u32, (height - params.y) as u32);
+        let frames = decode_gif(&data, visible, max_size)?;
+        let player = GifPlayer::new(params, frames);
+        info!("play gif {} frames={} size={}", player.status().key, player.status().frames, player.size());
+        self.image_cache.reserve(player.size());
+        self.gif_player = Some(player);
+        Ok(())
+    }
+
+    /// 停止播放GIF，屏幕停留在当前帧
+    pub fn stop_gif(&mut self) {
+        if self.gif_player.take().is_some() {
+            self.image_cache.reserve(0);
+        }
+    }
+
+    /// 绘制区域与正在播放的GIF重叠时停止播放，避免新的内容被下一帧覆盖
+    fn stop_gif_in(&mut self, x: u16, y: u16, width: u16, height: u16) {
+        if self.gif_player.as_ref().is_some_and(|player| player.overlaps(x, y, width, height)) {
+            self.stop_gif();
+        }
+    }
+
+    /// 绘制当前帧并切换到下一帧，返回下一帧前需要等待的时间，没有动画时返回 None
+    pub fn draw_gif_frame(&mut self, screen: &mut impl Screen) -> Result<Option<Duration>> {
+        let player = match self.gif_player.as_mut() {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let (x, y) = player.position();
+        let frame = player.current();
+        let (w, h) = frame.image.dimensions();
+        screen.draw_rgb565(x, y, w as u16, h as u16, frame.image.pixels())?;
+        let delay = frame.delay;
+        if !player.advance() {
+            self.stop_gif();
+        }
+        screen.flush();
+        Ok(Some(delay))
+    }
+
+    /// 绘制 jpg/png/gif 图片，返回图片大小
+    pub fn draw_image(&mut self, screen: &mut impl Screen, data: &[u8]) -> Result<(u16, u16)> {
+        let mime = mimetype::detect(data);
+        let size = if mime.extension.ends_with("jpg") || mime.extension.ends_with("jpeg") {
+            let (w, h, data) = decode_jpeg_to_rgb565(data)?;
+            screen.draw_rgb565(0, 0, w, h, &data)?;
+            (w, h)
+        } else {
+            let image = image::load_from_memory(data)?.to_rgb8();
+            screen.draw_rgb_image(0, 0, &image)?;
+            (image.width() as u16, image.height() as u16)
+        };
+        self.stop_gif_in(0, 0, size.0, size.1);
+        screen.flush();
+        Ok(size)
+    }
+
+    /// 绘制全屏大端序RGB565数据，返回屏幕大小
+    pub fn draw_rgb565_fullscreen(&mut self, screen: &mut impl Screen, data: &[u8]) -> Result<(u16, u16)> {
+        let (width, height) = screen.size();
+        let len = width as usize * height as usize * 2;
+        if data.len() < len {
+            return Err(anyhow!("数据长度不足: {} < {len}", data.len()));
+        }
+        self.draw_rgb565_area(screen, 0, 0, width, height, &data[0..len])?;
+        Ok((width, height))
+    }
+
+    /// 绘制lz4压缩后的全屏RGB565数据，返回屏幕大小
+    pub fn draw_rgb565_lz4(&mut self, screen: &mut impl Screen, data: &[u8]) -> Result<(u16, u16)> {
+        let rgb565 = lz4_flex::decompress_size_prepended(data)?;
+        self.draw_rgb565_fullscreen(screen, &rgb565)
+    }
+
+    /// 绘制USB/WiFi帧协议中的一块RGB565区域
+    pub fn draw_rgb565_area(&mut self, screen: &mut impl Screen, x: u16, y: u16, width: u16, height: u16, rgb565: &[u8]) -> Result<()> {
+        self.stop_gif_in(x, y, width, height);
+        screen.draw_rgb565_u8array(x, y, width, height, rgb565)?;
+        screen.flush();
+        Ok(())
+    }
+
+    /// 处理WebSocket二进制消息，返回需要回复的文本/二进制消息
+    /// 没有屏幕时只处理测试和上传字体的消息
+    pub fn handle_ws_binary<S: Screen>(&mut self, screen: Option<&mut S>, data: &[u8]) -> Option<WsReply> {
+        // Check for echo test prefix - echo back the data
+        const ECHO_TEST_PREFIX: &[u8] = b"ECHO_TEST:";
+        if data.starts_with(ECHO_TEST_PREFIX) {
+            return Some(WsReply::Binary(data[ECHO_TEST_PREFIX.len()..].to_vec()));
+        }
+        // Check for speed test prefix (legacy)
+        const SPEED_TEST_PREFIX: &[u8] = b"SPEED_TEST:";
+        if data.starts_with(SPEED_TEST_PREFIX) {
+            let payload_len = data.len() - SPEED_TEST_PREFIX.len();
+            return Some(WsReply::Text(format!("OK:{} bytes", payload_len)));
+        }
+        // 上传字体: "UPLOAD_FONT:{key}\n" + 字体文件
+        const UPLOAD_FONT_PREFIX: &[u8] = b"UPLOAD_FONT:";
+        if data.starts_with(UPLOAD_FONT_PREFIX) {
+            let payload = &data[UPLOAD_FONT_PREFIX.len()..];
+            let result = match payload.iter().position(|b| *b == b'\n') {
+                None => Err(anyhow!("缺少字体key")),
+                Some(pos) => {
+                    let key = String::from_utf8_lossy(&payload[..pos]).to_string();
+                    self.upload_font(key, payload[pos + 1..].to_vec())
+                }
+            };
+            return Some(WsReply::Text(match result {
+                Ok(keys) => format!("{keys:?}"),
+                Err(err) => format!("upload font error:{err:?}"),
+            }));
+        }
+
+        let screen = match screen {
+            Some(v) => v,
+            None => {
+                error!("Display not configured!");
+                return None;
+            }
+        };
+
+        //判断图片类型
+        let mime = mimetype::detect(data);
+        if mime.extension.ends_with("jpg") || mime.extension.ends_with("jpeg")
+            || mime.extension.ends_with("gif") || mime.extension.ends_with("png") {
+            if let Err(err) = self.draw_image(screen, data) {
+                error!("image decode error! {err:?}");
+            }
+        } else if data.starts_with(b"RGB565") {
+            // 未压缩的RGB565数据(带RGB565前缀)
+            if let Err(err) = self.draw_rgb565_fullscreen(screen, &data[6..]) {
+                error!("rgb565: {err:?}");
+            }
+        } else if data.starts_with(WIFI_NOP_MAGIC) {
+            // 无变化帧：画面静止，跳过解码和绘制，直接返回ACK
+            // 这样上位机可以立即发送下一帧，大幅提升静止画面的响应速度
+            return Some(WsReply::Text("ACK".to_string()));
+        } else if data.starts_with(WIFI_KEY_MAGIC) || data.starts_with(WIFI_DLT_MAGIC) {
+            // WiFi帧差分协议处理 (带ACK确认机制)
+            if data.len() < 12 {
+                return None;
+            }
+            let is_key_frame = data.starts_with(WIFI_KEY_MAGIC);
+            let width = u16::from_be_bytes([data[8], data[9]]);
+            let height = u16::from_be_bytes([data[10], data[11]]);
+            let lz4_data = &data[12..];
+            // 差分帧但没有参考帧时，发送NACK让客户端发送关键帧
+            if !is_key_frame && !self.delta_decoder.has_reference_frame() {
+                self.delta_decoder.log_error("waiting for key frame");
+                return Some(WsReply::Text("NACK".to_string()));
+            }
+            self.stop_gif_in(0, 0, width, height);
+            let decode_result = if is_key_frame {
+                self.delta_decoder.decode_key_frame(lz4_data)
+            } else {
+                self.delta_decoder.decode_delta_frame(lz4_data)
+            };
+            match decode_result {
+                Ok(rgb565) => {
+                    let expected_size = width as usize * height as usize * 2;
+                    if rgb565.len() >= expected_size {
+                        let _ = screen.draw_rgb565_u8array(0, 0, width, height, &rgb565[0..expected_size]);
+                        screen.flush();
+                        // 发送ACK确认，客户端收到后才发送下一帧
+                        return Some(WsReply::Text("ACK".to_string()));
+                    }
+                }
+                Err(e) => {
+                    self.delta_decoder.log_error(e);
+                    self.delta_decoder.reset();
+                    return Some(WsReply::Text("NACK".to_string()));
+                }
+            }
+        } else {
+            // 兼容旧协议: lz4压缩数据
+            if let Err(err) = self.draw_rgb565_lz4(screen, data) {
+                error!("lz4 decode:{err:?}");
+            }
+        }
+        None
+    }
+
+    /// 新的WebSocket连接/连接关闭时重置帧差分解码器
+    pub fn reset_delta_decoder(&mut self) {
+        self.delta_decoder.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::FrameBuffer;
+
+    struct TestScreen {
+        framebuffer: FrameBuffer,
+        flushed: usize,
+    }
+
+    impl Screen for TestScreen {
+        fn size(&self) -> (u16, u16) {
+            self.framebuffer.get_screen_size()
+        }
+        fn font(&self) -> FontRef<'static> {
+            FontRef::try_from_slice(include_bytes!("../../VonwaonBitmap-12pxLite.otf")).unwrap()
+        }
+        fn render_band_height(&self) -> u16 {
+            0
+        }
+        fn draw_rgb565(&mut self, x: u16, y: u16, width: u16, height: u16, pixels: &[u16]) -> Result<()> {
+            self.framebuffer.draw_rgb565(x, y, width, height, pixels)
+        }
+        fn draw_rgb565_u8array(&mut self, x: u16, y: u16, width: u16, height: u16, pixels: &[u8]) -> Result<()> {
+            self.framebuffer.draw_rgb565_u8array(x, y, width, height, pixels)
+        }
+        fn draw_rgb_image(&mut self, x: u16, y: u16, image: &RgbImage) -> Result<()> {
+            self.framebuffer.draw_rgb_image(x, y, image)
+        }
+        fn screenshot_png(&self) -> Result<Vec<u8>> {
+            self.framebuffer.to_png()
+        }
+        fn flush(&mut self) {
+            self.flushed += 1;
+        }
+    }
+
+    #[test]
+    fn test_handle_text_message() {
+        let mut screen = TestScreen { framebuffer: FrameBuffer::new(16, 8), flushed: 0 };
+        let mut device = Device::default();
+        let draw = r##"{"Draw":[{"Rectangle":{"left":0,"top":0,"width":16,"height":8,"stroke_width":0,"fill_color":"#f00"}}]}"##;
+        assert_eq!(device.handle_text_message(&mut screen, draw).unwrap(), None);
+        assert_eq!(screen.flushed, 1);
+        assert_eq!(screen.framebuffer.to_rgb_image().get_pixel(3, 3).0, [255, 0, 0]);
+
+        let (topic, png) = device.handle_text_message(&mut screen, r#"{"Screenshot":"shot"}"#).unwrap().unwrap();
+        assert_eq!(topic, "shot");
+        assert_eq!(image::load_from_memory(&png).unwrap().to_rgb8(), screen.framebuffer.to_rgb_image());
+
+        // 没有flash存储时需要内存缓存放得下
+        device.image_cache.set_budget(0);
+        let mut png = vec![];
+        PngEncoder::new(&mut png).write_image(&[0; 3], 1, 1, image::ExtendedColorType::Rgb8).unwrap();
+        let upload = format!(r#"{{"Upload":["a","{}"]}}"#, data_encoding::BASE64.encode(&png));
+        let err = device.handle_text_message(&mut screen, &upload).unwrap_err();
+        assert!(err.to_string().contains("图片缓存已满"));
+        assert!(device.handle_text_message(&mut screen, r#"{"Unknown":1}"#).is_err());
+    }
+
+    #[test]
+    fn test_handle_ws_binary() {
+        let mut screen = TestScreen { framebuffer: FrameBuffer::new(2, 1), flushed: 0 };
+        let mut device = Device::default();
+        assert_eq!(device.handle_ws_binary(None::<&mut TestScreen>, b"ECHO_TEST:abc"), Some(WsReply::Binary(b"abc".to_vec())));
+        assert_eq!(device.handle_ws_binary(Some(&mut screen), WIFI_NOP_MAGIC), Some(WsReply::Text("ACK".to_string())));
+
+        // 没有关键帧时差分帧返回NACK
+        let mut frame = WIFI_DLT_MAGIC.to_vec();
+        frame.extend_from_slice(&[0, 2, 0, 1]);
+        frame.extend_from_slice(&lz4_flex::compress_prepend_size(&[0; 4]));
+        assert_eq!(device.handle_ws_binary(Some(&mut screen), &frame), Some(WsReply::Text("NACK".to_string())));
+
+        let mut frame = WIFI_KEY_MAGIC.to_vec();
+        frame.extend_from_slice(&[0, 2, 0, 1]);
+        frame.extend_from_slice(&lz4_flex::compress_prepend_size(&[0xf8, 0x00, 0x07, 0xe0]));
+        assert_eq!(device.handle_ws_binary(Some(&mut screen), &frame), Some(WsReply::Text("ACK".to_string())));
+        let image = screen.framebuffer.to_rgb_image();
+        assert_eq!((image.get_pixel(0, 0).0[0], image.get_pixel(1, 0).0[1]), (255, 255));
+    }
+
+    #[test]
+    fn test_draw_stops_gif() {
+        let dir = std::env::temp_dir().join(format!("wifi-screen-device-gif-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = ImageStore::new(dir, 64 * 1024);
+        let mut gif = vec![];
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut gif);
+            for color in [[255, 0, 0, 255], [0, 0, 255, 255]] {
+                let frame = image::RgbaImage::from_pixel(8, 8, image::Rgba(color));
+                encoder.encode_frame(image::Frame::new(frame)).unwrap();
+            }
+        }
+        store.save("g", &gif).unwrap();
+        let mut screen = TestScreen { framebuffer: FrameBuffer::new(32, 16), flushed: 0 };
+        let mut device = Device::new(LruImageCache::new(1024), Some(store));
+        let play = r#"{"PlayGif":{"key":"g","x":0,"y":0,"loops":0}}"#;
+        let rect = |id: &str, left: i32| format!(
+            r#"{{"Scene":[{{"Add":{{"id":"{id}","Rectangle":{{"left":{left},"top":0,"width":4,"height":4,"stroke_width":0,"fill_color":"white","stroke_color":null}}}}}}]}}"#
+        );
+        device.handle_text_message(&mut screen, r#"{"Scene":[{"Set":[]}]}"#).unwrap();
+
+        // 帧计入图片缓存的预算
+        device.handle_text_message(&mut screen, play).unwrap();
+        assert_eq!(device.image_cache.stats().reserved, 2 * 8 * 8 * 2);
+        // 不重叠的局部刷新不影响播放，重叠时停止并释放预留的内存
+        device.handle_text_message(&mut screen, &rect("a", 20)).unwrap();
+        assert!(device.gif_player.is_some());
+        device.handle_text_message(&mut screen, &rect("b", 4)).unwrap();
+        assert!(device.gif_player.is_none());
+        assert_eq!(device.image_cache.stats().reserved, 0);
+
+        device.handle_text_message(&mut screen, play).unwrap();
+        device.draw_rgb565_area(&mut screen, 8, 8, 1, 1, &[0, 0]).unwrap();
+        assert!(device.gif_player.is_some());
+        device.draw_rgb565_area(&mut screen, 7, 7, 1, 1, &[0, 0]).unwrap();
+        assert!(device.gif_player.is_none());
+
+        device.handle_text_message(&mut screen, play).unwrap();
+        device.handle_text_message(&mut screen, r#"{"Draw":[]}"#).unwrap();
+        assert!(device.gif_player.is_none());
+
+        // 超出图片缓存预算时拒绝播放
+        device.image_cache.set_budget(100);
+        assert!(device.handle_text_message(&mut screen, play).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use ab_glyph::{point, Font, FontRef, FontVec, Glyph, GlyphId, OutlineCurve, OutlinedGlyph, Point, PxScale, ScaleFont};
+use anyhow::{anyhow, Result};
+
+// 字体：上传的 TTF/OTF 字体缓存，缺字时回退到内置字体
+
+/// 最多缓存的字体数量
+pub const MAX_FONT_CACHE: usize = 3;
+
+/// 解析上传的 TTF/OTF 字体文件
+pub fn load_font(data: Vec<u8>) -> Result<FontVec> {
+    FontVec::try_from_vec(data).map_err(|err| anyhow!("invalid font: {err:?}"))
+}
+
+/// 绘制文字使用的字体
+/// font 为上传的字体，其中没有的字使用内置字体 fallback 绘制
+#[derive(Clone, Copy)]
+pub struct TextFont<'a> {
+    pub font: Option<&'a FontVec>,
+    pub fallback: &'a FontRef<'a>,
+}
+
+impl<'a> TextFont<'a> {
+    /// 只使用内置字体
+    pub fn builtin(fallback: &'a FontRef<'a>) -> Self {
+        Self { font: None, fallback }
+    }
+
+    /// 从字体缓存中查找 Text.font 指定的字体，未指定时使用内置字体
+    pub fn find(fallback: &'a FontRef<'a>, font_cache: &'a HashMap<String, FontVec>, key: Option<&str>) -> Result<Self> {
+        let font = match key {
+            Some(key) => Some(font_cache.get(key).ok_or_else(|| anyhow!("font key not exist:{key}"))?),
+            None => None,
+        };
+        Ok(Self { font, fallback })
+    }
+
+    /// 行高(像素)
+    pub fn height(&self, size: f32) -> f32 {
+        match self.font {
+            Some(font) => font.as_scaled(size).height(),
+            None => self.fallback.as_scaled(size).height(),
+        }
+    }
+
+    /// 基线到行顶部的距离(像素)
+    pub(crate) fn ascent(&self, size: f32) -> f32 {
+        match self.font {
+            Some(font) => font.as_scaled(size).ascent(),
+            None => self.fallback.as_scaled(size).ascent(),
+        }
+    }
+
+    /// 字符是否使用上传的字体绘制
+    fn has_glyph(&self, c: char) -> bool {
+        self.font.is_some_and(|font| font.glyph_id(c).0 != 0)
+    }
+
+    fn layout_run(
+        &self,
+        uploaded: bool,
+        scale: PxScale,
+        text: &str,
+        start: f32,
+        ascent: f32,
+        f: &mut impl FnMut(OutlinedGlyph, ab_glyph::Rect, bool),
+    ) -> f32 {
+        let mut f = |g, bb| f(g, bb, uploaded);
+        match self.font {
+            Some(font) if uploaded => layout_run(scale, font, text, start, ascent, &mut f),
+            _ => layout_run(scale, self.fallback, text, start, ascent, &mut f),
+        }
+    }
+
+    /// 字的轮廓，坐标为像素（y 向下），原点与 glyph.position 相同
+    fn glyph_curves(&self, uploaded: bool, glyph: &Glyph) -> Vec<OutlineCurve> {
+        fn curves(font: &impl Font, glyph: &Glyph) -> Vec<OutlineCurve> {
+            let Some(outline) = font.outline(glyph.id) else {
+                return vec![];
+            };
+            let factor = font.as_scaled(glyph.scale).scale_factor();
+            let p = |p: &Point| point(glyph.position.x + p.x * factor.horizontal, glyph.position.y - p.y * factor.vertical);
+            outline
+                .curves
+                .iter()
+                .map(|curve| match curve {
+                    OutlineCurve::Line(p0, p1) => OutlineCurve::Line(p(p0), p(p1)),
+                    OutlineCurve::Quad(p0, p1, p2) => OutlineCurve::Quad(p(p0), p(p1), p(p2)),
+                    OutlineCurve::Cubic(p0, p1, p2, p3) => OutlineCurve::Cubic(p(p0), p(p1), p(p2), p(p3)),
+                })
+                .collect()
+        }
+        match self.font {
+            Some(font) if uploaded => curves(font, glyph),
+            _ => curves(self.fallback, glyph),
+        }
+    }
+}
+
+/// 用同一个字体排列一段文字，返回排列后的宽度
+fn layout_run(
+    scale: PxScale,
+    font: &impl Font,
+    text: &str,
+    start: f32,
+    ascent: f32,
+    f: &mut impl FnMut(OutlinedGlyph, ab_glyph::Rect),
+) -> f32 {
+    let font = font.as_scaled(scale);
+
+    let mut w = start;
+    let mut prev: Option<GlyphId> = None;
+
+    for c in text.chars() {
+        let glyph_id = font.glyph_id(c);
+        let glyph = glyph_id.with_scale_and_position(scale, point(w, ascent));
+        w += font.h_advance(glyph_id);
+        if let Some(g) = font.outline_glyph(glyph) {
+            if let Some(prev) = prev {
+                w += font.kern(glyph_id, prev);
+            }
+            prev = Some(glyph_id);
+            let bb = g.px_bounds();
+            f(g, bb);
+        }
+    }
+    w
+}
+
+/// 排列一行文字，按字符所在的字体分段排列，所有字共用一条基线
+pub(crate) fn layout_glyphs(
+    scale: impl Into<PxScale> + Copy,
+    font: &TextFont,
+    text: &str,
+    mut f: impl FnMut(OutlinedGlyph, ab_glyph::Rect),
+) -> (u32, u32) {
+    layout_line(scale, font, text, |g, bb, _| f(g, bb))
+}
+
+/// 排列一行文字，返回每个字的轮廓（像素坐标，原点为这一行的左上角），用于旋转后光栅化
+pub(crate) fn layout_outlines(
+    scale: impl Into<PxScale> + Copy,
+    font: &TextFont,
+    text: &str,
+    mut f: impl FnMut(Vec<OutlineCurve>),
+) {
+    layout_line(scale, font, text, |g, _, uploaded| f(font.glyph_curves(uploaded, g.glyph())));
+}
+
+/// f 的最后一个参数为是否使用上传的字体
+fn layout_line(
+    scale: impl Into<PxScale> + Copy,
+    font: &TextFont,
+    text: &str,
+    mut f: impl FnMut(OutlinedGlyph, ab_glyph::Rect, bool),
+) -> (u32, u32) {
+    if text.is_empty() {
+        return (0, 0);
+    }
+    let scale = scale.into();
+    let ascent = font.ascent(scale.y);
+
+    let mut w = 0.0;
+    let mut run = String::new();
+    let mut run_uploaded = false;
+    for c in text.chars() {
+        let uploaded = font.has_glyph(c);
+        if uploaded != run_uploaded && !run.is_empty() {
+            w = font.layout_run(run_uploaded, scale, &run, w, ascent, &mut f);
+            run.clear();
+        }
+        run_uploaded = uploaded;
+        run.push(c);
+    }
+    w = font.layout_run(run_uploaded, scale, &run, w, ascent, &mut f);
+
+    let w = w.ceil();
+    let h = font.height(scale.y).ceil();
+    assert!(w >= 0.0);
+    assert!(h >= 0.0);
+    (1 + w as u32, h as u32)
+}
+
+/// 计算一行文字的宽高
+pub fn text_size(scale: impl Into<PxScale> + Copy, font: &TextFont, text: &str) -> (u32, u32) {
+    layout_glyphs(scale, font, text, |_, _| {})
+}
@@ -0,0 +1,107 @@
+use anyhow::{anyhow, Result};
+use image::codecs::png::PngEncoder;
+use image::{ImageEncoder, RgbImage};
+
+use crate::rgb565::{rgb565_to_rgb888, rgb_image_to_rgb565};
+
+/// 屏幕显存的副本 (RGB565)，用于截图
+/// 固件中 display.rs 的 draw_*_fast 函数绘制时同步写入（不含色调调整），设备模拟器用它模拟屏幕
+/// 超出屏幕的部分会被裁剪
+pub struct FrameBuffer {
+    width: u16,
+    height: u16,
+    pixels: Vec<u16>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize],
+        }
+    }
+
+    pub fn get_screen_size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    fn put_pixel(&mut self, x: usize, y: usize, pixel: u16) {
+        if x < self.width as usize && y < self.height as usize {
+            self.pixels[y * self.width as usize + x] = pixel;
+        }
+    }
+
+    /// 对应 display::draw_rgb_image_fast
+    pub fn draw_rgb_image(&mut self, x: u16, y: u16, image: &RgbImage) -> Result<()> {
+        let pixels = rgb_image_to_rgb565(image, 0, 0, 0);
+        self.draw_rgb565(x, y, image.width() as u16, image.height() as u16, &pixels)
+    }
+
+    /// 对应 display::draw_rgb565_fast，pixels 为大端序 u16
+    pub fn draw_rgb565(&mut self, x: u16, y: u16, width: u16, height: u16, pixels: &[u16]) -> Result<()> {
+        if pixels.len() != width as usize * height as usize {
+            return Err(anyhow!("error: pixels.len() {} != expected {}", pixels.len(), width as usize * height as usize));
+        }
+        for (i, pixel) in pixels.iter().enumerate() {
+            let (px, py) = (i % width as usize, i / width as usize);
+            self.put_pixel(x as usize + px, y as usize + py, u16::from_be(*pixel));
+        }
+        Ok(())
+    }
+
+    /// 对应 display::draw_rgb565_u8array_fast，pixels 为大端序字节
+    pub fn draw_rgb565_u8array(&mut self, x: u16, y: u16, width: u16, height: u16, pixels: &[u8]) -> Result<()> {
+        let expected_bytes = width as usize * height as usize * 2;
+        if pixels.len() != expected_bytes {
+            return Err(anyhow!("error: pixels.len() {} != expected {}", pixels.len(), expected_bytes));
+        }
+        for (i, chunk) in pixels.chunks_exact(2).enumerate() {
+            let (px, py) = (i % width as usize, i / width as usize);
+            self.put_pixel(x as usize + px, y as usize + py, u16::from_be_bytes([chunk[0], chunk[1]]));
+        }
+        Ok(())
+    }
+
+    pub fn to_rgb_image(&self) -> RgbImage {
+        RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let (r, g, b) = rgb565_to_rgb888(self.pixels[y as usize * self.width as usize + x as usize]);
+            image::Rgb([r, g, b])
+        })
+    }
+
+    /// 将当前屏幕内容编码为PNG
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let image = self.to_rgb_image();
+        let mut out = vec![];
+        PngEncoder::new(&mut out).write_image(
+            &image,
+            image.width(),
+            image.height(),
+            image::ExtendedColorType::Rgb8,
+        )?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_clipped() {
+        let mut fb = FrameBuffer::new(4, 3);
+        let red = 0xF800u16.to_be();
+        fb.draw_rgb565(3, 2, 2, 2, &[red; 4]).unwrap();
+        fb.draw_rgb565_u8array(0, 0, 1, 1, &[0x07, 0xE0]).unwrap();
+        assert!(fb.draw_rgb565(0, 0, 2, 2, &[0; 3]).is_err());
+
+        let image = fb.to_rgb_image();
+        assert_eq!(image.get_pixel(3, 2).0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(0, 0).0, [0, 255, 0]);
+        assert_eq!(image.get_pixel(2, 2).0, [0, 0, 0]);
+
+        let png = image::load_from_memory(&fb.to_png().unwrap()).unwrap().to_rgb8();
+        assert_eq!(png, image);
+    }
+}
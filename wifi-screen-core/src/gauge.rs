@@ -0,0 +1,353 @@
+use serde::Deserialize;
+
+use crate::chart::label;
+use crate::gradient::Fill;
+use crate::render::{Arc, CSSColor, Circle, Element, Line, RoundedRectangle, Text};
+use crate::text_layout::{TextAlign, TextVAlign};
+
+// 仪表：圆形仪表盘、进度条、指针表
+// 与图表一样只传数值，在设备上展开为 Arc/RoundedRectangle/Line/Text 等基本元素绘制
+
+/// 阈值 (值, 颜色)：数值大于等于阈值时使用对应的颜色
+pub type Threshold = (f32, CSSColor);
+
+/// 默认范围 0~100
+const DEFAULT_MIN: f32 = 0.0;
+const DEFAULT_MAX: f32 = 100.0;
+/// 默认从左下方顺时针转到右下方
+const DEFAULT_ANGLE_START: f32 = 135.0;
+const DEFAULT_ANGLE_SWEEP: f32 = 270.0;
+/// 指针表默认的刻度数量
+const DEFAULT_TICKS: u32 = 10;
+
+/// 圆形仪表盘：底部轨道 + 表示数值的圆弧，中间显示数值
+#[derive(Clone, Deserialize)]
+pub struct Gauge {
+    pub top_left: (i32, i32),
+    pub diameter: u32,
+    pub value: f32,
+    /// 范围，默认 0~100
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    /// 起始角度和扫过的角度，默认 135 和 270
+    pub angle_start: Option<f32>,
+    pub angle_sweep: Option<f32>,
+    /// 圆弧宽度，默认为直径的 1/10
+    pub stroke_width: Option<u32>,
+    /// 数值圆弧的颜色
+    pub color: CSSColor,
+    /// 轨道颜色，不设置时不绘制
+    pub track_color: Option<CSSColor>,
+    /// 数值达到阈值时圆弧使用阈值的颜色，如 [[60,"orange"],[80,"red"]]
+    #[serde(default)]
+    pub thresholds: Vec<Threshold>,
+    /// 数值文字的颜色，不设置时不显示
+    pub label_color: Option<CSSColor>,
+    /// 数值文字的字号，默认为直径的 1/5
+    pub label_size: Option<f32>,
+    /// 数值后面的单位，如 "℃"
+    pub unit: Option<String>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+/// 进度条
+#[derive(Clone, Deserialize)]
+pub struct ProgressBar {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    pub value: f32,
+    /// 范围，默认 0~100
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    /// 进度的颜色或渐变
+    pub color: Fill,
+    /// 背景色，不设置时不绘制
+    pub background: Option<CSSColor>,
+    /// 圆角半径，默认为短边的一半
+    pub radius: Option<u32>,
+    /// 竖直进度条，从下往上增长
+    #[serde(default)]
+    pub vertical: bool,
+    /// 数值达到阈值时使用阈值的颜色
+    #[serde(default)]
+    pub thresholds: Vec<Threshold>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+}
+
+/// 指针表：表盘外圈（阈值区间显示为对应的颜色）、刻度和指针
+#[derive(Clone, Deserialize)]
+pub struct Meter {
+    pub top_left: (i32, i32),
+    pub diameter: u32,
+    pub value: f32,
+    /// 范围，默认 0~100
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    /// 起始角度和扫过的角度，默认 135 和 270
+    pub angle_start: Option<f32>,
+    pub angle_sweep: Option<f32>,
+    /// 表盘外圈的宽度，默认为直径的 1/20
+    pub stroke_width: Option<u32>,
+    /// 表盘外圈的颜色
+    pub color: CSSColor,
+    /// 从阈值到下一个阈值（或最大值）的外圈使用阈值的颜色
+    #[serde(default)]
+    pub thresholds: Vec<Threshold>,
+    /// 刻度数量，默认 10，0 为不绘制
+    pub ticks: Option<u32>,
+    /// 刻度颜色，默认与外圈相同
+    pub tick_color: Option<CSSColor>,
+    pub needle_color: CSSColor,
+    /// 指针宽度，默认 2
+    pub needle_width: Option<u32>,
+    /// 数值文字的颜色，不设置时不显示
+    pub label_color: Option<CSSColor>,
+    /// 数值文字的字号，默认为直径的 1/8
+    pub label_size: Option<f32>,
+    /// 数值后面的单位
+    pub unit: Option<String>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+/// 数值在范围中的比例 0~1
+fn fraction(value: f32, min: Option<f32>, max: Option<f32>) -> f32 {
+    let (min, max) = (min.unwrap_or(DEFAULT_MIN), max.unwrap_or(DEFAULT_MAX));
+    if max <= min {
+        return 0.0;
+    }
+    ((value - min) / (max - min)).clamp(0.0, 1.0)
+}
+
+/// 数值达到的最大阈值的颜色
+fn threshold_color(value: f32, thresholds: &[Threshold]) -> Option<&CSSColor> {
+    thresholds.iter().filter(|(t, _)| value >= *t).max_by(|a, b| a.0.total_cmp(&b.0)).map(|(_, color)| color)
+}
+
+/// 圆形仪表的几何参数
+struct Dial {
+    top_left: (i32, i32),
+    diameter: u32,
+    angle_start: f32,
+    angle_sweep: f32,
+    antialias: bool,
+}
+
+impl Dial {
+    /// 圆心到角度 t（0~1 对应起始到结束角度）方向上距离 r 的点
+    fn point(&self, t: f32, r: f32) -> (i32, i32) {
+        let radius = (self.diameter.max(1) - 1) as f32 / 2.0;
+        let angle = (self.angle_start + self.angle_sweep * t).to_radians();
+        // 相对左上角取整后再平移，分条渲染时每个条带的结果相同
+        let (dx, dy) = ((radius + r * angle.cos()).round(), (radius + r * angle.sin()).round());
+        (self.top_left.0 + dx as i32, self.top_left.1 + dy as i32)
+    }
+
+    /// 比例 from~to 之间宽度为 stroke_width 的圆弧，外侧与表盘边缘对齐
+    fn arc(&self, from: f32, to: f32, stroke_width: u32, color: &CSSColor) -> Option<Element> {
+        if to <= from {
+            return None;
+        }
+        let inset = stroke_width as i32 / 2;
+        Some(Element::Arc(Arc {
+            top_left: (self.top_left.0 + inset, self.top_left.1 + inset),
+            diameter: self.diameter.saturating_sub(inset as u32 * 2),
+            stroke_width,
+            angle_start: self.angle_start + self.angle_sweep * from,
+            angle_sweep: self.angle_sweep * (to - from),
+            color: color.clone(),
+            opacity: None,
+            antialias: self.antialias,
+        }))
+    }
+}
+
+/// 以 (x, y) 为中心的数值文字
+fn value_label((x, y): (i32, i32), value: f32, unit: Option<&str>, size: f32, color: &CSSColor) -> Element {
+    Element::Text(Text {
+        x,
+        y,
+        text: format!("{}{}", label(value), unit.unwrap_or_default()),
+        size,
+        color: color.clone(),
+        align: TextAlign::Center,
+        valign: TextVAlign::Middle,
+        ..Default::default()
+    })
+}
+
+impl Gauge {
+    fn dial(&self) -> Dial {
+        Dial {
+            top_left: self.top_left,
+            diameter: self.diameter,
+            angle_start: self.angle_start.unwrap_or(DEFAULT_ANGLE_START),
+            angle_sweep: self.angle_sweep.unwrap_or(DEFAULT_ANGLE_SWEEP),
+            antialias: self.antialias,
+        }
+    }
+
+    /// 展开为基本元素
+    pub fn elements(&self) -> Vec<Element> {
+        let dial = self.dial();
+        let stroke_width = self.stroke_width.unwrap_or(self.diameter / 10).max(1);
+        let t = fraction(self.value, self.min, self.max);
+        let color = threshold_color(self.value, &self.thresholds).unwrap_or(&self.color);
+        let mut elements = vec![];
+        elements.extend(self.track_color.as_ref().and_then(|track| dial.arc(0.0, 1.0, stroke_width, track)));
+        elements.extend(dial.arc(0.0, t, stroke_width, color));
+        if let Some(label_color) = &self.label_color {
+            let size = self.label_size.unwrap_or(self.diameter as f32 / 5.0);
+            elements.push(value_label(dial.point(0.0, 0.0), self.value, self.unit.as_deref(), size, label_color));
+        }
+        elements
+    }
+}
+
+impl ProgressBar {
+    /// 展开为基本元素
+    pub fn elements(&self) -> Vec<Element> {
+        let radius = self.radius.unwrap_or(self.width.min(self.height) / 2);
+        let rect = |left: i32, top: i32, width: u32, height: u32, fill: Fill| {
+            Element::RoundedRectangle(RoundedRectangle {
+                left,
+                top,
+                width,
+                height,
+                stroke_width: 0,
+                fill_color: Some(fill),
+                stroke_color: None,
+                top_left_corner: (radius, radius),
+                top_right_corner: (radius, radius),
+                bottom_right_corner: (radius, radius),
+                bottom_left_corner: (radius, radius),
+                opacity: None,
+            })
+        };
+        let mut elements = vec![];
+        if let Some(background) = &self.background {
+            elements.push(rect(self.left, self.top, self.width, self.height, background.clone().into()));
+        }
+        let t = fraction(self.value, self.min, self.max);
+        let fill = threshold_color(self.value, &self.thresholds).map_or_else(|| self.color.clone(), |color| color.clone().into());
+        if self.vertical {
+            let height = (self.height as f32 * t).round() as u32;
+            if height > 0 {
+                elements.push(rect(self.left, self.top + (self.height - height) as i32, self.width, height, fill));
+            }
+        } else {
+            let width = (self.width as f32 * t).round() as u32;
+            if width > 0 {
+                elements.push(rect(self.left, self.top, width, self.height, fill));
+            }
+        }
+        elements
+    }
+}
+
+impl Meter {
+    fn dial(&self) -> Dial {
+        Dial {
+            top_left: self.top_left,
+            diameter: self.diameter,
+            angle_start: self.angle_start.unwrap_or(DEFAULT_ANGLE_START),
+            angle_sweep: self.angle_sweep.unwrap_or(DEFAULT_ANGLE_SWEEP),
+            antialias: self.antialias,
+        }
+    }
+
+    /// 展开为基本元素
+    pub fn elements(&self) -> Vec<Element> {
+        let dial = self.dial();
+        let stroke_width = self.stroke_width.unwrap_or(self.diameter / 20).max(1);
+        let mut elements = vec![];
+        elements.extend(dial.arc(0.0, 1.0, stroke_width, &self.color));
+        // 阈值区间
+        let mut thresholds: Vec<&Threshold> = self.thresholds.iter().collect();
+        thresholds.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (i, (value, color)) in thresholds.iter().enumerate() {
+            let from = fraction(*value, self.min, self.max);
+            let to = thresholds.get(i + 1).map_or(1.0, |next| fraction(next.0, self.min, self.max));
+            elements.extend(dial.arc(from, to, stroke_width, color));
+        }
+
+        let radius = (self.diameter.max(1) - 1) as f32 / 2.0;
+        let ticks = self.ticks.unwrap_or(DEFAULT_TICKS);
+        let tick_length = (self.diameter as f32 / 12.0).max(2.0);
+        let tick_color = self.tick_color.as_ref().unwrap_or(&self.color);
+        let (outer, inner) = (radius - stroke_width as f32, radius - stroke_width as f32 - tick_length);
+        for i in (0..=ticks).filter(|_| ticks > 0) {
+            let t = i as f32 / ticks as f32;
+            elements.push(Element::Line(Line {
+                start: dial.point(t, inner),
+                end: dial.point(t, outer),
+                stroke_width: 1,
+                color: tick_color.clone(),
+                opacity: None,
+                antialias: self.antialias,
+            }));
+        }
+
+        if let Some(label_color) = &self.label_color {
+            let size = self.label_size.unwrap_or(self.diameter as f32 / 8.0);
+            // 显示在转轴与表盘缺口之间
+            let position = dial.point(0.5, -radius / 2.0);
+            elements.push(value_label(position, self.value, self.unit.as_deref(), size, label_color));
+        }
+
+        let needle_width = self.needle_width.unwrap_or(2);
+        let center = dial.point(0.0, 0.0);
+        let needle = radius - stroke_width as f32 - tick_length / 2.0;
+        elements.push(Element::Line(Line {
+            start: center,
+            end: dial.point(fraction(self.value, self.min, self.max), needle),
+            stroke_width: needle_width,
+            color: self.needle_color.clone(),
+            opacity: None,
+            antialias: self.antialias,
+        }));
+        let hub = needle_width * 2 + 2;
+        elements.push(Element::Circle(Circle {
+            top_left: (center.0 - hub as i32 / 2, center.1 - hub as i32 / 2),
+            diameter: hub,
+            stroke_width: 0,
+            fill_color: Some(self.needle_color.clone().into()),
+            stroke_color: None,
+            opacity: None,
+            antialias: self.antialias,
+        }));
+        elements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gauge_helpers() {
+        assert_eq!(fraction(50.0, None, None), 0.5);
+        assert_eq!(fraction(150.0, None, None), 1.0);
+        assert_eq!(fraction(-5.0, Some(-10.0), Some(10.0)), 0.25);
+        assert_eq!(fraction(5.0, Some(10.0), Some(10.0)), 0.0);
+
+        let thresholds: Vec<Threshold> = serde_json::from_str(r#"[[80,"red"],[60,"orange"]]"#).unwrap();
+        assert!(threshold_color(50.0, &thresholds).is_none());
+        assert_eq!(threshold_color(70.0, &thresholds).map(CSSColor::rgba), Some([255, 165, 0, 255]));
+        assert_eq!(threshold_color(90.0, &thresholds).map(CSSColor::rgba), Some([255, 0, 0, 255]));
+
+        let dial = Dial { top_left: (0, 0), diameter: 101, angle_start: 90.0, angle_sweep: 180.0, antialias: false };
+        assert_eq!(dial.point(0.0, 50.0), (50, 100));
+        assert_eq!(dial.point(0.5, 50.0), (0, 50));
+    }
+}
@@ -0,0 +1,210 @@
+use std::io::Cursor;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, RgbImage};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::rgb565::Rgb565Image;
+
+// GIF 动画播放：上传的 GIF 预先解码为 RGB565 帧（固件中放在 PSRAM），由设备上的播放线程按 GIF 中的帧延时绘制
+// 播放线程每绘制一帧获取一次 Context 锁，其他绘制请求在帧之间执行；绘制区域与动画重叠时停止播放，避免新的内容被下一帧覆盖
+// 解码后的帧计入图片缓存的预算
+
+/// 解码后所有帧的总大小上限(字节)
+pub const MAX_GIF_SIZE: usize = 1024 * 1024;
+
+/// 帧延时不超过10ms时按100ms播放（与浏览器一致）
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// 播放参数 {"key":"xxx","x":0,"y":0,"loops":0}
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct PlayGif {
+    pub key: String,
+    #[serde(default)]
+    pub x: u16,
+    #[serde(default)]
+    pub y: u16,
+    /// 播放次数，0 为无限循环
+    #[serde(default)]
+    pub loops: u32,
+}
+
+pub struct GifFrame {
+    /// 不透明的 RGB565 图片，可直接发送到屏幕
+    pub image: Rgb565Image,
+    pub delay: Duration,
+}
+
+/// 解码 GIF 的所有帧，超出 (max_width, max_height) 的部分裁掉，透明像素显示为黑色
+pub fn decode_gif(data: &[u8], (max_width, max_height): (u32, u32), max_size: usize) -> Result<Vec<GifFrame>> {
+    let decoder = GifDecoder::new(Cursor::new(data))?;
+    let mut frames = vec![];
+    let mut size = 0;
+    for frame in decoder.into_frames() {
+        let frame = frame?;
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let delay = match numer / denom.max(1) {
+            0..=10 => DEFAULT_DELAY,
+            ms => Duration::from_millis(ms as u64),
+        };
+        let buffer = frame.into_buffer();
+        let (width, height) = (buffer.width().min(max_width), buffer.height().min(max_height));
+        if width == 0 || height == 0 {
+            return Err(anyhow!("GIF不在屏幕范围内"));
+        }
+        size += width as usize * height as usize * 2;
+        if size > max_size {
+            return Err(anyhow!("GIF解码后超过{max_size}字节，请减小尺寸或帧数"));
+        }
+        let rgb = RgbImage::from_fn(width, height, |x, y| {
+            let p = buffer.get_pixel(x, y);
+            if p[3] < 128 {
+                image::Rgb([0, 0, 0])
+            } else {
+                image::Rgb([p[0], p[1], p[2]])
+            }
+        });
+        frames.push(GifFrame { image: Rgb565Image::from_rgb(&rgb), delay });
+    }
+    if frames.is_empty() {
+        return Err(anyhow!("GIF没有帧"));
+    }
+    Ok(frames)
+}
+
+/// 播放状态，显示在 /status 中
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct GifStatus {
+    pub key: String,
+    pub x: u16,
+    pub y: u16,
+    pub loops: u32,
+    pub frames: usize,
+    /// 当前帧
+    pub frame: usize,
+    /// 已播放完的次数
+    pub played: u32,
+}
+
+pub struct GifPlayer {
+    params: PlayGif,
+    frames: Vec<GifFrame>,
+    frame: usize,
+    played: u32,
+}
+
+impl Serialize for GifPlayer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.status().serialize(serializer)
+    }
+}
+
+impl GifPlayer {
+    pub fn new(params: PlayGif, frames: Vec<GifFrame>) -> Self {
+        Self { params, frames, frame: 0, played: 0 }
+    }
+
+    /// 绘制位置
+    pub fn position(&self) -> (u16, u16) {
+        (self.params.x, self.params.y)
+    }
+
+    /// 动画占用的屏幕区域 (x, y, 宽, 高)
+    pub fn bounds(&self) -> (u16, u16, u16, u16) {
+        let (width, height) = self.frames[0].image.dimensions();
+        (self.params.x, self.params.y, width as u16, height as u16)
+    }
+
+    /// 区域是否与动画重叠
+    pub fn overlaps(&self, x: u16, y: u16, width: u16, height: u16) -> bool {
+        let (gx, gy, gw, gh) = self.bounds();
+        let (x, y, gx, gy) = (x as u32, y as u32, gx as u32, gy as u32);
+        x < gx + gw as u32 && gx < x + width as u32 && y < gy + gh as u32 && gy < y + height as u32
+    }
+
+    /// 所有帧占用的内存(字节)
+    pub fn size(&self) -> usize {
+        self.frames.iter().map(|frame| frame.image.pixels().len() * 2).sum()
+    }
+
+    /// 当前需要绘制的帧
+    pub fn current(&self) -> &GifFrame {
+        &self.frames[self.frame]
+    }
+
+    /// 切换到下一帧，播放完指定次数时返回 false（停在最后一帧）
+    pub fn advance(&mut self) -> bool {
+        if self.frame + 1 < self.frames.len() {
+            self.frame += 1;
+            return true;
+        }
+        self.played += 1;
+        if self.params.loops > 0 && self.played >= self.params.loops {
+            return false;
+        }
+        self.frame = 0;
+        true
+    }
+
+    pub fn status(&self) -> GifStatus {
+        GifStatus {
+            key: self.params.key.clone(),
+            x: self.params.x,
+            y: self.params.y,
+            loops: self.params.loops,
+            frames: self.frames.len(),
+            frame: self.frame,
+            played: self.played,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, Frame, RgbaImage};
+
+    fn gif() -> Vec<u8> {
+        let mut out = vec![];
+        {
+            let mut encoder = GifEncoder::new(&mut out);
+            for (color, ms) in [([255, 0, 0, 255], 50), ([0, 0, 255, 0], 0)] {
+                let image = RgbaImage::from_pixel(20, 10, image::Rgba(color));
+                encoder.encode_frame(Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(ms, 1))).unwrap();
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_decode_and_play() {
+        let frames = decode_gif(&gif(), (16, 16), MAX_GIF_SIZE).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].image.dimensions(), (16, 10));
+        assert_eq!(frames[0].delay, Duration::from_millis(50));
+        assert_eq!(frames[1].delay, DEFAULT_DELAY);
+        assert_eq!(frames[0].image.get_pixel(0, 0), (0xf800, 255));
+        // 第二帧完全透明，显示为黑色
+        assert_eq!(frames[1].image.get_pixel(0, 0), (0, 255));
+        assert!(decode_gif(&gif(), (16, 16), 16 * 10 * 2).is_err());
+        assert!(decode_gif(&gif(), (0, 16), MAX_GIF_SIZE).is_err());
+
+        let params = PlayGif { key: "a".to_string(), x: 0, y: 0, loops: 2 };
+        let mut player = GifPlayer::new(params, frames);
+        assert_eq!(player.bounds(), (0, 0, 16, 10));
+        assert_eq!(player.size(), 2 * 16 * 10 * 2);
+        assert!(player.overlaps(15, 9, 10, 10));
+        assert!(!player.overlaps(16, 0, 10, 10));
+        assert!(!player.overlaps(0, 10, 10, 10));
+        let mut drawn = 1;
+        while player.advance() {
+            drawn += 1;
+        }
+        assert_eq!(drawn, 4);
+        assert_eq!(player.status().played, 2);
+        assert_eq!(player.status().frame, 1);
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use crate::render::CSSColor;
+
+// 图形的填充：纯色或线性/径向渐变
+// 渐变按图形的外接矩形计算，预先生成256级颜色表，逐像素只需计算位置
+
+/// 填充，JSON 格式: "red" 或 {"type":"linear","angle":90,"stops":[[0,"red"],[1,"blue"]]}
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Fill {
+    Color(CSSColor),
+    Gradient(Gradient),
+}
+
+impl Fill {
+    /// 纯色填充的颜色，渐变时返回 None
+    pub fn color(&self) -> Option<&CSSColor> {
+        match self {
+            Fill::Color(color) => Some(color),
+            Fill::Gradient(_) => None,
+        }
+    }
+}
+
+impl From<CSSColor> for Fill {
+    fn from(color: CSSColor) -> Self {
+        Fill::Color(color)
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Gradient {
+    /// 线性渐变，angle 为渐变方向（度），0 从左到右，90 从上到下
+    Linear {
+        #[serde(default)]
+        angle: f32,
+        stops: Vec<ColorStop>,
+    },
+    /// 径向渐变，center 为圆心在外接矩形中的相对位置 (0~1)，默认 [0.5,0.5]
+    /// radius 为半径(像素)，默认为圆心到最远角的距离
+    Radial {
+        center: Option<(f32, f32)>,
+        radius: Option<f32>,
+        stops: Vec<ColorStop>,
+    },
+}
+
+/// 颜色节点 (位置 0~1, 颜色)，按位置从小到大排列
+pub type ColorStop = (f32, CSSColor);
+
+/// 颜色表的级数
+const LUT_SIZE: usize = 256;
+
+enum Shape {
+    /// t = x * dx + y * dy + c
+    Linear { dx: f32, dy: f32, c: f32 },
+    Radial { cx: f32, cy: f32, inv_radius: f32 },
+}
+
+/// 在外接矩形 (x, y, 宽, 高) 上计算渐变色
+pub struct GradientPainter {
+    shape: Shape,
+    lut: Vec<[u8; 4]>,
+}
+
+impl GradientPainter {
+    pub fn new(gradient: &Gradient, (left, top, width, height): (i32, i32, u32, u32)) -> Result<Self> {
+        let (w, h) = (width.max(1) as f32, height.max(1) as f32);
+        let (shape, stops) = match gradient {
+            Gradient::Linear { angle, stops } => {
+                let (sin, cos) = angle.to_radians().sin_cos();
+                // 与 CSS 一致：渐变线的长度使外接矩形的角正好落在 0 和 1 上
+                let len = (w * cos).abs() + (h * sin).abs();
+                let (dx, dy) = (cos / len, sin / len);
+                let (cx, cy) = (left as f32 + w / 2.0, top as f32 + h / 2.0);
+                (Shape::Linear { dx, dy, c: 0.5 - cx * dx - cy * dy }, stops)
+            }
+            Gradient::Radial { center, radius, stops } => {
+                let (rx, ry) = center.unwrap_or((0.5, 0.5));
+                let (cx, cy) = (left as f32 + rx * w, top as f32 + ry * h);
+                let radius = radius.unwrap_or_else(|| {
+                    let fx = rx.max(1.0 - rx) * w;
+                    let fy = ry.max(1.0 - ry) * h;
+                    (fx * fx + fy * fy).sqrt()
+                });
+                (Shape::Radial { cx, cy, inv_radius: 1.0 / radius.max(1.0) }, stops)
+            }
+        };
+        if stops.is_empty() {
+            return Err(anyhow!("渐变至少需要一个颜色"));
+        }
+        let lut = (0..LUT_SIZE).map(|i| color_at(stops, i as f32 / (LUT_SIZE - 1) as f32)).collect();
+        Ok(Self { shape, lut })
+    }
+
+    /// 像素 (x, y) 的颜色 RGBA
+    pub fn color(&self, x: i32, y: i32) -> [u8; 4] {
+        // 取像素中心
+        let (x, y) = (x as f32 + 0.5, y as f32 + 0.5);
+        let t = match self.shape {
+            Shape::Linear { dx, dy, c } => x * dx + y * dy + c,
+            Shape::Radial { cx, cy, inv_radius } => ((x - cx) * (x - cx) + (y - cy) * (y - cy)).sqrt() * inv_radius,
+        };
+        self.lut[(t.clamp(0.0, 1.0) * (LUT_SIZE - 1) as f32).round() as usize]
+    }
+}
+
+/// 位置 t 处的颜色，在相邻两个颜色节点之间线性插值
+fn color_at(stops: &[ColorStop], t: f32) -> [u8; 4] {
+    let (first, last) = (&stops[0], &stops[stops.len() - 1]);
+    if t <= first.0 {
+        return first.1.rgba();
+    }
+    if t >= last.0 {
+        return last.1.rgba();
+    }
+    for pair in stops.windows(2) {
+        let ((p0, c0), (p1, c1)) = (&pair[0], &pair[1]);
+        if t > *p1 {
+            continue;
+        }
+        let k = if p1 > p0 { (t - p0) / (p1 - p0) } else { 1.0 };
+        let (c0, c1) = (c0.rgba(), c1.rgba());
+        return std::array::from_fn(|i| (c0[i] as f32 + (c1[i] as f32 - c0[i] as f32) * k).round() as u8);
+    }
+    last.1.rgba()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gradient_color() {
+        let fill: Fill = serde_json::from_str(r#"{"type":"linear","stops":[[0,"black"],[1,"white"]]}"#).unwrap();
+        let Fill::Gradient(gradient) = fill else { panic!() };
+        let painter = GradientPainter::new(&gradient, (10, 0, 100, 10)).unwrap();
+        assert_eq!(painter.color(10, 0), [1, 1, 1, 255]);
+        assert_eq!(painter.color(59, 5), [126, 126, 126, 255]);
+        assert_eq!(painter.color(200, 5), [255, 255, 255, 255]);
+
+        // 90度从上到下
+        let gradient = Gradient::Linear { angle: 90.0, stops: vec![(0.0, CSSColor::default()), (1.0, CSSColor(csscolorparser::Color::new(1.0, 0.0, 0.0, 1.0)))] };
+        let painter = GradientPainter::new(&gradient, (0, 0, 10, 100)).unwrap();
+        assert_eq!(painter.color(0, 99)[0], 254);
+
+        let fill: Fill = serde_json::from_str(r#"{"type":"radial","radius":10,"stops":[[0,"red"],[0.5,"lime"],[1,"blue"]]}"#).unwrap();
+        let Fill::Gradient(gradient) = fill else { panic!() };
+        let painter = GradientPainter::new(&gradient, (0, 0, 20, 20)).unwrap();
+        assert_eq!(painter.color(10, 10), [219, 36, 0, 255]);
+        assert_eq!(painter.color(0, 10), [0, 24, 231, 255]);
+        assert_eq!(painter.color(0, 0), [0, 0, 255, 255]);
+
+        let fill: Fill = serde_json::from_str(r#""red""#).unwrap();
+        assert!(fill.color().is_some());
+        assert!(GradientPainter::new(&Gradient::Linear { angle: 0.0, stops: vec![] }, (0, 0, 1, 1)).is_err());
+    }
+}
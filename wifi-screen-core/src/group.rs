@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+
+use ab_glyph::{FontRef, FontVec};
+use anyhow::Result;
+use image::imageops;
+use image::{ImageBuffer, Pixel, RgbImage};
+use serde::Deserialize;
+
+use crate::gradient::{Fill, Gradient};
+use crate::image_fit::{resample, source_size, ImageQuality};
+use crate::render::{render_elements, Element, ImageCache};
+use crate::scene::Bounds;
+
+// 分组：子元素的坐标相对于组的原点，整组可以平移、缩放、裁剪，同一个控件可以放在不同的位置
+// 缩放直接修改子元素的坐标、尺寸和字号，文字和图形缩放后仍然清晰
+
+/// 最小的缩放比例
+const MIN_SCALE: f32 = 0.01;
+
+#[derive(Clone, Deserialize)]
+pub struct Group {
+    /// 子元素坐标原点在画布上的位置
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    /// 裁剪区域 (x, y, 宽, 高)，相对于组的原点，随组一起缩放，超出的部分不绘制
+    pub clip: Option<(i32, i32, u32, u32)>,
+    /// 缩放比例，默认 1
+    pub scale: Option<f32>,
+    pub children: Vec<Element>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+}
+
+impl Group {
+    fn scale(&self) -> f32 {
+        self.scale.unwrap_or(1.0).max(MIN_SCALE)
+    }
+
+    /// 画布上的裁剪区域
+    pub fn clip_bounds(&self) -> Option<Bounds> {
+        let (x, y, w, h) = self.clip?;
+        let s = Scale(self.scale());
+        let (left, width) = s.span(x, w);
+        let (top, height) = s.span(y, h);
+        Some(Bounds::new(self.x + left, self.y + top, width, height))
+    }
+
+    /// 缩放、平移后的子元素（画布坐标），不包括裁剪
+    /// 缩放后的 RawImage/RawRgbImage 只保留 visible（画布上需要绘制的区域）和裁剪区域中的部分
+    pub fn elements(&self, image_cache: &HashMap<String, ImageCache>, visible: Bounds) -> Result<Vec<Element>> {
+        let s = Scale(self.scale());
+        let visible = self.clip_bounds().map_or(visible, |clip| clip.intersection(&visible));
+        // 组的坐标系中的可见区域
+        let visible = Bounds { left: visible.left - self.x, top: visible.top - self.y, right: visible.right - self.x, bottom: visible.bottom - self.y };
+        self.children
+            .iter()
+            .map(|child| {
+                let mut child = child.clone();
+                if s.0 != 1.0 {
+                    s.element(&mut child, image_cache, visible)?;
+                }
+                child.translate(self.x, self.y);
+                Ok(child)
+            })
+            .collect()
+    }
+
+    /// 子元素缩放、平移后覆盖的区域（画布坐标），不包括裁剪，图像只计算缩放后的位置和大小
+    pub(crate) fn children_bounds(
+        &self,
+        image_cache: &HashMap<String, ImageCache>,
+        bounds: impl Fn(&Element) -> Option<Bounds>,
+    ) -> Option<Bounds> {
+        let s = Scale(self.scale());
+        self.children
+            .iter()
+            .map(|child| {
+                let rect = match child {
+                    Element::RawImage((x, y, img)) => s.image_rect((*x, *y), img.dimensions()),
+                    Element::RawRgbImage((x, y, img)) => s.image_rect((*x, *y), img.dimensions()),
+                    child if s.0 != 1.0 => {
+                        let mut child = child.clone();
+                        s.element(&mut child, image_cache, Bounds::new(0, 0, 0, 0)).ok()?;
+                        bounds(&child)?
+                    }
+                    child => bounds(child)?,
+                };
+                Some(Bounds::new(rect.left + self.x, rect.top + self.y, rect.width(), rect.height()))
+            })
+            .reduce(|a, b| Some(a?.union(&b?)))?
+    }
+
+    pub(crate) fn draw(
+        &self,
+        canvas: &mut RgbImage,
+        font: &FontRef,
+        font_cache: &HashMap<String, FontVec>,
+        image_cache: &HashMap<String, ImageCache>,
+    ) -> Result<()> {
+        let mut children = self.elements(image_cache, Bounds::new(0, 0, canvas.width(), canvas.height()))?;
+        let Some(clip) = self.clip_bounds() else {
+            return render_elements(canvas, font, font_cache, image_cache, &children);
+        };
+        // 在裁剪区域大小的画布上绘制，再复制回来
+        let area = clip.intersection(&Bounds::new(0, 0, canvas.width(), canvas.height()));
+        if area.is_empty() {
+            return Ok(());
+        }
+        let (left, top) = (area.left as u32, area.top as u32);
+        let mut clipped = imageops::crop_imm(canvas, left, top, area.width(), area.height()).to_image();
+        children.iter_mut().for_each(|child| child.translate(-area.left, -area.top));
+        render_elements(&mut clipped, font, font_cache, image_cache, &children)?;
+        imageops::replace(canvas, &clipped, left as i64, top as i64);
+        Ok(())
+    }
+}
+
+/// 缩放元素的坐标和尺寸，坐标相对于组的原点
+#[derive(Clone, Copy)]
+struct Scale(f32);
+
+impl Scale {
+    fn v(self, v: i32) -> i32 {
+        (v as f32 * self.0).round() as i32
+    }
+
+    fn point(self, p: &mut (i32, i32)) {
+        *p = (self.v(p.0), self.v(p.1));
+    }
+
+    /// 线宽等尺寸，不为 0 的尺寸缩小后至少为 1
+    fn len(self, v: u32) -> u32 {
+        ((v as f32 * self.0).round() as u32).max(v.min(1))
+    }
+
+    fn opt(self, v: &mut Option<u32>) {
+        if let Some(v) = v.as_mut() {
+            *v = self.len(*v);
+        }
+    }
+
+    fn f(self, v: &mut f32) {
+        *v *= self.0;
+    }
+
+    /// 缩放起点和长度，相邻的矩形缩放后仍然相邻
+    fn span(self, start: i32, len: u32) -> (i32, u32) {
+        let (a, b) = (self.v(start), self.v(start + len as i32));
+        (a, (b - a).max(len.min(1) as i32) as u32)
+    }
+
+    fn rect(self, left: &mut i32, top: &mut i32, width: &mut u32, height: &mut u32) {
+        (*left, *width) = self.span(*left, *width);
+        (*top, *height) = self.span(*top, *height);
+    }
+
+    /// 圆、圆弧等的外接正方形
+    fn square(self, top_left: &mut (i32, i32), diameter: &mut u32) {
+        let (mut width, mut height) = (*diameter, *diameter);
+        self.rect(&mut top_left.0, &mut top_left.1, &mut width, &mut height);
+        *diameter = width.min(height);
+    }
+
+    /// 图像缩放后的区域
+    fn image_rect(self, (mut x, mut y): (i32, i32), (mut width, mut height): (u32, u32)) -> Bounds {
+        self.rect(&mut x, &mut y, &mut width, &mut height);
+        Bounds::new(x, y, width, height)
+    }
+
+    /// 缩放图像，只计算 visible 中可见的像素，返回新的位置和图像
+    fn image<P: Pixel<Subpixel = u8>>(self, position: (i32, i32), img: &ImageBuffer<P, Vec<u8>>, visible: Bounds) -> ((i32, i32), ImageBuffer<P, Vec<u8>>) {
+        let rect = self.image_rect(position, img.dimensions());
+        let area = rect.intersection(&visible);
+        if area.is_empty() {
+            return ((rect.left, rect.top), ImageBuffer::new(0, 0));
+        }
+        let get = |x, y| img.get_pixel(x, y).to_rgba().0.map(|v| v as f32);
+        let part = ((area.left - rect.left) as u32, (area.top - rect.top) as u32, area.width(), area.height());
+        let scaled = resample(get, (0, 0, img.width(), img.height()), (rect.width(), rect.height()), part, ImageQuality::Nearest);
+        ((area.left, area.top), scaled)
+    }
+
+    /// 径向渐变的半径为像素，其他渐变参数是相对于图形的
+    fn fill(self, fill: Option<&mut Fill>) {
+        if let Some(Fill::Gradient(Gradient::Radial { radius: Some(radius), .. })) = fill {
+            self.f(radius);
+        }
+    }
+
+    /// visible 为组的坐标系中可见的区域，缩放后的 RawImage/RawRgbImage 只保留其中的部分
+    fn element(self, element: &mut Element, image_cache: &HashMap<String, ImageCache>, visible: Bounds) -> Result<()> {
+        match element {
+            Element::Text(text) => {
+                (text.x, text.y) = (self.v(text.x), self.v(text.y));
+                self.f(&mut text.size);
+                self.opt(&mut text.max_width);
+                if let Some(h) = text.line_height.as_mut() {
+                    self.f(h);
+                }
+                self.opt(&mut text.stroke_width);
+                self.opt(&mut text.padding);
+                if let Some(shadow) = text.shadow.as_mut() {
+                    if let Some(offset) = shadow.offset.as_mut() {
+                        self.point(offset);
+                    }
+                    self.opt(&mut shadow.blur);
+                }
+                if let Some(anchor) = text.anchor.as_mut() {
+                    self.point(anchor);
+                }
+            }
+            Element::RichText(text) => {
+                (text.x, text.y) = (self.v(text.x), self.v(text.y));
+                self.f(&mut text.size);
+                self.opt(&mut text.max_width);
+                if let Some(h) = text.line_height.as_mut() {
+                    self.f(h);
+                }
+                for size in text.spans.iter_mut().filter_map(|span| span.size.as_mut()) {
+                    self.f(size);
+                }
+            }
+            Element::Image(image) => {
+                if image.width.is_none() && image.height.is_none() {
+                    let (w, h) = source_size(image, image_cache)?;
+                    (image.width, image.height) = (Some(w), Some(h));
+                }
+                let (mut width, mut height) = (image.width.unwrap_or(0), image.height.unwrap_or(0));
+                self.rect(&mut image.x, &mut image.y, &mut width, &mut height);
+                // 只设置了一边时另一边仍按图片比例计算
+                image.width = image.width.map(|_| width);
+                image.height = image.height.map(|_| height);
+            }
+            Element::RawImage((x, y, img)) => {
+                let (position, scaled) = self.image((*x, *y), img.as_ref(), visible);
+                ((*x, *y), **img) = (position, scaled);
+            }
+            Element::RawRgbImage((x, y, img)) => {
+                let (position, scaled) = self.image((*x, *y), img.as_ref(), visible);
+                ((*x, *y), **img) = (position, scaled);
+            }
+            Element::Line(line) => {
+                self.point(&mut line.start);
+                self.point(&mut line.end);
+                line.stroke_width = self.len(line.stroke_width);
+            }
+            Element::Circle(circle) => {
+                self.square(&mut circle.top_left, &mut circle.diameter);
+                circle.stroke_width = self.len(circle.stroke_width);
+                self.fill(circle.fill_color.as_mut());
+            }
+            Element::Ellipse(ellipse) => {
+                let (left, top) = &mut ellipse.top_left;
+                let (width, height) = &mut ellipse.size;
+                self.rect(left, top, width, height);
+                ellipse.stroke_width = self.len(ellipse.stroke_width);
+                self.fill(ellipse.fill_color.as_mut());
+            }
+            Element::Arc(arc) => {
+                self.square(&mut arc.top_left, &mut arc.diameter);
+                arc.stroke_width = self.len(arc.stroke_width);
+            }
+            Element::Sector(sector) => {
+                self.square(&mut sector.top_left, &mut sector.diameter);
+                sector.stroke_width = self.len(sector.stroke_width);
+                self.fill(sector.fill_color.as_mut());
+            }
+            Element::Rectangle(rect) => {
+                self.rect(&mut rect.left, &mut rect.top, &mut rect.width, &mut rect.height);
+                rect.stroke_width = self.len(rect.stroke_width);
+                self.fill(rect.fill_color.as_mut());
+            }
+            Element::RoundedRectangle(rect) => {
+                self.rect(&mut rect.left, &mut rect.top, &mut rect.width, &mut rect.height);
+                rect.stroke_width = self.len(rect.stroke_width);
+                for (w, h) in [
+                    &mut rect.top_left_corner,
+                    &mut rect.top_right_corner,
+                    &mut rect.bottom_right_corner,
+                    &mut rect.bottom_left_corner,
+                ] {
+                    (*w, *h) = (self.len(*w), self.len(*h));
+                }
+                self.fill(rect.fill_color.as_mut());
+            }
+            Element::Polyline(polyline) => {
+                polyline.points.iter_mut().for_each(|p| self.point(p));
+                polyline.stroke_width = self.len(polyline.stroke_width);
+            }
+            Element::Triangle(triangle) => {
+                self.point(&mut triangle.vertex1);
+                self.point(&mut triangle.vertex2);
+                self.point(&mut triangle.vertex3);
+                triangle.stroke_width = self.len(triangle.stroke_width);
+                self.fill(triangle.fill_color.as_mut());
+            }
+            Element::Polygon(polygon) => {
+                polygon.points.iter_mut().for_each(|p| self.point(p));
+                polygon.stroke_width = self.len(polygon.stroke_width);
+                self.fill(polygon.fill_color.as_mut());
+            }
+            Element::CubicBezier(curve) => {
+                self.point(&mut curve.start);
+                self.point(&mut curve.control1);
+                self.point(&mut curve.control2);
+                self.point(&mut curve.end);
+                curve.stroke_width = self.len(curve.stroke_width);
+            }
+            Element::Path(path) => {
+                (path.x, path.y) = (self.v(path.x), self.v(path.y));
+                path.scale = Some(path.scale.unwrap_or(1.0) * self.0);
+                path.stroke_width = self.len(path.stroke_width);
+                self.fill(path.fill_color.as_mut());
+            }
+            Element::LineChart(chart) => {
+                self.rect(&mut chart.x, &mut chart.y, &mut chart.width, &mut chart.height);
+                self.opt(&mut chart.stroke_width);
+                self.fill(chart.fill.as_mut());
+            }
+            Element::BarChart(chart) => {
+                self.rect(&mut chart.x, &mut chart.y, &mut chart.width, &mut chart.height);
+                self.opt(&mut chart.bar_gap);
+                self.fill(Some(&mut chart.color));
+            }
+            Element::Sparkline(chart) => {
+                self.rect(&mut chart.x, &mut chart.y, &mut chart.width, &mut chart.height);
+                self.opt(&mut chart.stroke_width);
+                self.fill(chart.fill.as_mut());
+            }
+            Element::Gauge(gauge) => {
+                self.square(&mut gauge.top_left, &mut gauge.diameter);
+                self.opt(&mut gauge.stroke_width);
+                if let Some(size) = gauge.label_size.as_mut() {
+                    self.f(size);
+                }
+            }
+            Element::ProgressBar(bar) => {
+                self.rect(&mut bar.left, &mut bar.top, &mut bar.width, &mut bar.height);
+                self.opt(&mut bar.radius);
+                self.fill(Some(&mut bar.color));
+            }
+            Element::Meter(meter) => {
+                self.square(&mut meter.top_left, &mut meter.diameter);
+                self.opt(&mut meter.stroke_width);
+                self.opt(&mut meter.needle_width);
+                if let Some(size) = meter.label_size.as_mut() {
+                    self.f(size);
+                }
+            }
+            Element::QrCode(qr) => {
+                (qr.x, qr.y) = (self.v(qr.x), self.v(qr.y));
+                qr.module_size = Some(self.len(qr.module_size.unwrap_or(2)));
+            }
+            Element::Group(group) => {
+                // 子组的裁剪区域和子元素在子组的坐标系中，只需要累积缩放比例
+                (group.x, group.y) = (self.v(group.x), self.v(group.y));
+                group.scale = Some(group.scale() * self.0);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(json: &str) -> Group {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_group_elements() {
+        let g = group(r#"{"x":100,"y":50,"scale":2,"clip":[0,0,30,20],"children":[
+            {"Rectangle":{"left":5,"top":5,"width":10,"height":3,"stroke_width":1,"fill_color":"red","stroke_color":null}},
+            {"Text":{"x":1,"y":2,"text":"a","size":12,"color":"white","max_width":40}},
+            {"Group":{"x":10,"y":0,"scale":0.5,"children":[{"Line":{"start":[0,0],"end":[4,4],"stroke_width":1,"color":"red"}}]}}
+        ]}"#);
+        assert_eq!(g.clip_bounds(), Some(Bounds::new(100, 50, 60, 40)));
+        let screen = Bounds::new(0, 0, 320, 240);
+        let elements = g.elements(&HashMap::new(), screen).unwrap();
+        match &elements[0] {
+            Element::Rectangle(r) => assert_eq!((r.left, r.top, r.width, r.height, r.stroke_width), (110, 60, 20, 6, 2)),
+            _ => panic!("expect rectangle"),
+        }
+        match &elements[1] {
+            Element::Text(t) => assert_eq!((t.x, t.y, t.size, t.max_width), (102, 54, 24.0, Some(80))),
+            _ => panic!("expect text"),
+        }
+        // 子组的缩放比例累积
+        let Element::Group(inner) = &elements[2] else { panic!("expect group") };
+        assert_eq!((inner.x, inner.y, inner.scale), (120, 50, Some(1.0)));
+        match &inner.elements(&HashMap::new(), screen).unwrap()[0] {
+            Element::Line(l) => assert_eq!((l.start, l.end), ((120, 50), (124, 54))),
+            _ => panic!("expect line"),
+        }
+    }
+
+    #[test]
+    fn test_scale_raw_image() {
+        // 放大 20 倍的图像只计算裁剪区域和画布中可见的部分
+        let mut g = group(r#"{"x":10,"y":10,"scale":20,"clip":[0,0,5,5],"children":[]}"#);
+        let img = image::RgbaImage::from_fn(64, 64, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        g.children.push(Element::RawImage((0, 0, Box::new(img))));
+        let elements = g.elements(&HashMap::new(), Bounds::new(0, 0, 80, 60)).unwrap();
+        let Element::RawImage((x, y, img)) = &elements[0] else { panic!("expect image") };
+        assert_eq!((*x, *y, img.dimensions()), (10, 10, (70, 50)));
+        assert_eq!(img.get_pixel(69, 49).0, [3, 2, 0, 255]);
+        let bounds = g.children_bounds(&HashMap::new(), |_| None);
+        assert_eq!(bounds, Some(Bounds::new(10, 10, 1280, 1280)));
+    }
+
+    #[test]
+    fn test_scale_span() {
+        let s = Scale(0.5);
+        // 相邻的矩形缩放后仍然相邻，线宽至少为 1
+        assert_eq!(s.span(0, 3), (0, 2));
+        assert_eq!(s.span(3, 3), (2, 1));
+        assert_eq!(s.len(1), 1);
+        assert_eq!(s.len(0), 0);
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use serde::{Serialize, Serializer};
+
+use crate::image_store::ImageStore;
+use crate::render::{decode_image_rgb565, Element, ImageCache};
+use crate::sprite::SpriteSheet;
+
+// 图片内存缓存：按解码后的字节数计算占用，超出预算时淘汰最久未使用的图片
+// 固定(pin)的图片不会被淘汰，被淘汰的图片仍保存在 ImageStore 中，需要时重新加载
+
+/// 默认缓存预算(字节)
+pub const DEFAULT_IMAGE_CACHE_BUDGET: usize = 1024 * 1024;
+
+/// 解码后的图片占用的字节数
+pub fn image_size(image: &ImageCache) -> usize {
+    match image {
+        ImageCache::RgbImage(img) => img.as_raw().len(),
+        ImageCache::RgbaImage(img) => img.as_raw().len(),
+        ImageCache::Rgb565(img) => img.size(),
+        ImageCache::Sheet(img, _) => image_size(img),
+    }
+}
+
+pub fn with_sprite_sheet(image: ImageCache, sheet: Option<SpriteSheet>) -> ImageCache {
+    match sheet {
+        Some(sheet) => ImageCache::Sheet(Box::new(image), Box::new(sheet)),
+        None => image,
+    }
+}
+
+/// 缓存统计，显示在 /status 中
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CacheStats {
+    pub budget: usize,
+    pub used: usize,
+    /// 预算中为其他用途（GIF 帧）预留的大小
+    pub reserved: usize,
+    pub keys: Vec<String>,
+    pub pinned: Vec<String>,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+pub struct LruImageCache {
+    images: HashMap<String, ImageCache>,
+    /// 每张图片最后一次使用的时间（递增计数）
+    last_used: HashMap<String, u64>,
+    pinned: HashSet<String>,
+    budget: usize,
+    used: usize,
+    reserved: usize,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl Default for LruImageCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_IMAGE_CACHE_BUDGET)
+    }
+}
+
+impl Serialize for LruImageCache {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.stats().serialize(serializer)
+    }
+}
+
+impl LruImageCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            images: HashMap::new(),
+            last_used: HashMap::new(),
+            pinned: HashSet::new(),
+            budget,
+            used: 0,
+            reserved: 0,
+            clock: 0,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    /// 传给 render_elements 等渲染函数
+    pub fn images(&self) -> &HashMap<String, ImageCache> {
+        &self.images
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ImageCache> {
+        self.images.get(key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.images.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.images.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    fn touch(&mut self, key: &str) {
+        self.clock += 1;
+        self.last_used.insert(key.to_string(), self.clock);
+    }
+
+    /// 放入缓存，超出预算时淘汰其他图片
+    pub fn insert(&mut self, key: String, image: ImageCache) {
+        self.remove(&key);
+        self.used += image_size(&image);
+        self.touch(&key);
+        self.images.insert(key.clone(), image);
+        self.evict(&[&key]);
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<ImageCache> {
+        let image = self.images.remove(key)?;
+        self.used -= image_size(&image);
+        self.last_used.remove(key);
+        Some(image)
+    }
+
+    /// 淘汰最久未使用的图片直到不超过预算，keep 中的图片和固定的图片不会被淘汰
+    fn evict(&mut self, keep: &[&str]) {
+        while self.used + self.reserved > self.budget {
+            let oldest = self
+                .last_used
+                .iter()
+                .filter(|(k, _)| !self.pinned.contains(*k) && !keep.contains(&k.as_str()))
+                .min_by_key(|(_, t)| **t)
+                .map(|(k, _)| k.clone());
+            match oldest {
+                Some(key) => {
+                    self.remove(&key);
+                    self.evictions += 1;
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict(&[]);
+    }
+
+    /// 从预算中预留内存（GIF 帧），超出预算时淘汰图片，0 为释放
+    pub fn reserve(&mut self, bytes: usize) {
+        self.reserved = bytes;
+        self.evict(&[]);
+    }
+
+    /// 淘汰所有未固定的图片后最多可以预留的大小
+    pub fn reservable(&self) -> usize {
+        let pinned: usize = self.pinned.iter().filter_map(|key| self.images.get(key)).map(image_size).sum();
+        self.budget.saturating_sub(pinned)
+    }
+
+    /// 固定或取消固定图片，固定的图片不会被淘汰（仍计入预算）
+    pub fn set_pinned(&mut self, key: &str, pinned: bool) {
+        if pinned {
+            self.pinned.insert(key.to_string());
+        } else {
+            self.pinned.remove(key);
+            self.evict(&[]);
+        }
+    }
+
+    pub fn pinned(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.pinned.iter().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            budget: self.budget,
+            used: self.used,
+            reserved: self.reserved,
+            keys: self.keys(),
+            pinned: self.pinned(),
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+        }
+    }
+
+    /// 更新缓存中图片的精灵图定义，图片不在缓存中时返回 false
+    pub fn set_sprite_sheet(&mut self, key: &str, sheet: Option<SpriteSheet>) -> bool {
+        match self.images.remove(key) {
+            Some(image) => {
+                self.images.insert(key.to_string(), with_sprite_sheet(image.into_image(), sheet));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 确保这些图片在缓存中，不在缓存中的从存储中加载，存储中也没有的忽略
+    pub fn load(&mut self, store: &ImageStore, keys: &[&str]) -> Result<()> {
+        for key in keys {
+            if self.images.contains_key(*key) {
+                self.hits += 1;
+                self.touch(key);
+                continue;
+            }
+            self.misses += 1;
+            if !store.contains(key) {
+                continue;
+            }
+            let image = with_sprite_sheet(decode_image_rgb565(&store.load(key)?)?, store.sprite_sheet(key)?);
+            self.used += image_size(&image);
+            self.touch(key);
+            self.images.insert(key.to_string(), image);
+            self.evict(keys);
+        }
+        Ok(())
+    }
+
+    /// 绘制前加载元素引用的图片
+    pub fn load_images<'a>(&mut self, store: &ImageStore, elements: impl IntoIterator<Item = &'a Element>) -> Result<()> {
+        fn image_keys<'a>(element: &'a Element, keys: &mut Vec<&'a str>) {
+            match element {
+                Element::Image(image) => keys.extend(image.key.as_deref()),
+                Element::Group(group) => group.children.iter().for_each(|child| image_keys(child, keys)),
+                _ => (),
+            }
+        }
+        let mut keys = vec![];
+        elements.into_iter().for_each(|element| image_keys(element, &mut keys));
+        self.load(store, &keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    /// 10x10 RGB 图片占用 300 字节
+    fn image() -> ImageCache {
+        ImageCache::RgbImage(Box::new(RgbImage::new(10, 10)))
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = LruImageCache::new(1000);
+        for key in ["a", "b", "c"] {
+            cache.insert(key.to_string(), image());
+        }
+        assert_eq!(cache.stats().used, 900);
+
+        // 使用 a 后，最久未使用的是 b
+        cache.touch("a");
+        cache.insert("d".to_string(), image());
+        assert_eq!(cache.keys(), vec!["a", "c", "d"]);
+        assert_eq!(cache.stats().evictions, 1);
+
+        // 固定的图片不会被淘汰
+        cache.set_pinned("c", true);
+        cache.insert("e".to_string(), image());
+        assert_eq!(cache.keys(), vec!["c", "d", "e"]);
+
+        cache.set_budget(600);
+        assert_eq!(cache.keys(), vec!["c", "e"]);
+        assert_eq!(cache.stats().used, 600);
+
+        cache.remove("e");
+        assert_eq!(cache.stats().used, 300);
+
+        // 预留的内存计入预算，固定的图片不会被淘汰
+        cache.insert("f".to_string(), image());
+        assert_eq!(cache.reservable(), 300);
+        cache.reserve(300);
+        assert_eq!(cache.keys(), vec!["c"]);
+        assert_eq!(cache.stats().reserved, 300);
+        cache.reserve(0);
+        cache.insert("g".to_string(), image());
+        assert_eq!(cache.keys(), vec!["c", "g"]);
+    }
+
+    #[test]
+    fn test_load_images() {
+        let dir = std::env::temp_dir().join(format!("wifi-screen-image-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = ImageStore::new(dir, 1024 * 1024);
+        let mut png = std::io::Cursor::new(vec![]);
+        image::RgbaImage::new(10, 10).write_to(&mut png, image::ImageFormat::Png).unwrap();
+        for key in ["a", "b", "c"] {
+            store.save(key, png.get_ref()).unwrap();
+        }
+
+        // 全透明 PNG 解码为 RGB565 + 1位透明通道，每张 213 字节，预算只能放两张，但同一次绘制需要的图片不会互相淘汰
+        let mut cache = LruImageCache::new(550);
+        let elements: Vec<Element> = ["a", "b", "c", "none"]
+            .iter()
+            .map(|key| serde_json::from_str(&format!(r#"{{"Image":{{"x":0,"y":0,"key":"{key}"}}}}"#)).unwrap())
+            .collect();
+        cache.load_images(&store, &elements).unwrap();
+        assert_eq!(cache.keys(), vec!["a", "b", "c"]);
+
+        cache.load_images(&store, &elements[..1]).unwrap();
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 4));
+
+        cache.load(&store, &["b"]).unwrap();
+        cache.insert("d".to_string(), image());
+        assert_eq!(cache.keys(), vec!["b", "d"]);
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+use image::{ImageBuffer, Pixel, RgbaImage};
+use serde::Deserialize;
+
+use crate::render::{decode_image, Element, Image, ImageCache};
+use crate::rgb565::rgb565_to_rgb888;
+use crate::scene::Bounds;
+use crate::utils::decode_base64;
+
+// 图像缩放、裁剪：Image 元素设置了 frame/crop 时只绘制图片的一部分，设置了 width/height 时再按 fit 缩放到显示区域
+// 缩放时只计算画布上可见的像素，不生成整张缩放后的图片，内存占用不超过画布的大小
+
+/// 显示区域的最大宽高，远大于屏幕的尺寸按错误处理
+pub const MAX_IMAGE_SIZE: u32 = 2048;
+
+/// 图片在显示区域中的缩放方式（与 CSS object-fit 相同）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFit {
+    /// 拉伸填满显示区域，不保持比例
+    #[default]
+    Fill,
+    /// 保持比例完整显示，居中
+    Contain,
+    /// 保持比例填满显示区域，超出部分裁掉，居中
+    Cover,
+    /// 不缩放，居中，超出部分裁掉
+    None,
+}
+
+/// 缩放质量，越高越慢
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageQuality {
+    #[default]
+    Nearest,
+    Bilinear,
+    Bicubic,
+    Lanczos,
+}
+
+impl ImageQuality {
+    /// 插值函数和半径，与 imageops::FilterType 的 Nearest/Triangle/CatmullRom/Lanczos3 相同
+    fn kernel(self) -> (fn(f32) -> f32, f32) {
+        match self {
+            ImageQuality::Nearest => (|_| 1.0, 0.0),
+            ImageQuality::Bilinear => (|x| (1.0 - x.abs()).max(0.0), 1.0),
+            ImageQuality::Bicubic => (catmull_rom, 2.0),
+            ImageQuality::Lanczos => (lanczos3, 3.0),
+        }
+    }
+}
+
+fn catmull_rom(x: f32) -> f32 {
+    let a = x.abs();
+    let k = if a < 1.0 {
+        9.0 * a.powi(3) - 15.0 * a.powi(2) + 6.0
+    } else if a < 2.0 {
+        -3.0 * a.powi(3) + 15.0 * a.powi(2) - 24.0 * a + 12.0
+    } else {
+        0.0
+    };
+    k / 6.0
+}
+
+fn lanczos3(x: f32) -> f32 {
+    let sinc = |t: f32| if t == 0.0 { 1.0 } else { (t * std::f32::consts::PI).sin() / (t * std::f32::consts::PI) };
+    if x.abs() < 3.0 {
+        sinc(x) * sinc(x / 3.0)
+    } else {
+        0.0
+    }
+}
+
+/// 缩放裁剪的计算结果
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FitLayout {
+    /// 源图中裁剪的区域 (x, y, 宽, 高)
+    pub crop: (u32, u32, u32, u32),
+    /// 裁剪后缩放到的大小
+    pub scaled: (u32, u32),
+    /// 缩放后的图片在显示区域中可见的部分 (x, y, 宽, 高)，相对于缩放后的图片
+    pub visible: (u32, u32, u32, u32),
+    /// 可见部分绘制的位置（相对于元素的 x, y）
+    pub offset: (i32, i32),
+}
+
+impl Image {
+    /// 是否需要缩放或裁剪（不能按原图直接绘制）
+    pub fn has_transform(&self) -> bool {
+        self.needs_scaling() || self.crop.is_some() || self.frame.is_some()
+    }
+
+    /// 是否需要缩放
+    pub fn needs_scaling(&self) -> bool {
+        self.width.is_some() || self.height.is_some()
+    }
+
+    /// 是否需要先生成新的图片再绘制：缩放，或者裁剪 base64 图片（缓存的图片可以直接绘制其中一部分）
+    pub fn needs_copy(&self) -> bool {
+        self.needs_scaling() || (self.key.is_none() && self.has_transform())
+    }
+
+    /// 绘制的源图区域 (x, y, 宽, 高)：精灵图的帧，再按 crop 裁剪（crop 相对于帧）
+    pub fn source_rect(&self, image: &ImageCache) -> Result<(u32, u32, u32, u32)> {
+        let (width, height) = image.dimensions();
+        let (mut x, mut y, mut w, mut h) = (0, 0, width, height);
+        if let Some(frame) = self.frame.as_ref() {
+            match image {
+                ImageCache::Sheet(_, sheet) => (x, y, w, h) = sheet.frame_rect(frame, (width, height))?,
+                _ => return Err(anyhow!("图片没有设置精灵图:{:?}", self.key)),
+            }
+        }
+        if let Some((cx, cy, cw, ch)) = self.crop {
+            let (cx, cy) = (cx.min(w), cy.min(h));
+            (x, y, w, h) = (x + cx, y + cy, cw.min(w - cx), ch.min(h - cy));
+        }
+        if w == 0 || h == 0 {
+            return Err(anyhow!("图像裁剪区域为空: {:?}", self.crop));
+        }
+        Ok((x, y, w, h))
+    }
+
+    /// 根据源图区域计算缩放后的大小和可见部分
+    pub fn fit_layout(&self, crop: (u32, u32, u32, u32)) -> FitLayout {
+        let (cw, ch) = (crop.2.max(1), crop.3.max(1));
+        let scale = |v: u32, to: u32, from: u32| ((v as u64 * to as u64 + from as u64 / 2) / from as u64).max(1) as u32;
+        let (bw, bh) = match (self.width, self.height) {
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (w, scale(ch, w, cw)),
+            (None, Some(h)) => (scale(cw, h, ch), h),
+            (None, None) => (cw, ch),
+        };
+        let scaled = match self.fit {
+            ImageFit::Fill => (bw, bh),
+            ImageFit::None => (cw, ch),
+            // 比较 bw/cw 和 bh/ch，按较小(contain)或较大(cover)的比例缩放
+            ImageFit::Contain | ImageFit::Cover => {
+                let width_first = (bw as u64 * ch as u64 <= bh as u64 * cw as u64) == (self.fit == ImageFit::Contain);
+                if width_first {
+                    (bw, scale(ch, bw, cw))
+                } else {
+                    (scale(cw, bh, ch), bh)
+                }
+            }
+        };
+        // 缩放后的图片在显示区域中居中，超出显示区域的部分不绘制
+        let dx = (bw as i64 - scaled.0 as i64) / 2;
+        let dy = (bh as i64 - scaled.1 as i64) / 2;
+        let (vx, vy) = ((-dx).max(0) as u32, (-dy).max(0) as u32);
+        let visible = (vx, vy, scaled.0.min(bw), scaled.1.min(bh));
+        FitLayout { crop, scaled, visible, offset: (dx.max(0) as i32, dy.max(0) as i32) }
+    }
+}
+
+/// 一个方向上输出像素 range 的重采样权重 (源像素起点, 权重)，与 imageops::resize 的计算方法相同
+fn axis_weights(src: u32, dst: u32, range: Range<u32>, quality: ImageQuality) -> Vec<(u32, Vec<f32>)> {
+    let (kernel, support) = quality.kernel();
+    let ratio = src as f32 / dst as f32;
+    let sratio = ratio.max(1.0);
+    let src_support = support * sratio;
+    range
+        .map(|out| {
+            let input = (out as f32 + 0.5) * ratio;
+            let left = ((input - src_support).floor() as i64).clamp(0, src as i64 - 1);
+            let right = ((input + src_support).ceil() as i64).clamp(left + 1, src as i64);
+            let input = input - 0.5;
+            let mut weights: Vec<f32> = (left..right).map(|i| kernel((i as f32 - input) / sratio)).collect();
+            let sum: f32 = weights.iter().sum();
+            weights.iter_mut().for_each(|w| *w /= sum);
+            (left as u32, weights)
+        })
+        .collect()
+}
+
+/// 源图的 crop 区域缩放到 scaled 大小后，只计算其中 area (x, y, 宽, 高) 部分的像素
+/// get 返回源图像素的 RGBA，先按列再按行插值（与 imageops::resize 相同），每行只需要一行源图宽度的缓冲区
+pub(crate) fn resample<P: Pixel<Subpixel = u8>>(
+    get: impl Fn(u32, u32) -> [f32; 4],
+    (cx, cy, cw, ch): (u32, u32, u32, u32),
+    (sw, sh): (u32, u32),
+    (ax, ay, aw, ah): (u32, u32, u32, u32),
+    quality: ImageQuality,
+) -> ImageBuffer<P, Vec<u8>> {
+    let mut out = ImageBuffer::new(aw, ah);
+    if aw == 0 || ah == 0 {
+        return out;
+    }
+    let columns = axis_weights(cw, sw, ax..ax + aw, quality);
+    let rows = axis_weights(ch, sh, ay..ay + ah, quality);
+    // 可见部分用到的源图列
+    let first = columns.iter().map(|(left, _)| *left).min().unwrap_or(0);
+    let last = columns.iter().map(|(left, w)| left + w.len() as u32).max().unwrap_or(0);
+    let mut line = vec![[0f32; 4]; (last - first) as usize];
+    for (y, (top, wy)) in rows.iter().enumerate() {
+        for (i, value) in line.iter_mut().enumerate() {
+            *value = [0.; 4];
+            for (j, w) in wy.iter().enumerate() {
+                let p = get(cx + first + i as u32, cy + top + j as u32);
+                value.iter_mut().zip(p).for_each(|(v, p)| *v += p * w);
+            }
+        }
+        for (x, (left, wx)) in columns.iter().enumerate() {
+            let mut t = [0f32; 4];
+            for (i, w) in wx.iter().enumerate() {
+                let p = line[(left - first) as usize + i];
+                t.iter_mut().zip(p).for_each(|(t, p)| *t += p * w);
+            }
+            let t = t.map(|v| v.clamp(0., 255.).round() as u8);
+            out.put_pixel(x as u32, y as u32, *P::from_slice(&t[..P::CHANNEL_COUNT as usize]));
+        }
+    }
+    out
+}
+
+fn fit_source(source: &ImageCache, layout: &FitLayout, area: (u32, u32, u32, u32), quality: ImageQuality, (x, y): (i32, i32)) -> Element {
+    let (crop, scaled) = (layout.crop, layout.scaled);
+    match source {
+        ImageCache::RgbImage(img) => {
+            let get = |x, y| img.get_pixel(x, y).to_rgba().0.map(|v| v as f32);
+            Element::RawRgbImage((x, y, Box::new(resample(get, crop, scaled, area, quality))))
+        }
+        ImageCache::RgbaImage(img) => {
+            let get = |x, y| img.get_pixel(x, y).0.map(|v| v as f32);
+            Element::RawImage((x, y, Box::new(resample(get, crop, scaled, area, quality))))
+        }
+        ImageCache::Rgb565(img) => {
+            let get = |x, y| {
+                let (pixel, a) = img.get_pixel(x, y);
+                let (r, g, b) = rgb565_to_rgb888(pixel);
+                [r, g, b, a].map(|v| v as f32)
+            };
+            Element::RawImage((x, y, Box::new(resample(get, crop, scaled, area, quality))))
+        }
+        ImageCache::Sheet(img, _) => fit_source(img, layout, area, quality, (x, y)),
+    }
+}
+
+/// 源图区域（精灵图的帧、裁剪后）的大小
+pub(crate) fn source_size(image: &Image, image_cache: &HashMap<String, ImageCache>) -> Result<(u32, u32)> {
+    let (_, _, width, height) = match (&image.key, &image.base64) {
+        (Some(key), _) => image.source_rect(image_cache.get(key).ok_or(anyhow!("image key not exist:{key}"))?)?,
+        (None, Some(b64)) => image.source_rect(&decode_image(&decode_base64(b64)?)?)?,
+        (None, None) => return Err(anyhow!("请填写图像的\"key\"或者\"base64\"字符串")),
+    };
+    Ok((width, height))
+}
+
+/// 对 Image 元素引用的图片（缓存的key或base64）进行裁剪和缩放，返回可以直接绘制的 RawImage/RawRgbImage
+/// 只保留 clip（画布上需要绘制的区域）中的部分
+pub fn fit_image(image: &Image, image_cache: &HashMap<String, ImageCache>, clip: Bounds) -> Result<Element> {
+    let decoded;
+    let source = match (&image.key, &image.base64) {
+        (Some(key), _) => image_cache.get(key).ok_or(anyhow!("image key not exist:{key}"))?,
+        (None, Some(b64)) => {
+            decoded = decode_image(&decode_base64(b64)?)?;
+            &decoded
+        }
+        (None, None) => return Err(anyhow!("请填写图像的\"key\"或者\"base64\"字符串")),
+    };
+    let layout = image.fit_layout(image.source_rect(source)?);
+    let (vx, vy, vw, vh) = layout.visible;
+    if vw > MAX_IMAGE_SIZE || vh > MAX_IMAGE_SIZE {
+        return Err(anyhow!("图像显示区域{vw}x{vh}超过{MAX_IMAGE_SIZE}x{MAX_IMAGE_SIZE}"));
+    }
+    let (x, y) = (image.x + layout.offset.0, image.y + layout.offset.1);
+    let area = Bounds::new(x, y, vw, vh).intersection(&clip);
+    if area.is_empty() {
+        return Ok(Element::RawImage((x, y, Box::new(RgbaImage::new(0, 0)))));
+    }
+    // 可见部分在缩放后的图片中的位置
+    let visible = (vx + (area.left - x) as u32, vy + (area.top - y) as u32, area.width(), area.height());
+    Ok(fit_source(source, &layout, visible, image.quality, (area.left, area.top)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(json: &str) -> Image {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_fit_layout() {
+        let layout = |json: &str| image(json).fit_layout((0, 0, 200, 100));
+        // 只设置宽度时保持比例
+        let l = layout(r#"{"x":0,"y":0,"width":100}"#);
+        assert_eq!((l.scaled, l.visible, l.offset), ((100, 50), (0, 0, 100, 50), (0, 0)));
+        let l = layout(r#"{"x":0,"y":0,"width":100,"height":100,"fit":"contain"}"#);
+        assert_eq!((l.scaled, l.visible, l.offset), ((100, 50), (0, 0, 100, 50), (0, 25)));
+        let l = layout(r#"{"x":0,"y":0,"width":100,"height":100,"fit":"cover"}"#);
+        assert_eq!((l.scaled, l.visible, l.offset), ((200, 100), (50, 0, 100, 100), (0, 0)));
+        let l = layout(r#"{"x":0,"y":0,"width":50,"height":300,"fit":"none"}"#);
+        assert_eq!((l.scaled, l.visible, l.offset), ((200, 100), (75, 0, 50, 100), (0, 100)));
+
+        let source = ImageCache::RgbImage(Box::new(image::RgbImage::new(200, 100)));
+        let crop = image(r#"{"x":0,"y":0,"crop":[150,50,100,100],"height":25}"#).source_rect(&source).unwrap();
+        assert_eq!(crop, (150, 50, 50, 50));
+        assert_eq!(image(r#"{"x":0,"y":0,"height":25}"#).fit_layout(crop).scaled, (25, 25));
+        assert!(image(r#"{"x":0,"y":0,"crop":[200,0,10,10]}"#).source_rect(&source).is_err());
+        assert!(image(r#"{"x":0,"y":0,"frame":1}"#).source_rect(&source).is_err());
+    }
+
+    #[test]
+    fn test_resample_matches_resize() {
+        let source = RgbaImage::from_fn(20, 12, |x, y| image::Rgba([(x * 12) as u8, (y * 20) as u8, ((x * y) % 256) as u8, 128 + x as u8]));
+        let get = |x, y| source.get_pixel(x, y).0.map(|v| v as f32);
+        let filters = [
+            (ImageQuality::Nearest, image::imageops::FilterType::Nearest),
+            (ImageQuality::Bilinear, image::imageops::FilterType::Triangle),
+            (ImageQuality::Bicubic, image::imageops::FilterType::CatmullRom),
+            (ImageQuality::Lanczos, image::imageops::FilterType::Lanczos3),
+        ];
+        for (quality, filter) in filters {
+            for (crop, scaled) in [((0, 0, 20, 12), (50, 7)), ((3, 2, 10, 8), (7, 30))] {
+                let cropped = image::imageops::crop_imm(&source, crop.0, crop.1, crop.2, crop.3).to_image();
+                let full = image::imageops::resize(&cropped, scaled.0, scaled.1, filter);
+                let area = (2, 1, scaled.0 / 2, scaled.1 - 2);
+                let part: RgbaImage = resample(get, crop, scaled, area, quality);
+                let expected = image::imageops::crop_imm(&full, area.0, area.1, area.2, area.3).to_image();
+                assert_eq!(part, expected, "{quality:?} {crop:?} {scaled:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_fit_image_clip() {
+        let mut cache = HashMap::new();
+        cache.insert("a".to_string(), ImageCache::RgbImage(Box::new(image::RgbImage::new(1, 100))));
+        let canvas = Bounds::new(0, 0, 240, 240);
+        // cover 缩放后为 240x24000，只生成屏幕上可见的部分
+        let fit = |json: &str| fit_image(&image(json), &cache, canvas);
+        match fit(r#"{"x":0,"y":0,"key":"a","width":240,"height":240,"fit":"cover"}"#).unwrap() {
+            Element::RawRgbImage((x, y, img)) => assert_eq!((x, y, img.dimensions()), (0, 0, (240, 240))),
+            _ => panic!("expect rgb image"),
+        }
+        match fit(r#"{"x":-10,"y":200,"key":"a","width":100,"height":100}"#).unwrap() {
+            Element::RawRgbImage((x, y, img)) => assert_eq!((x, y, img.dimensions()), (0, 200, (90, 40))),
+            _ => panic!("expect rgb image"),
+        }
+        match fit(r#"{"x":300,"y":0,"key":"a","width":100,"height":100}"#).unwrap() {
+            Element::RawImage((_, _, img)) => assert_eq!(img.dimensions(), (0, 0)),
+            _ => panic!("expect empty image"),
+        }
+        assert!(fit(r#"{"x":0,"y":0,"key":"a","width":4000,"height":4000}"#).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::sprite::SpriteSheet;
+
+// 图片存储：上传的图片以原始文件保存在文件系统中（固件为 flash 上的 SPIFFS 分区），重启后不丢失
+// 绘制时按需解码到内存缓存 LruImageCache 中
+
+/// 保存固定图片 key 的文件，以 . 开头不会出现在图片列表中
+const PINNED_FILE: &str = ".pinned";
+
+/// key 的最大长度（SPIFFS 文件名最长31字节）
+pub const MAX_KEY_LEN: usize = 24;
+
+/// 存储中的一张图片
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StoredImage {
+    pub key: String,
+    /// 文件大小(字节)
+    pub size: u64,
+}
+
+/// 存储空间使用情况
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StoreUsage {
+    pub capacity: u64,
+    pub used: u64,
+    pub images: Vec<StoredImage>,
+}
+
+pub struct ImageStore {
+    dir: PathBuf,
+    /// 允许保存的图片总大小(字节)
+    capacity: u64,
+}
+
+/// key 用作文件名，只允许字母、数字和 `_-.`
+pub fn check_key(key: &str) -> Result<()> {
+    let valid = !key.is_empty()
+        && key.len() <= MAX_KEY_LEN
+        && !key.starts_with('.')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!("图片key只能包含字母、数字和_-.，且不超过{MAX_KEY_LEN}个字符:{key}"))
+    }
+}
+
+impl ImageStore {
+    pub fn new(dir: impl Into<PathBuf>, capacity: u64) -> Self {
+        Self { dir: dir.into(), capacity }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        check_key(key)?;
+        Ok(self.dir.join(key))
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.path(key).is_ok_and(|path| path.is_file())
+    }
+
+    /// 所有图片，按 key 排序
+    pub fn list(&self) -> Result<Vec<StoredImage>> {
+        let mut images = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let key = entry.file_name().to_string_lossy().to_string();
+            if check_key(&key).is_err() {
+                continue;
+            }
+            let size = entry.metadata()?.len();
+            images.push(StoredImage { key, size });
+        }
+        images.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(images)
+    }
+
+    pub fn usage(&self) -> Result<StoreUsage> {
+        let images = self.list()?;
+        let used = images.iter().map(|i| i.size).sum();
+        Ok(StoreUsage { capacity: self.capacity, used, images })
+    }
+
+    /// 保存图片，key 已存在时覆盖
+    pub fn save(&self, key: &str, data: &[u8]) -> Result<()> {
+        let path = self.path(key)?;
+        let usage = self.usage()?;
+        let old = usage.images.iter().find(|i| i.key == key).map_or(0, |i| i.size);
+        let free = self.capacity.saturating_sub(usage.used - old);
+        if data.len() as u64 > free {
+            return Err(anyhow!("图片存储空间不足: 需要{}字节, 剩余{}字节", data.len(), free));
+        }
+        fs::write(path, data)?;
+        Ok(())
+    }
+
+    pub fn load(&self, key: &str) -> Result<Vec<u8>> {
+        let path = self.path(key)?;
+        if !path.is_file() {
+            return Err(anyhow!("image key not exist:{key}"));
+        }
+        Ok(fs::read(path)?)
+    }
+
+    /// 固定的图片 key（每行一个）
+    pub fn pinned(&self) -> Result<Vec<String>> {
+        let path = self.dir.join(PINNED_FILE);
+        if !path.is_file() {
+            return Ok(vec![]);
+        }
+        let text = fs::read_to_string(path)?;
+        Ok(text.lines().filter(|key| check_key(key).is_ok()).map(|key| key.to_string()).collect())
+    }
+
+    pub fn save_pinned(&self, keys: &[String]) -> Result<()> {
+        fs::write(self.dir.join(PINNED_FILE), keys.join("\n"))?;
+        Ok(())
+    }
+
+    /// 精灵图定义保存在 .{key}.json 中
+    fn sprite_sheet_path(&self, key: &str) -> Result<PathBuf> {
+        check_key(key)?;
+        Ok(self.dir.join(format!(".{key}.json")))
+    }
+
+    pub fn sprite_sheet(&self, key: &str) -> Result<Option<SpriteSheet>> {
+        let path = self.sprite_sheet_path(key)?;
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&fs::read(path)?)?))
+    }
+
+    /// 设置图片的精灵图定义，None 时删除
+    pub fn save_sprite_sheet(&self, key: &str, sheet: Option<&SpriteSheet>) -> Result<()> {
+        let path = self.sprite_sheet_path(key)?;
+        match sheet {
+            Some(sheet) => {
+                if !self.contains(key) {
+                    return Err(anyhow!("image key not exist:{key}"));
+                }
+                sheet.check()?;
+                fs::write(path, serde_json::to_vec(sheet)?)?;
+            }
+            None if path.is_file() => fs::remove_file(path)?,
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// 删除图片（和精灵图定义），返回图片是否存在
+    pub fn remove(&self, key: &str) -> Result<bool> {
+        let path = self.path(key)?;
+        if !path.is_file() {
+            return Ok(false);
+        }
+        fs::remove_file(path)?;
+        self.save_sprite_sheet(key, None)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str, capacity: u64) -> ImageStore {
+        let dir = std::env::temp_dir().join(format!("wifi-screen-image-store-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        ImageStore::new(dir, capacity)
+    }
+
+    #[test]
+    fn test_save_list_remove() {
+        let store = store("basic", 100);
+        assert!(store.save("a.png", &[1; 60]).is_ok());
+        assert!(store.save("b", &[2; 50]).is_err());
+        // 覆盖时只计算新旧文件大小之差
+        assert!(store.save("a.png", &[3; 100]).is_ok());
+        assert!(store.save("../x", &[1]).is_err());
+        assert!(store.save("中文", &[1]).is_err());
+
+        let usage = store.usage().unwrap();
+        assert_eq!(usage.used, 100);
+        assert_eq!(usage.images, vec![StoredImage { key: "a.png".to_string(), size: 100 }]);
+        assert_eq!(store.load("a.png").unwrap(), vec![3; 100]);
+
+        let sheet = SpriteSheet { cell: Some((10, 10)), ..Default::default() };
+        store.save_sprite_sheet("a.png", Some(&sheet)).unwrap();
+        assert_eq!(store.sprite_sheet("a.png").unwrap(), Some(sheet.clone()));
+        assert!(store.save_sprite_sheet("b", Some(&sheet)).is_err());
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        assert!(store.remove("a.png").unwrap());
+        assert!(!store.remove("a.png").unwrap());
+        assert_eq!(store.sprite_sheet("a.png").unwrap(), None);
+        assert!(store.load("a.png").is_err());
+
+        store.save_pinned(&["a".to_string(), "b".to_string()]).unwrap();
+        assert_eq!(store.pinned().unwrap(), vec!["a", "b"]);
+        assert!(store.list().unwrap().is_empty());
+    }
+}
@@ -34,6 +34,24 @@ pub fn draw_cubic_bezier_curve_mut<C>(
 ) where
     C: Canvas,
 {
+    let points = cubic_bezier_curve_points(start, end, control_a, control_b);
+    for window in points.windows(2) {
+        // round to nearest pixel, to avoid ugly line artifacts
+        let round = |(x, y): (f32, f32)| (x.round(), y.round());
+        draw_line_segment_mut(canvas, round(window[0]), round(window[1]), color);
+    }
+}
+
+/// Samples points along a cubic Bézier curve, so that the curve can be
+/// approximated by the line segments connecting them.
+///
+/// The first point is `start` and the last point is `end`.
+pub fn cubic_bezier_curve_points(
+    start: (f32, f32),
+    end: (f32, f32),
+    control_a: (f32, f32),
+    control_b: (f32, f32),
+) -> Vec<(f32, f32)> {
     // Bezier Curve function from: https://pomax.github.io/bezierinfo/#control
     let cubic_bezier_curve = |t: f32| {
         let t2 = t * t;
@@ -49,7 +67,7 @@ pub fn draw_cubic_bezier_curve_mut<C>(
             + (3.0 * control_a.1 * mt2 * t)
             + (3.0 * control_b.1 * mt * t2)
             + (end.1 * t3);
-        (x.round(), y.round()) // round to nearest pixel, to avoid ugly line artifacts
+        (x, y)
     };
 
     let distance = |point_a: (f32, f32), point_b: (f32, f32)| {
@@ -63,17 +81,9 @@ pub fn draw_cubic_bezier_curve_mut<C>(
     // Use hyperbola function to give shorter curves a bias in number of line segments.
     let num_segments: i32 = ((curve_length_bound.powi(2) + 800.0).sqrt() / 8.0) as i32;
 
-    // Sample points along the curve and connect them with line segments.
+    // Sample points along the curve.
     let t_interval = 1f32 / (num_segments as f32);
-    let mut t1 = 0f32;
-    for i in 0..num_segments {
-        let t2 = (i as f32 + 1.0) * t_interval;
-        draw_line_segment_mut(
-            canvas,
-            cubic_bezier_curve(t1),
-            cubic_bezier_curve(t2),
-            color,
-        );
-        t1 = t2;
-    }
+    (0..=num_segments)
+        .map(|i| cubic_bezier_curve(i as f32 * t_interval))
+        .collect()
 }
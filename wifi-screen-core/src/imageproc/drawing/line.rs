@@ -281,12 +281,15 @@ fn plot_wu_line<I, T, B>(
     let dx = end.0 - start.0;
     let dy = end.1 - start.1;
     let gradient = dy as f32 / dx as f32;
-    let mut fy = start.1 as f32;
 
     for x in start.0..(end.0 + 1) {
-        plotter.plot(x, fy as i32, color, 1.0 - fy.fract());
-        plotter.plot(x, fy as i32 + 1, color, fy.fract());
-        fy += gradient;
+        // Compute the offset relative to the start point (instead of accumulating
+        // the absolute y) and floor it, so that a translated line, e.g. one starting
+        // outside the image at negative coordinates, is plotted at the same pixels.
+        let fy = gradient * (x - start.0) as f32;
+        let (y, fract) = (start.1 + fy.floor() as i32, fy - fy.floor());
+        plotter.plot(x, y, color, 1.0 - fract);
+        plotter.plot(x, y + 1, color, fract);
     }
 }
 
@@ -1,7 +1,7 @@
 //! Helpers for drawing basic shapes on images.
 
 mod bezier;
-pub use self::bezier::{draw_cubic_bezier_curve, draw_cubic_bezier_curve_mut};
+pub use self::bezier::{cubic_bezier_curve_points, draw_cubic_bezier_curve, draw_cubic_bezier_curve_mut};
 
 mod canvas;
 pub use self::canvas::{Blend, Canvas};
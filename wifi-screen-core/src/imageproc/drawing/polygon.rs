@@ -201,7 +201,9 @@ where
         }
 
         intersections.sort_unstable();
-        intersections.chunks(2).for_each(|range| {
+        // A vertex lying exactly on the scanline can produce an odd number of
+        // intersections, ignore the unpaired one instead of panicking.
+        intersections.chunks_exact(2).for_each(|range| {
             let mut from = min(range[0], width as i32);
             let mut to = min(range[1], width as i32 - 1);
             if from < width as i32 && to >= 0 {
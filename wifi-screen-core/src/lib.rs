@@ -0,0 +1,32 @@
+// ESP32-WiFi-Screen 核心库：与平台无关的渲染和协议代码
+// 不依赖 esp-idf，固件、设备模拟器(tools/emulator)和上位机共用
+
+pub mod antialias;
+pub mod chart;
+pub mod device;
+pub mod font;
+pub mod framebuffer;
+pub mod gauge;
+pub mod gif_player;
+pub mod gradient;
+pub mod group;
+#[allow(unused)]
+pub mod imageproc;
+pub mod image_fit;
+pub mod image_cache;
+pub mod image_store;
+pub mod path;
+pub mod qrcode;
+pub mod render;
+pub mod rich_text;
+pub mod scene;
+pub mod splash;
+pub mod sprite;
+pub mod text_effect;
+pub mod template;
+pub mod text_layout;
+pub mod text_transform;
+pub mod rgb565;
+pub mod delta_decoder;
+pub mod usb_protocol;
+pub mod utils;
@@ -0,0 +1,309 @@
+use anyhow::{anyhow, Result};
+
+use crate::imageproc::drawing::cubic_bezier_curve_points;
+
+// SVG 路径：解析路径数据（d 属性），曲线和圆弧分割为折线，由 CoverageMask 填充和描边
+// 支持 M/L/H/V/C/Q/A/Z 及对应的小写（相对坐标）命令
+
+/// 圆弧分割为折线时每段的长度(像素)
+const SEGMENT_LENGTH: f32 = 2.0;
+/// 每段圆弧最多分割的段数，避免超大半径的圆弧生成过多的点
+const MAX_ARC_SEGMENTS: usize = 256;
+
+/// 由折线组成的子路径
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubPath {
+    pub points: Vec<(f32, f32)>,
+    /// 是否闭合（首尾相连描边）
+    pub closed: bool,
+}
+
+impl SubPath {
+    pub fn new(points: Vec<(f32, f32)>, closed: bool) -> Self {
+        Self { points, closed }
+    }
+}
+
+/// 所有子路径的外接矩形 (left, top, right, bottom)
+pub fn paths_bounds(paths: &[SubPath]) -> Option<(f32, f32, f32, f32)> {
+    paths.iter().flat_map(|path| &path.points).fold(None, |bounds, &(x, y)| match bounds {
+        None => Some((x, y, x, y)),
+        Some((l, t, r, b)) => Some((l.min(x), t.min(y), r.max(x), b.max(y))),
+    })
+}
+
+struct Tokens<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Tokens<'_> {
+    fn skip_separators(&mut self) {
+        while self.pos < self.data.len() && (self.data[self.pos].is_ascii_whitespace() || self.data[self.pos] == b',') {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_separators();
+        self.data.get(self.pos).copied()
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        let c = self.peek().filter(u8::is_ascii_alphabetic)?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn number(&mut self) -> Result<f32> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |tokens: &mut Self| {
+            while tokens.pos < tokens.data.len() && tokens.data[tokens.pos].is_ascii_digit() {
+                tokens.pos += 1;
+            }
+        };
+        if matches!(self.data.get(self.pos), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        digits(self);
+        if self.data.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            digits(self);
+        }
+        if matches!(self.data.get(self.pos), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.data.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            digits(self);
+        }
+        std::str::from_utf8(&self.data[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|n: &f32| n.is_finite())
+            .ok_or_else(|| anyhow!("路径数据错误: 位置{start}应为数字"))
+    }
+
+    /// 圆弧的标志位，可以不加分隔符，如 "a10 10 0 01 20 0"
+    fn flag(&mut self) -> Result<bool> {
+        match self.peek() {
+            Some(b'0') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            _ => Err(anyhow!("路径数据错误: 位置{}应为0或1", self.pos)),
+        }
+    }
+
+    fn point(&mut self) -> Result<(f32, f32)> {
+        Ok((self.number()?, self.number()?))
+    }
+}
+
+struct PathBuilder {
+    offset: (f32, f32),
+    scale: f32,
+    paths: Vec<SubPath>,
+    points: Vec<(f32, f32)>,
+    /// 当前点和子路径的起点（路径坐标）
+    current: (f32, f32),
+    start: (f32, f32),
+}
+
+impl PathBuilder {
+    /// 路径坐标转换为画布坐标
+    fn transform(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (self.offset.0 + x * self.scale, self.offset.1 + y * self.scale)
+    }
+
+    fn finish(&mut self, closed: bool) {
+        let points = std::mem::take(&mut self.points);
+        if points.len() >= 2 {
+            self.paths.push(SubPath::new(points, closed));
+        }
+    }
+
+    fn move_to(&mut self, p: (f32, f32)) {
+        self.finish(false);
+        self.current = p;
+        self.start = p;
+    }
+
+    /// 添加画布坐标的点，子路径的第一个点为当前点
+    fn push(&mut self, points: impl IntoIterator<Item = (f32, f32)>) {
+        if self.points.is_empty() {
+            self.points.push(self.transform(self.current));
+        }
+        self.points.extend(points);
+    }
+
+    fn line_to(&mut self, p: (f32, f32)) {
+        self.push([self.transform(p)]);
+        self.current = p;
+    }
+
+    fn cubic_to(&mut self, c1: (f32, f32), c2: (f32, f32), p: (f32, f32)) {
+        let points = cubic_bezier_curve_points(self.transform(self.current), self.transform(p), self.transform(c1), self.transform(c2));
+        self.push(points.into_iter().skip(1));
+        self.current = p;
+    }
+
+    /// 二次贝塞尔曲线转换为三次贝塞尔曲线
+    fn quad_to(&mut self, c: (f32, f32), p: (f32, f32)) {
+        let p0 = self.current;
+        let c1 = (p0.0 + (c.0 - p0.0) * 2.0 / 3.0, p0.1 + (c.1 - p0.1) * 2.0 / 3.0);
+        let c2 = (p.0 + (c.0 - p.0) * 2.0 / 3.0, p.1 + (c.1 - p.1) * 2.0 / 3.0);
+        self.cubic_to(c1, c2, p);
+    }
+
+    /// 椭圆弧，按 SVG 规范将端点参数转换为圆心参数
+    fn arc_to(&mut self, (rx, ry): (f32, f32), rotation: f32, large_arc: bool, sweep: bool, p: (f32, f32)) {
+        let p0 = self.current;
+        if p0 == p {
+            return;
+        }
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 {
+            return self.line_to(p);
+        }
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let (dx, dy) = ((p0.0 - p.0) / 2.0, (p0.1 - p.1) / 2.0);
+        let (x1, y1) = (cos * dx + sin * dy, -sin * dx + cos * dy);
+        // 半径太小时等比放大
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let sign = if large_arc == sweep { -1.0 } else { 1.0 };
+        let coef = sign * (num / den).max(0.0).sqrt();
+        let (cx1, cy1) = (coef * rx * y1 / ry, -coef * ry * x1 / rx);
+        let (cx, cy) = (cos * cx1 - sin * cy1 + (p0.0 + p.0) / 2.0, sin * cx1 + cos * cy1 + (p0.1 + p.1) / 2.0);
+
+        let angle = |(ux, uy): (f32, f32), (vx, vy): (f32, f32)| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+        let u = ((x1 - cx1) / rx, (y1 - cy1) / ry);
+        let v = ((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+        let theta = angle((1.0, 0.0), u);
+        let mut delta = angle(u, v);
+        if !sweep && delta > 0.0 {
+            delta -= std::f32::consts::TAU;
+        } else if sweep && delta < 0.0 {
+            delta += std::f32::consts::TAU;
+        }
+
+        let length = delta.abs() * (rx + ry) / 2.0 * self.scale;
+        let segments = ((length / SEGMENT_LENGTH).ceil() as usize).clamp(4, MAX_ARC_SEGMENTS);
+        let points: Vec<(f32, f32)> = (1..=segments)
+            .map(|i| {
+                let (s, c) = (theta + delta * i as f32 / segments as f32).sin_cos();
+                self.transform((cx + rx * c * cos - ry * s * sin, cy + rx * c * sin + ry * s * cos))
+            })
+            .collect();
+        self.push(points);
+        self.current = p;
+    }
+}
+
+/// 解析 SVG 路径数据，路径坐标按 scale 缩放后平移到 offset
+pub fn parse_path(d: &str, offset: (i32, i32), scale: f32) -> Result<Vec<SubPath>> {
+    let mut tokens = Tokens { data: d.as_bytes(), pos: 0 };
+    let mut builder = PathBuilder {
+        offset: (offset.0 as f32, offset.1 as f32),
+        scale,
+        paths: vec![],
+        points: vec![],
+        current: (0.0, 0.0),
+        start: (0.0, 0.0),
+    };
+    let mut command: Option<u8> = None;
+    while let Some(c) = tokens.peek() {
+        let c = match tokens.command() {
+            Some(c) => c,
+            // 省略命令时重复上一个命令，M 之后为 L
+            None => match command {
+                Some(b'M') => b'L',
+                Some(b'm') => b'l',
+                Some(c) if !c.eq_ignore_ascii_case(&b'z') => c,
+                _ => return Err(anyhow!("路径数据错误: 位置{}应为命令，得到'{}'", tokens.pos, c as char)),
+            },
+        };
+        command = Some(c);
+        let relative = c.is_ascii_lowercase();
+        let (cx, cy) = builder.current;
+        let abs = |(x, y): (f32, f32)| if relative { (cx + x, cy + y) } else { (x, y) };
+        match c.to_ascii_uppercase() {
+            b'M' => builder.move_to(abs(tokens.point()?)),
+            b'L' => builder.line_to(abs(tokens.point()?)),
+            b'H' => {
+                let x = tokens.number()?;
+                builder.line_to((if relative { cx + x } else { x }, cy));
+            }
+            b'V' => {
+                let y = tokens.number()?;
+                builder.line_to((cx, if relative { cy + y } else { y }));
+            }
+            b'C' => {
+                let (c1, c2, p) = (abs(tokens.point()?), abs(tokens.point()?), abs(tokens.point()?));
+                builder.cubic_to(c1, c2, p);
+            }
+            b'Q' => {
+                let (c1, p) = (abs(tokens.point()?), abs(tokens.point()?));
+                builder.quad_to(c1, p);
+            }
+            b'A' => {
+                let radius = tokens.point()?;
+                let rotation = tokens.number()?;
+                let (large_arc, sweep) = (tokens.flag()?, tokens.flag()?);
+                builder.arc_to(radius, rotation, large_arc, sweep, abs(tokens.point()?));
+            }
+            b'Z' => {
+                builder.finish(true);
+                builder.current = builder.start;
+            }
+            _ => return Err(anyhow!("路径数据错误: 不支持的命令'{}'", c as char)),
+        }
+    }
+    builder.finish(false);
+    Ok(builder.paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_path() {
+        let paths = parse_path("M1,2 L11 2 v10 h-10z m20 0 l5 5 5-5", (100, 0), 2.0).unwrap();
+        assert_eq!(paths, vec![
+            SubPath::new(vec![(102.0, 4.0), (122.0, 4.0), (122.0, 24.0), (102.0, 24.0)], true),
+            SubPath::new(vec![(142.0, 4.0), (152.0, 14.0), (162.0, 4.0)], false),
+        ]);
+
+        // 半圆：从 (0,0) 到 (20,0)，顺时针经过 (10,-10)
+        let paths = parse_path("M0 0A10 10 0 0120 0", (0, 0), 1.0).unwrap();
+        let points = &paths[0].points;
+        assert!(points.iter().any(|p| (p.0 - 10.0).abs() < 0.5 && (p.1 + 10.0).abs() < 0.1));
+        let last = points[points.len() - 1];
+        assert!((last.0 - 20.0).abs() < 0.01 && last.1.abs() < 0.01);
+
+        let paths = parse_path("M0 0Q10 10 20 0C20 10 0 10 0 0", (0, 0), 1.0).unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths_bounds(&paths).map(|b| b.0), Some(0.0));
+
+        assert!(parse_path("M0 0 S10 10 20 0", (0, 0), 1.0).is_err());
+        assert!(parse_path("L10", (0, 0), 1.0).is_err());
+        assert!(parse_path("10 10", (0, 0), 1.0).is_err());
+        assert!(parse_path("M0 0 L1e39 0", (0, 0), 1.0).is_err());
+
+        // 超大半径的圆弧分割段数有上限
+        let paths = parse_path("M0 0 A1000000 1000000 0 1 1 10 0", (0, 0), 1.0).unwrap();
+        assert!(paths[0].points.len() <= MAX_ARC_SEGMENTS + 1);
+    }
+}
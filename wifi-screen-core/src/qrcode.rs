@@ -0,0 +1,124 @@
+use anyhow::{anyhow, Result};
+use image::RgbImage;
+use qrcodegen::QrCodeEcc;
+use serde::Deserialize;
+
+use crate::render::{blend_pixel, CSSColor};
+
+// 二维码：在设备上生成，用于显示设备IP、配置页面地址等
+
+/// 默认每个模块的大小(像素)
+const DEFAULT_MODULE_SIZE: u32 = 2;
+/// 默认静区（四周空白）的宽度(模块)
+const DEFAULT_MARGIN: u32 = 2;
+
+/// 纠错等级，等级越高可以容忍的污损越多，二维码也越大
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QrEcc {
+    /// 约 7%
+    Low,
+    /// 约 15%
+    #[default]
+    Medium,
+    /// 约 25%
+    Quartile,
+    /// 约 30%
+    High,
+}
+
+impl From<QrEcc> for QrCodeEcc {
+    fn from(ecc: QrEcc) -> Self {
+        match ecc {
+            QrEcc::Low => QrCodeEcc::Low,
+            QrEcc::Medium => QrCodeEcc::Medium,
+            QrEcc::Quartile => QrCodeEcc::Quartile,
+            QrEcc::High => QrCodeEcc::High,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct QrCode {
+    /// 左上角（包括静区）
+    pub x: i32,
+    pub y: i32,
+    /// 每个模块的大小(像素)，默认 2
+    pub module_size: Option<u32>,
+    pub data: String,
+    /// 纠错等级 low/medium/quartile/high，默认 medium
+    #[serde(default)]
+    pub ecc: QrEcc,
+    /// 前景色，默认黑色
+    pub fg: Option<CSSColor>,
+    /// 背景色（包括静区），默认白色
+    pub bg: Option<CSSColor>,
+    /// 静区宽度(模块)，默认 2
+    pub margin: Option<u32>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+}
+
+impl QrCode {
+    pub fn encode(&self) -> Result<qrcodegen::QrCode> {
+        qrcodegen::QrCode::encode_text(&self.data, self.ecc.into())
+            .map_err(|_| anyhow!("二维码数据太长: {}字节", self.data.len()))
+    }
+
+    fn module_size(&self) -> u32 {
+        self.module_size.unwrap_or(DEFAULT_MODULE_SIZE).max(1)
+    }
+
+    fn margin(&self) -> u32 {
+        self.margin.unwrap_or(DEFAULT_MARGIN)
+    }
+
+    /// 二维码的边长(像素)，包括静区
+    pub fn size(&self) -> Result<u32> {
+        let modules = self.encode()?.size() as u32;
+        Ok((modules + self.margin() * 2) * self.module_size())
+    }
+
+    pub(crate) fn draw(&self, canvas: &mut RgbImage) -> Result<()> {
+        let qr = self.encode()?;
+        let (module_size, margin) = (self.module_size() as i32, self.margin() as i32);
+        let fg = self.fg.as_ref().map_or([0, 0, 0, 255], CSSColor::rgba);
+        let bg = self.bg.as_ref().map_or([255, 255, 255, 255], CSSColor::rgba);
+        let side = (qr.size() + margin * 2) * module_size;
+        let (width, height) = (canvas.width() as i32, canvas.height() as i32);
+        // 只遍历画布内的像素
+        for py in self.y.max(0)..(self.y + side).min(height) {
+            let my = (py - self.y) / module_size - margin;
+            for px in self.x.max(0)..(self.x + side).min(width) {
+                let mx = (px - self.x) / module_size - margin;
+                let color = if qr.get_module(mx, my) { fg } else { bg };
+                blend_pixel(canvas.get_pixel_mut(px as u32, py as u32), color);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qrcode() {
+        let qr: QrCode = serde_json::from_str(r#"{"x":0,"y":0,"data":"http://192.168.72.1","ecc":"low"}"#).unwrap();
+        // 版本 2 为 25x25 模块
+        assert_eq!(qr.size().unwrap(), (25 + 4) * 2);
+
+        let mut canvas = RgbImage::new(60, 60);
+        qr.draw(&mut canvas).unwrap();
+        // 静区为白色，左上角定位图案的外框为黑色
+        assert_eq!(canvas.get_pixel(1, 1).0, [255, 255, 255]);
+        assert_eq!(canvas.get_pixel(4, 4).0, [0, 0, 0]);
+        assert_eq!(canvas.get_pixel(57, 57).0, [255, 255, 255]);
+        // 超出二维码的部分不绘制
+        assert_eq!(canvas.get_pixel(58, 58).0, [0, 0, 0]);
+
+        let qr = QrCode { data: "x".repeat(4000), ..qr };
+        assert!(qr.size().is_err());
+    }
+}
@@ -0,0 +1,1349 @@
+use ab_glyph::{FontRef, FontVec};
+use anyhow::{anyhow, Result};
+use embedded_graphics::geometry::AngleUnit;
+use embedded_graphics::prelude::{DrawTarget, Drawable, OriginDimensions, Point, Primitive, RgbColor, Size};
+use embedded_graphics::primitives::{PrimitiveStyle, PrimitiveStyleBuilder, StyledDrawable};
+use embedded_graphics::pixelcolor::Rgb888;
+use image::imageops::{self, overlay};
+use image::{GenericImageView, Pixel, Rgb, RgbImage, Rgba, RgbaImage};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use crate::antialias::{ellipse_points, CoverageMask};
+use crate::chart::{BarChart, LineChart, Sparkline};
+use crate::font::{layout_glyphs, TextFont};
+use crate::gauge::{Gauge, Meter, ProgressBar};
+use crate::gradient::{Fill, GradientPainter};
+use crate::group::Group;
+use crate::image_fit::{fit_image, ImageFit, ImageQuality};
+use crate::path::{parse_path, paths_bounds, SubPath};
+use crate::qrcode::QrCode;
+use crate::rich_text::RichText;
+use crate::sprite::{SpriteFrame, SpriteSheet};
+use crate::imageproc::drawing::cubic_bezier_curve_points;
+use crate::imageproc::pixelops::weighted_sum;
+use crate::rgb565::{rgb565_to_rgb888, Rgb565Image};
+use crate::scene::{element_bounds, Bounds};
+use crate::text_effect::{draw_text_effects, TextShadow};
+use crate::text_layout::{layout_text, TextAlign, TextVAlign, TextWrap};
+use crate::text_transform::draw_rotated_text;
+use crate::utils::decode_base64;
+use tjpgdec_rs::{JpegDecoder, MemoryPool, RECOMMENDED_POOL_SIZE};
+
+// 画布渲染（与平台无关）：元素定义、文字/图像/图形绘制、JPEG解码
+
+pub enum ImageCache {
+    RgbImage(Box<RgbImage>),
+    RgbaImage(Box<RgbaImage>),
+    /// 上传的图片，内存占用是 RgbImage/RgbaImage 的一半左右，不透明时可以直接发送到屏幕
+    Rgb565(Box<Rgb565Image>),
+    /// 设置了精灵图的图片，Image 元素可以通过 frame 只绘制其中一帧
+    Sheet(Box<ImageCache>, Box<SpriteSheet>),
+}
+
+impl ImageCache {
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            ImageCache::RgbImage(img) => img.dimensions(),
+            ImageCache::RgbaImage(img) => img.dimensions(),
+            ImageCache::Rgb565(img) => img.dimensions(),
+            ImageCache::Sheet(img, _) => img.dimensions(),
+        }
+    }
+
+    /// 去掉精灵图信息后的图片
+    pub fn image(&self) -> &ImageCache {
+        match self {
+            ImageCache::Sheet(img, _) => img.image(),
+            img => img,
+        }
+    }
+
+    pub fn into_image(self) -> ImageCache {
+        match self {
+            ImageCache::Sheet(img, _) => img.into_image(),
+            img => img,
+        }
+    }
+
+    pub fn to_rgba_image(&self) -> RgbaImage {
+        match self.image() {
+            ImageCache::RgbImage(img) => RgbaImage::from_fn(img.width(), img.height(), |x, y| img.get_pixel(x, y).to_rgba()),
+            ImageCache::RgbaImage(img) => img.as_ref().clone(),
+            ImageCache::Rgb565(img) => img.to_rgba_image(),
+            ImageCache::Sheet(img, _) => img.to_rgba_image(),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct CSSColor(pub csscolorparser::Color);
+
+impl CSSColor {
+    pub fn rgba(&self) -> [u8; 4] {
+        self.0.to_rgba8()
+    }
+}
+
+impl<'de> Deserialize<'de> for CSSColor {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        csscolorparser::parse(&s)
+            .map(CSSColor)
+            .map_err(|err| serde::de::Error::custom(format_args!("invalid css color: {err:?}")))
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub enum Element {
+    Text(Text),
+    RichText(RichText),
+    // #[serde(skip)]
+    // TextWithFont((Text, FontRef<'static>)),
+    Image(Image),
+    #[serde(skip)]
+    #[allow(dead_code)]
+    RawImage((i32, i32, Box<RgbaImage>)),
+    #[serde(skip)]
+    RawRgbImage((i32, i32, Box<RgbImage>)),
+    Line(Line),
+    Circle(Circle),
+    Ellipse(Ellipse),
+    Arc(Arc),
+    Sector(Sector),
+    Rectangle(Rectangle),
+    RoundedRectangle(RoundedRectangle),
+    Polyline(Polyline),
+    Triangle(Triangle),
+    Polygon(Polygon),
+    CubicBezier(CubicBezier),
+    Path(Path),
+    LineChart(LineChart),
+    BarChart(BarChart),
+    Sparkline(Sparkline),
+    Gauge(Gauge),
+    ProgressBar(ProgressBar),
+    Meter(Meter),
+    QrCode(QrCode),
+    Group(Group),
+}
+
+impl Element {
+    /// 元素的位置（左上角或起点）
+    pub fn position(&self) -> (i32, i32) {
+        match self {
+            Element::Text(text) => (text.x, text.y),
+            Element::RichText(text) => (text.x, text.y),
+            Element::Image(image) => (image.x, image.y),
+            Element::RawImage((x, y, _)) | Element::RawRgbImage((x, y, _)) => (*x, *y),
+            Element::Line(line) => line.start,
+            Element::Circle(circle) => circle.top_left,
+            Element::Ellipse(ellipse) => ellipse.top_left,
+            Element::Arc(arc) => arc.top_left,
+            Element::Sector(sector) => sector.top_left,
+            Element::Rectangle(rect) => (rect.left, rect.top),
+            Element::RoundedRectangle(rect) => (rect.left, rect.top),
+            Element::Polyline(polyline) => polyline.points.first().copied().unwrap_or_default(),
+            Element::Triangle(triangle) => triangle.vertex1,
+            Element::Polygon(polygon) => polygon.points.first().copied().unwrap_or_default(),
+            Element::CubicBezier(curve) => curve.start,
+            Element::Path(path) => (path.x, path.y),
+            Element::LineChart(chart) => (chart.x, chart.y),
+            Element::BarChart(chart) => (chart.x, chart.y),
+            Element::Sparkline(chart) => (chart.x, chart.y),
+            Element::Gauge(gauge) => gauge.top_left,
+            Element::ProgressBar(bar) => (bar.left, bar.top),
+            Element::Meter(meter) => meter.top_left,
+            Element::QrCode(qr) => (qr.x, qr.y),
+            Element::Group(group) => (group.x, group.y),
+        }
+    }
+
+    /// 元素的不透明度 0~1
+    pub fn opacity(&self) -> f32 {
+        let opacity = match self {
+            Element::Text(text) => text.opacity,
+            Element::RichText(text) => text.opacity,
+            Element::Image(image) => image.opacity,
+            Element::RawImage(_) | Element::RawRgbImage(_) => None,
+            Element::Line(line) => line.opacity,
+            Element::Circle(circle) => circle.opacity,
+            Element::Ellipse(ellipse) => ellipse.opacity,
+            Element::Arc(arc) => arc.opacity,
+            Element::Sector(sector) => sector.opacity,
+            Element::Rectangle(rect) => rect.opacity,
+            Element::RoundedRectangle(rect) => rect.opacity,
+            Element::Polyline(polyline) => polyline.opacity,
+            Element::Triangle(triangle) => triangle.opacity,
+            Element::Polygon(polygon) => polygon.opacity,
+            Element::CubicBezier(curve) => curve.opacity,
+            Element::Path(path) => path.opacity,
+            Element::LineChart(chart) => chart.opacity,
+            Element::BarChart(chart) => chart.opacity,
+            Element::Sparkline(chart) => chart.opacity,
+            Element::Gauge(gauge) => gauge.opacity,
+            Element::ProgressBar(bar) => bar.opacity,
+            Element::Meter(meter) => meter.opacity,
+            Element::QrCode(qr) => qr.opacity,
+            Element::Group(group) => group.opacity,
+        };
+        opacity.unwrap_or(1.0).clamp(0.0, 1.0)
+    }
+
+    /// 平移元素
+    pub fn translate(&mut self, dx: i32, dy: i32) {
+        fn offset(p: &mut (i32, i32), dx: i32, dy: i32) {
+            p.0 += dx;
+            p.1 += dy;
+        }
+        match self {
+            Element::Text(text) => {
+                text.x += dx;
+                text.y += dy;
+                if let Some(anchor) = text.anchor.as_mut() {
+                    offset(anchor, dx, dy);
+                }
+            }
+            Element::RichText(text) => {
+                text.x += dx;
+                text.y += dy;
+            }
+            Element::Image(image) => {
+                image.x += dx;
+                image.y += dy;
+            }
+            Element::RawImage((x, y, _)) | Element::RawRgbImage((x, y, _)) => {
+                *x += dx;
+                *y += dy;
+            }
+            Element::Line(line) => {
+                offset(&mut line.start, dx, dy);
+                offset(&mut line.end, dx, dy);
+            }
+            Element::Circle(circle) => offset(&mut circle.top_left, dx, dy),
+            Element::Ellipse(ellipse) => offset(&mut ellipse.top_left, dx, dy),
+            Element::Arc(arc) => offset(&mut arc.top_left, dx, dy),
+            Element::Sector(sector) => offset(&mut sector.top_left, dx, dy),
+            Element::Rectangle(rect) => {
+                rect.left += dx;
+                rect.top += dy;
+            }
+            Element::RoundedRectangle(rect) => {
+                rect.left += dx;
+                rect.top += dy;
+            }
+            Element::Polyline(polyline) => polyline.points.iter_mut().for_each(|p| offset(p, dx, dy)),
+            Element::Triangle(triangle) => {
+                offset(&mut triangle.vertex1, dx, dy);
+                offset(&mut triangle.vertex2, dx, dy);
+                offset(&mut triangle.vertex3, dx, dy);
+            }
+            Element::Polygon(polygon) => polygon.points.iter_mut().for_each(|p| offset(p, dx, dy)),
+            Element::CubicBezier(curve) => {
+                offset(&mut curve.start, dx, dy);
+                offset(&mut curve.control1, dx, dy);
+                offset(&mut curve.control2, dx, dy);
+                offset(&mut curve.end, dx, dy);
+            }
+            Element::Path(path) => {
+                path.x += dx;
+                path.y += dy;
+            }
+            Element::LineChart(chart) => {
+                chart.x += dx;
+                chart.y += dy;
+            }
+            Element::BarChart(chart) => {
+                chart.x += dx;
+                chart.y += dy;
+            }
+            Element::Sparkline(chart) => {
+                chart.x += dx;
+                chart.y += dy;
+            }
+            Element::Gauge(gauge) => offset(&mut gauge.top_left, dx, dy),
+            Element::ProgressBar(bar) => {
+                bar.left += dx;
+                bar.top += dy;
+            }
+            Element::Meter(meter) => offset(&mut meter.top_left, dx, dy),
+            Element::QrCode(qr) => {
+                qr.x += dx;
+                qr.y += dy;
+            }
+            // 子元素的坐标相对于组的原点
+            Element::Group(group) => {
+                group.x += dx;
+                group.y += dy;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct Text {
+    pub x: i32,
+    pub y: i32,
+    pub text: String,
+    pub size: f32,
+    pub color: CSSColor,
+    /// 水平对齐 left/center/right
+    #[serde(default)]
+    pub align: TextAlign,
+    /// 垂直对齐 top/middle/bottom
+    #[serde(default)]
+    pub valign: TextVAlign,
+    /// 最大宽度，超过时自动换行
+    pub max_width: Option<u32>,
+    /// 换行方式 none/word/char，默认 word
+    pub wrap: Option<TextWrap>,
+    /// 行高(像素)，默认为字体高度
+    pub line_height: Option<f32>,
+    /// 最大行数
+    pub max_lines: Option<u32>,
+    /// 文字超出 max_width 或 max_lines 时显示省略号
+    #[serde(default)]
+    pub ellipsis: bool,
+    /// 上传的字体key，缺字时使用内置字体
+    pub font: Option<String>,
+    /// 描边颜色
+    pub stroke_color: Option<CSSColor>,
+    /// 描边宽度(像素)，默认 1
+    pub stroke_width: Option<u32>,
+    /// 阴影
+    pub shadow: Option<TextShadow>,
+    /// 背景色，大小为文字的外接矩形加上 padding
+    pub background: Option<CSSColor>,
+    /// 背景的内边距(像素)，默认 2
+    pub padding: Option<u32>,
+    /// 旋转角度(度)，正数为顺时针
+    pub rotation: Option<f32>,
+    /// 旋转中心，默认为 (x, y)
+    pub anchor: Option<(i32, i32)>,
+    /// 竖排：从上到下、从右到左，只按 `\n` 分列
+    #[serde(default)]
+    pub vertical: bool,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Line {
+    pub start: (i32, i32),
+    pub end: (i32, i32),
+    pub stroke_width: u32,
+    pub color: CSSColor,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Rectangle {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    pub stroke_width: u32,
+    /// 填充颜色或渐变，也可以写作 fill
+    #[serde(alias = "fill")]
+    pub fill_color: Option<Fill>,
+    pub stroke_color: Option<CSSColor>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct RoundedRectangle {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    pub stroke_width: u32,
+    /// 填充颜色或渐变，也可以写作 fill
+    #[serde(alias = "fill")]
+    pub fill_color: Option<Fill>,
+    pub stroke_color: Option<CSSColor>,
+    pub top_left_corner: (u32, u32),
+    pub top_right_corner: (u32, u32),
+    pub bottom_right_corner: (u32, u32),
+    pub bottom_left_corner: (u32, u32),
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Circle {
+    pub top_left: (i32, i32),
+    pub diameter: u32,
+    pub stroke_width: u32,
+    /// 填充颜色或渐变，也可以写作 fill
+    #[serde(alias = "fill")]
+    pub fill_color: Option<Fill>,
+    pub stroke_color: Option<CSSColor>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Arc {
+    pub top_left: (i32, i32),
+    pub diameter: u32,
+    pub stroke_width: u32,
+    pub angle_start: f32,
+    pub angle_sweep: f32,
+    pub color: CSSColor,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Sector {
+    pub top_left: (i32, i32),
+    pub diameter: u32,
+    pub stroke_width: u32,
+    pub angle_start: f32,
+    pub angle_sweep: f32,
+    /// 填充颜色或渐变，也可以写作 fill
+    #[serde(alias = "fill")]
+    pub fill_color: Option<Fill>,
+    pub stroke_color: Option<CSSColor>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Polyline {
+    pub points: Vec<(i32, i32)>,
+    pub stroke_width: u32,
+    pub color: CSSColor,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Image {
+    pub x: i32,
+    pub y: i32,
+    pub key: Option<String>,
+    pub base64: Option<Box<String>>,
+    /// 显示区域的宽度，只设置宽度或高度时按图片比例计算另一边
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// 图片在显示区域中的缩放方式，默认 fill
+    #[serde(default)]
+    pub fit: ImageFit,
+    /// 只显示图片的一部分 (x, y, 宽, 高)，设置了 frame 时相对于帧
+    #[serde(alias = "src_rect")]
+    pub crop: Option<(u32, u32, u32, u32)>,
+    /// 精灵图的帧（网格序号或帧名称）
+    pub frame: Option<SpriteFrame>,
+    /// 缩放质量，默认 nearest
+    #[serde(default)]
+    pub quality: ImageQuality,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Ellipse {
+    pub top_left: (i32, i32),
+    pub size: (u32, u32),
+    pub stroke_width: u32,
+    /// 填充颜色或渐变，也可以写作 fill
+    #[serde(alias = "fill")]
+    pub fill_color: Option<Fill>,
+    pub stroke_color: Option<CSSColor>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Triangle {
+    pub vertex1: (i32, i32),
+    pub vertex2: (i32, i32),
+    pub vertex3: (i32, i32),
+    pub stroke_width: u32,
+    /// 填充颜色或渐变，也可以写作 fill
+    #[serde(alias = "fill")]
+    pub fill_color: Option<Fill>,
+    pub stroke_color: Option<CSSColor>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct Polygon {
+    /// 顶点，首尾自动相连
+    pub points: Vec<(i32, i32)>,
+    pub stroke_width: u32,
+    /// 填充颜色或渐变，也可以写作 fill
+    #[serde(alias = "fill")]
+    pub fill_color: Option<Fill>,
+    pub stroke_color: Option<CSSColor>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+/// 三次贝塞尔曲线
+#[derive(Clone, Deserialize)]
+pub struct CubicBezier {
+    pub start: (i32, i32),
+    pub control1: (i32, i32),
+    pub control2: (i32, i32),
+    pub end: (i32, i32),
+    pub stroke_width: u32,
+    pub color: CSSColor,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+/// SVG 路径，支持 M/L/H/V/C/Q/A/Z 命令
+#[derive(Clone, Deserialize)]
+pub struct Path {
+    /// 路径坐标原点在画布上的位置
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    /// 路径数据，与 SVG 的 d 属性相同，如 "M0 0L10 0L5 8Z"
+    pub d: String,
+    /// 路径坐标的缩放比例，默认 1
+    pub scale: Option<f32>,
+    #[serde(default)]
+    pub stroke_width: u32,
+    /// 填充颜色或渐变，也可以写作 fill，多个子路径按奇偶规则填充
+    #[serde(alias = "fill")]
+    pub fill_color: Option<Fill>,
+    pub stroke_color: Option<CSSColor>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+    /// 抗锯齿
+    #[serde(default)]
+    pub antialias: bool,
+}
+
+impl Path {
+    /// 解析为画布坐标的子路径
+    pub fn sub_paths(&self) -> Result<Vec<SubPath>> {
+        parse_path(&self.d, (self.x, self.y), self.scale.unwrap_or(1.0))
+    }
+}
+
+/// 将元素列表绘制到画布上
+/// 画布由调用者创建，绘制完成后再由调用者输出到屏幕（或其他目标）
+pub fn render_elements(
+    canvas: &mut RgbImage,
+    font: &FontRef,
+    font_cache: &HashMap<String, FontVec>,
+    image_cache: &HashMap<String, ImageCache>,
+    elements: &[Element],
+) -> Result<()> {
+    for element in elements {
+        render_with_opacity(canvas, font, font_cache, image_cache, element, element.opacity())?;
+    }
+    Ok(())
+}
+
+/// 按不透明度绘制元素：不透明时直接绘制，否则先保存元素覆盖的区域，绘制完成后与原来的像素混合
+fn render_with_opacity(
+    canvas: &mut RgbImage,
+    font: &FontRef,
+    font_cache: &HashMap<String, FontVec>,
+    image_cache: &HashMap<String, ImageCache>,
+    element: &Element,
+    opacity: f32,
+) -> Result<()> {
+    if opacity >= 1.0 {
+        return render_element(canvas, font, font_cache, image_cache, element);
+    }
+    if opacity <= 0.0 {
+        return Ok(());
+    }
+    let screen = Bounds::new(0, 0, canvas.width(), canvas.height());
+    let area = element_bounds(element, font, font_cache, image_cache).unwrap_or(screen).intersection(&screen);
+    if area.is_empty() {
+        return Ok(());
+    }
+    let (left, top) = (area.left as u32, area.top as u32);
+    let saved = imageops::crop_imm(canvas, left, top, area.width(), area.height()).to_image();
+    render_element(canvas, font, font_cache, image_cache, element)?;
+    for (x, y, old) in saved.enumerate_pixels() {
+        let pixel = canvas.get_pixel_mut(left + x, top + y);
+        *pixel = weighted_sum(*old, *pixel, 1.0 - opacity, opacity);
+    }
+    Ok(())
+}
+
+fn render_element(
+    canvas: &mut RgbImage,
+    font: &FontRef,
+    font_cache: &HashMap<String, FontVec>,
+    image_cache: &HashMap<String, ImageCache>,
+    element: &Element,
+) -> Result<()> {
+    match element {
+        Element::Text(text) => {
+            let font = TextFont::find(font, font_cache, text.font.as_deref())?;
+            let lines = layout_text(&font, text);
+            if text.has_effects() {
+                draw_text_effects(canvas, &font, text, &lines);
+                return Ok(());
+            }
+            if text.rotation.is_some() {
+                draw_rotated_text(canvas, &font, text, &lines);
+                return Ok(());
+            }
+            for line in lines {
+                draw_text(
+                    canvas,
+                    line.x,
+                    line.y,
+                    &font,
+                    text.size,
+                    &line.text,
+                    Rgba(text.color.rgba()),
+                )?;
+            }
+        }
+        Element::RichText(text) => {
+            render_elements(canvas, font, font_cache, image_cache, &text.elements(font, font_cache)?)?;
+        }
+        // Element::TextWithFont((text, font)) => {
+        //     draw_text_mut(canvas.as_mut(), Rgba(text.color.to_rgba8()), text.x, text.y, text.size, font, &text.text);
+        // }
+        Element::RawRgbImage((x, y, image)) => {
+            draw_rgb_image(canvas, image.as_ref(), *x as i64, *y as i64)?;
+        }
+        Element::RawImage((x, y, image)) => {
+            draw_image(canvas, image.as_ref(), *x as i64, *y as i64)?;
+        }
+        Element::Image(image) if image.needs_copy() => {
+            let element = fit_image(image, image_cache, Bounds::new(0, 0, canvas.width(), canvas.height()))?;
+            render_element(canvas, font, font_cache, image_cache, &element)?;
+        }
+        Element::Image(image) => {
+            if let Some(key) = &image.key {
+                let img = image_cache.get(key).ok_or(anyhow!("image key not exist:{key}"))?;
+                // 精灵图的帧和裁剪区域直接从缓存的图片中绘制，不复制
+                let rect = image.source_rect(img)?;
+                return draw_cached_image(canvas, img, rect, image.x as i64, image.y as i64);
+            }
+
+            if let Some(b64) = &image.base64 {
+                return match decode_base64_image(image.x, image.y, b64)? {
+                    Element::RawRgbImage((x, y, img)) => draw_rgb_image(canvas, img.as_ref(), x as i64, y as i64),
+                    Element::RawImage((x, y, img)) => draw_image(canvas, img.as_ref(), x as i64, y as i64),
+                    _ => unreachable!(),
+                };
+            }
+            return Err(anyhow!("请填写图像的\"key\"或者\"base64\"字符串"));
+        }
+        Element::Line(line) if line.antialias => {
+            let points = [(line.start.0 as f32, line.start.1 as f32), (line.end.0 as f32, line.end.1 as f32)];
+            draw_path(canvas, &[SubPath::new(points.to_vec(), false)], false, true, line.stroke_width, Some(&line.color), None)?;
+        }
+        Element::Line(line) => {
+            let shape = embedded_graphics::primitives::Line::new(
+                Point::new(line.start.0, line.start.1),
+                Point::new(line.end.0, line.end.1),
+            );
+            draw_stroke(canvas, shape, line.stroke_width, &line.color);
+        }
+        Element::Triangle(triangle) if triangle.antialias => {
+            let points = [triangle.vertex1, triangle.vertex2, triangle.vertex3].map(|(x, y)| (x as f32, y as f32));
+            let paths = [SubPath::new(points.to_vec(), true)];
+            draw_path(canvas, &paths, false, true, triangle.stroke_width, triangle.stroke_color.as_ref(), triangle.fill_color.as_ref())?;
+        }
+        Element::Triangle(triangle) => {
+            let shape = embedded_graphics::primitives::Triangle::new(
+                Point::new(triangle.vertex1.0, triangle.vertex1.1),
+                Point::new(triangle.vertex2.0, triangle.vertex2.1),
+                Point::new(triangle.vertex3.0, triangle.vertex3.1),
+            );
+            draw_shape(canvas, shape, triangle.stroke_width, triangle.stroke_color.as_ref(), triangle.fill_color.as_ref())?;
+        }
+        Element::Circle(circle) if circle.antialias => {
+            let paths = [SubPath::new(ellipse_outline(circle.top_left, (circle.diameter, circle.diameter)), true)];
+            draw_path(canvas, &paths, true, true, circle.stroke_width, circle.stroke_color.as_ref(), circle.fill_color.as_ref())?;
+        }
+        Element::Circle(circle) => {
+            let shape = embedded_graphics::primitives::Circle::new(
+                Point::new(circle.top_left.0, circle.top_left.1),
+                circle.diameter,
+            );
+            draw_shape(canvas, shape, circle.stroke_width, circle.stroke_color.as_ref(), circle.fill_color.as_ref())?;
+        }
+        Element::Ellipse(ellipse) if ellipse.antialias => {
+            let paths = [SubPath::new(ellipse_outline(ellipse.top_left, ellipse.size), true)];
+            draw_path(canvas, &paths, true, true, ellipse.stroke_width, ellipse.stroke_color.as_ref(), ellipse.fill_color.as_ref())?;
+        }
+        Element::Ellipse(ellipse) => {
+            let shape = embedded_graphics::primitives::Ellipse::new(
+                Point::new(ellipse.top_left.0, ellipse.top_left.1),
+                Size::new(ellipse.size.0, ellipse.size.1),
+            );
+            draw_shape(canvas, shape, ellipse.stroke_width, ellipse.stroke_color.as_ref(), ellipse.fill_color.as_ref())?;
+        }
+        Element::RoundedRectangle(rect) => {
+            let corner = embedded_graphics::primitives::CornerRadii {
+                top_left: Size::new(rect.top_left_corner.0, rect.top_left_corner.1),
+                top_right: Size::new(rect.top_right_corner.0, rect.top_right_corner.1),
+                bottom_right: Size::new(rect.bottom_right_corner.0, rect.bottom_right_corner.1),
+                bottom_left: Size::new(rect.bottom_left_corner.0, rect.bottom_left_corner.1),
+            };
+            let shape = embedded_graphics::primitives::RoundedRectangle::new(
+                embedded_graphics::primitives::Rectangle::new(
+                    Point::new(rect.left, rect.top),
+                    Size::new(rect.width, rect.height),
+                ),
+                corner,
+            );
+            draw_shape(canvas, shape, rect.stroke_width, rect.stroke_color.as_ref(), rect.fill_color.as_ref())?;
+        }
+        Element::Rectangle(rect) => {
+            let shape = embedded_graphics::primitives::Rectangle::new(
+                Point::new(rect.left, rect.top),
+                Size::new(rect.width, rect.height),
+            );
+            draw_shape(canvas, shape, rect.stroke_width, rect.stroke_color.as_ref(), rect.fill_color.as_ref())?;
+        }
+        Element::Arc(arc) if arc.antialias => {
+            let radius = (arc.diameter.max(1) - 1) as f32 / 2.0;
+            let center = (arc.top_left.0 as f32 + radius, arc.top_left.1 as f32 + radius);
+            let paths = [SubPath::new(ellipse_points(center, (radius, radius), arc.angle_start, arc.angle_sweep), false)];
+            draw_path(canvas, &paths, true, true, arc.stroke_width, Some(&arc.color), None)?;
+        }
+        Element::Arc(arc) => {
+            let shape = embedded_graphics::primitives::Arc::new(
+                Point::new(arc.top_left.0, arc.top_left.1),
+                arc.diameter,
+                arc.angle_start.deg(),
+                arc.angle_sweep.deg(),
+            );
+            draw_stroke(canvas, shape, arc.stroke_width, &arc.color);
+        }
+        Element::Sector(sector) => {
+            let shape = embedded_graphics::primitives::Sector::new(
+                Point::new(sector.top_left.0, sector.top_left.1),
+                sector.diameter,
+                sector.angle_start.deg(),
+                sector.angle_sweep.deg(),
+            );
+            draw_shape(canvas, shape, sector.stroke_width, sector.stroke_color.as_ref(), sector.fill_color.as_ref())?;
+        }
+        Element::Polyline(polyline) if polyline.antialias => {
+            let points: Vec<(f32, f32)> = polyline.points.iter().map(|(x, y)| (*x as f32, *y as f32)).collect();
+            draw_path(canvas, &[SubPath::new(points, false)], false, true, polyline.stroke_width, Some(&polyline.color), None)?;
+        }
+        Element::Polyline(polyline) => {
+            let points: Vec<Point> = polyline.points.iter().map(|(x, y)| Point::new(*x, *y)).collect();
+            let shape = embedded_graphics::primitives::Polyline::new(&points);
+            draw_stroke(canvas, shape, polyline.stroke_width, &polyline.color);
+        }
+        Element::Polygon(polygon) => {
+            let points = polygon.points.iter().map(|(x, y)| (*x as f32, *y as f32)).collect();
+            let paths = [SubPath::new(points, true)];
+            draw_path(canvas, &paths, false, polygon.antialias, polygon.stroke_width, polygon.stroke_color.as_ref(), polygon.fill_color.as_ref())?;
+        }
+        Element::CubicBezier(curve) => {
+            let [start, control1, control2, end] = [curve.start, curve.control1, curve.control2, curve.end].map(|(x, y)| (x as f32, y as f32));
+            let paths = [SubPath::new(cubic_bezier_curve_points(start, end, control1, control2), false)];
+            draw_path(canvas, &paths, true, curve.antialias, curve.stroke_width, Some(&curve.color), None)?;
+        }
+        Element::Path(path) => {
+            let paths = path.sub_paths()?;
+            // 曲线和直线混合，按折线描边
+            draw_path(canvas, &paths, false, path.antialias, path.stroke_width, path.stroke_color.as_ref(), path.fill_color.as_ref())?;
+        }
+        Element::LineChart(chart) => render_elements(canvas, font, font_cache, image_cache, &chart.elements())?,
+        Element::BarChart(chart) => render_elements(canvas, font, font_cache, image_cache, &chart.elements())?,
+        Element::Sparkline(chart) => render_elements(canvas, font, font_cache, image_cache, &chart.elements())?,
+        Element::Gauge(gauge) => render_elements(canvas, font, font_cache, image_cache, &gauge.elements())?,
+        Element::ProgressBar(bar) => render_elements(canvas, font, font_cache, image_cache, &bar.elements())?,
+        Element::Meter(meter) => render_elements(canvas, font, font_cache, image_cache, &meter.elements())?,
+        Element::QrCode(qr) => qr.draw(canvas)?,
+        Element::Group(group) => group.draw(canvas, font, font_cache, image_cache)?,
+    }
+    Ok(())
+}
+
+/// 图形像素的颜色：纯色或渐变
+#[derive(Clone, Copy)]
+pub(crate) enum Paint<'a> {
+    Color([u8; 4]),
+    Gradient(&'a GradientPainter),
+}
+
+impl Paint<'_> {
+    pub(crate) fn color(&self, x: i32, y: i32) -> [u8; 4] {
+        match self {
+            Paint::Color(color) => *color,
+            Paint::Gradient(gradient) => gradient.color(x, y),
+        }
+    }
+}
+
+/// 图形按这两个标记色绘制，再由 CanvasTarget 换成描边/填充的颜色
+const STROKE_MARK: Rgb888 = Rgb888::WHITE;
+const FILL_MARK: Rgb888 = Rgb888::BLACK;
+
+/// embedded-graphics 的绘制目标，超出画布的像素忽略
+/// 描边和填充的像素按各自的颜色（或渐变）取色，再按颜色的透明度与画布混合
+struct CanvasTarget<'a> {
+    canvas: &'a mut RgbImage,
+    stroke: Option<Paint<'a>>,
+    fill: Option<Paint<'a>>,
+}
+
+impl OriginDimensions for CanvasTarget<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.canvas.width(), self.canvas.height())
+    }
+}
+
+impl DrawTarget for CanvasTarget<'_> {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> std::result::Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics::Pixel<Self::Color>>,
+    {
+        for embedded_graphics::Pixel(pt, c) in pixels {
+            let paint = if c == STROKE_MARK { self.stroke } else { self.fill };
+            if let Some(paint) = paint {
+                if (0..self.canvas.width() as i32).contains(&pt.x) && (0..self.canvas.height() as i32).contains(&pt.y) {
+                    blend_pixel(self.canvas.get_pixel_mut(pt.x as u32, pt.y as u32), paint.color(pt.x, pt.y));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 按颜色的透明度混合到像素上
+pub(crate) fn blend_pixel(pixel: &mut Rgb<u8>, [r, g, b, a]: [u8; 4]) {
+    match a {
+        0 => (),
+        255 => *pixel = Rgb([r, g, b]),
+        a => {
+            let alpha = a as f32 / 255.0;
+            *pixel = weighted_sum(*pixel, Rgb([r, g, b]), 1.0 - alpha, alpha);
+        }
+    }
+}
+
+/// 按覆盖率 0~1 和颜色的透明度混合到像素上
+pub(crate) fn blend_coverage(pixel: &mut Rgb<u8>, color: Rgba<u8>, coverage: f32) {
+    let gv = coverage * color[3] as f32 / 255.0;
+    if gv > 0.0 {
+        *pixel = weighted_sum(pixel.to_rgba(), color, 1.0 - gv, gv).to_rgb();
+    }
+}
+
+/// 绘制线条类图形（Line/Arc/Polyline）
+fn draw_stroke<S>(canvas: &mut RgbImage, shape: S, stroke_width: u32, color: &CSSColor)
+where
+    S: Primitive + StyledDrawable<PrimitiveStyle<Rgb888>, Color = Rgb888>,
+{
+    let style = PrimitiveStyle::with_stroke(STROKE_MARK, stroke_width);
+    let stroke = Some(Paint::Color(color.rgba()));
+    let _ = shape.into_styled(style).draw(&mut CanvasTarget { canvas, stroke, fill: None });
+}
+
+/// 绘制封闭图形，描边和填充一起绘制，半透明时重叠的部分不会混合两次
+fn draw_shape<S>(canvas: &mut RgbImage, shape: S, stroke_width: u32, stroke_color: Option<&CSSColor>, fill: Option<&Fill>) -> Result<()>
+where
+    S: Primitive + StyledDrawable<PrimitiveStyle<Rgb888>, Color = Rgb888>,
+{
+    let painter = match fill {
+        Some(Fill::Gradient(gradient)) => {
+            let bounds = shape.bounding_box();
+            Some(GradientPainter::new(
+                gradient,
+                (bounds.top_left.x, bounds.top_left.y, bounds.size.width, bounds.size.height),
+            )?)
+        }
+        _ => None,
+    };
+    let fill = match (fill, painter.as_ref()) {
+        (Some(Fill::Color(color)), _) => Some(Paint::Color(color.rgba())),
+        (_, Some(painter)) => Some(Paint::Gradient(painter)),
+        _ => None,
+    };
+    let stroke = stroke_color.map(|color| Paint::Color(color.rgba()));
+
+    let mut builder = PrimitiveStyleBuilder::new().stroke_width(stroke_width);
+    if stroke.is_some() {
+        builder = builder.stroke_color(STROKE_MARK);
+    }
+    if fill.is_some() {
+        builder = builder.fill_color(FILL_MARK);
+    }
+    let _ = shape.into_styled(builder.build()).draw(&mut CanvasTarget { canvas, stroke, fill });
+    Ok(())
+}
+
+/// 左上角为 top_left、大小为 size 的椭圆（圆）的轮廓
+fn ellipse_outline(top_left: (i32, i32), (width, height): (u32, u32)) -> Vec<(f32, f32)> {
+    let (rx, ry) = ((width.max(1) - 1) as f32 / 2.0, (height.max(1) - 1) as f32 / 2.0);
+    let mut points = ellipse_points((top_left.0 as f32 + rx, top_left.1 as f32 + ry), (rx, ry), 0.0, 360.0);
+    // 首尾重合
+    points.pop();
+    points
+}
+
+/// 按轮廓绘制图形（抗锯齿的基本图形、多边形、贝塞尔曲线、路径）：先填充再描边，填充到轮廓为止，描边以轮廓为中心
+/// 闭合的子路径才填充；curve 为 true 时按平滑曲线描边（圆、椭圆、圆弧、贝塞尔曲线），否则按折线描边
+fn draw_path(
+    canvas: &mut RgbImage,
+    paths: &[SubPath],
+    curve: bool,
+    antialias: bool,
+    stroke_width: u32,
+    stroke_color: Option<&CSSColor>,
+    fill: Option<&Fill>,
+) -> Result<()> {
+    let Some((left, top, right, bottom)) = paths_bounds(paths) else {
+        return Ok(());
+    };
+    let corners = [(left, top), (right, bottom)];
+    let closed: Vec<SubPath> = paths.iter().filter(|path| path.closed).cloned().collect();
+    if let Some(fill) = fill.filter(|_| !closed.is_empty()) {
+        let mut mask = CoverageMask::new(canvas, &corners, 0.0, antialias);
+        mask.fill_paths(&closed);
+        match fill {
+            Fill::Color(color) => mask.blend(canvas, Paint::Color(color.rgba())),
+            Fill::Gradient(gradient) => {
+                let bounds = (left.round() as i32, top.round() as i32, (right - left).round() as u32 + 1, (bottom - top).round() as u32 + 1);
+                mask.blend(canvas, Paint::Gradient(&GradientPainter::new(gradient, bounds)?));
+            }
+        }
+    }
+    if let Some(color) = stroke_color.filter(|_| stroke_width > 0) {
+        let mut mask = CoverageMask::new(canvas, &corners, stroke_width as f32 / 2.0, antialias);
+        for path in paths {
+            if curve {
+                mask.stroke_curve(&path.points, stroke_width as f32, path.closed);
+            } else {
+                mask.stroke_polyline(&path.points, stroke_width as f32, path.closed);
+            }
+        }
+        mask.blend(canvas, Paint::Color(color.rgba()));
+    }
+    Ok(())
+}
+
+/// 解码 base64 图像，jpg 解码为 RawRgbImage，其他格式解码为 RawImage
+pub(crate) fn decode_base64_image(x: i32, y: i32, b64: &str) -> Result<Element> {
+    match decode_image(&decode_base64(b64)?)? {
+        ImageCache::RgbImage(img) => Ok(Element::RawRgbImage((x, y, img))),
+        ImageCache::RgbaImage(img) => Ok(Element::RawImage((x, y, img))),
+        img => Ok(Element::RawImage((x, y, Box::new(img.to_rgba_image())))),
+    }
+}
+
+/// 解码上传的图片文件，JPG 解码为 RGB，其他格式解码为 RGBA
+pub fn decode_image(data: &[u8]) -> Result<ImageCache> {
+    let mime = mimetype::detect(data);
+    if mime.extension.ends_with("jpg") || mime.extension.ends_with("jpeg") {
+        let img = decode_jpg_to_rgb(Box::new(data.to_vec()))
+            .map_err(|err| anyhow!("decode jpg:{err:?}"))?;
+        Ok(ImageCache::RgbImage(img))
+    } else {
+        let img = image::load_from_memory(data)?.to_rgba8();
+        Ok(ImageCache::RgbaImage(Box::new(img)))
+    }
+}
+
+/// 解码上传的图片文件为 RGB565，用于图片缓存
+/// JPG 直接解码为 RGB565，其他格式根据透明度保存 1 位或 8 位透明通道（完全不透明时不保存）
+pub fn decode_image_rgb565(data: &[u8]) -> Result<ImageCache> {
+    let mime = mimetype::detect(data);
+    let img = if mime.extension.ends_with("jpg") || mime.extension.ends_with("jpeg") {
+        let (w, h, pixels) = decode_jpeg_to_rgb565(data)?;
+        Rgb565Image::new(w as u32, h as u32, *pixels, None)?
+    } else {
+        Rgb565Image::from_rgba(&image::load_from_memory(data)?.to_rgba8())
+    };
+    Ok(ImageCache::Rgb565(Box::new(img)))
+}
+
+/// 分条渲染时每个条带缓冲区的默认大小(字节)
+pub const DEFAULT_BAND_BYTES: u32 = 32 * 1024;
+
+/// 条带高度，band_height 为 0 时按 DEFAULT_BAND_BYTES 根据屏幕宽度计算
+pub fn band_height(width: u32, band_height: u32) -> u32 {
+    if band_height > 0 {
+        band_height
+    } else {
+        (DEFAULT_BAND_BYTES / (width.max(1) * 3)).max(1)
+    }
+}
+
+/// 分条渲染前的预处理：base64图像只解码一次，不在每个条带中重复解码
+/// screen 为屏幕区域，缩放后的图像只保留屏幕上可见的部分
+fn prepare_element(element: &Element, image_cache: &HashMap<String, ImageCache>, screen: Bounds) -> Result<Element> {
+    match element {
+        // 需要缩放的图像也只处理一次
+        Element::Image(image) if image.needs_copy() => fit_image(image, image_cache, screen),
+        Element::Image(Image { x, y, key: None, base64: Some(b64), .. }) => decode_base64_image(*x, *y, b64),
+        // 组的子元素只缩放一次，展开为不缩放的组，缩放后的图像只保留裁剪区域中的部分
+        Element::Group(group) => {
+            let visible = group.clip_bounds().map_or(screen, |clip| clip.intersection(&screen));
+            let children = group
+                .elements(image_cache, screen)?
+                .iter()
+                .map(|child| {
+                    let prepared = prepare_element(child, image_cache, visible)?;
+                    let opacity = child.opacity();
+                    if prepared.opacity() == opacity {
+                        return Ok(prepared);
+                    }
+                    // RawImage 没有不透明度，放到一个组中
+                    let group = Group { x: 0, y: 0, clip: None, scale: None, children: vec![prepared], opacity: Some(opacity) };
+                    Ok(Element::Group(group))
+                })
+                .collect::<Result<Vec<Element>>>()?;
+            let clip = group.clip_bounds().map(|clip| (clip.left, clip.top, clip.width(), clip.height()));
+            Ok(Element::Group(Group { x: 0, y: 0, clip, scale: None, children, opacity: group.opacity }))
+        }
+        element => Ok(element.clone()),
+    }
+}
+
+/// 分条渲染屏幕上的 area 区域（元素为屏幕坐标）：每次只渲染 band_height 行，渲染完一条后调用 draw(x, y, 条带图像) 输出到屏幕
+/// 条带缓冲区为 area 宽 * band_height * 3 字节，缩放后的图像和 base64 图像在渲染前处理一次，其中缩放后的图像只保留 area 中的部分
+pub fn render_elements_banded(
+    area: Bounds,
+    band_height: u32,
+    font: &FontRef,
+    font_cache: &HashMap<String, FontVec>,
+    image_cache: &HashMap<String, ImageCache>,
+    elements: &[Element],
+    mut draw: impl FnMut(u16, u16, &RgbImage) -> Result<()>,
+) -> Result<()> {
+    if area.is_empty() {
+        return Ok(());
+    }
+    let (width, height) = (area.width(), area.height());
+    let band_height = band_height.clamp(1, height);
+    // 预处理后的 RawImage 没有不透明度，先记下来
+    let opacity: Vec<f32> = elements.iter().map(Element::opacity).collect();
+    let mut elements = elements
+        .iter()
+        .map(|element| {
+            let mut element = prepare_element(element, image_cache, area)?;
+            // 移到区域的坐标系
+            element.translate(-area.left, -area.top);
+            Ok(element)
+        })
+        .collect::<Result<Vec<Element>>>()?;
+    let bounds: Vec<Option<Bounds>> = elements
+        .iter()
+        .map(|element| element_bounds(element, font, font_cache, image_cache))
+        .collect();
+
+    let mut canvas = RgbImage::new(width, band_height);
+    let mut y = 0;
+    while y < height {
+        let h = band_height.min(height - y);
+        if canvas.height() != h {
+            canvas = RgbImage::new(width, h);
+        } else {
+            canvas.fill(0);
+        }
+        let band = Bounds::new(0, y as i32, width, h);
+        for ((element, bounds), opacity) in elements.iter().zip(&bounds).zip(&opacity) {
+            if bounds.map_or(true, |b| b.intersects(&band)) {
+                render_with_opacity(&mut canvas, font, font_cache, image_cache, element, *opacity)?;
+            }
+        }
+        draw(area.left as u16, (area.top + y as i32) as u16, &canvas)?;
+        // 元素上移一个条带，下一个条带从画布顶部开始绘制
+        for element in elements.iter_mut() {
+            element.translate(0, -(h as i32));
+        }
+        y += h;
+    }
+    Ok(())
+}
+
+pub fn decode_jpg_to_rgb(jpg_data: Box<Vec<u8>>) -> Result<Box<RgbImage>> {
+    let (w, h, pixels) = decode_jpeg_to_rgb565(&jpg_data)?;
+    
+    let mut rgb = Vec::with_capacity(w as usize * h as usize * 3);
+    for pixel in pixels.iter() {
+        let (r, g, b) = rgb565_to_rgb888(pixel.to_be());
+        rgb.extend_from_slice(&[r, g, b]);
+    }
+    
+    let img = RgbImage::from_raw(w as u32, h as u32, rgb).unwrap();
+    Ok(Box::new(img))
+}
+
+/// RGB888 转 RGB565
+#[inline]
+fn rgb888_to_rgb565(r: u8, g: u8, b: u8) -> u16 {
+    let r5 = (r >> 3) as u16;
+    let g6 = (g >> 2) as u16;
+    let b5 = (b >> 3) as u16;
+    (r5 << 11) | (g6 << 5) | b5
+}
+
+/// 使用 tjpg_decoder 解码 JPEG 为 RGB565 格式
+/// 使用内存池版本，与 C 版本 tjpgd 一致，避免栈溢出
+pub fn decode_jpeg_to_rgb565(jpeg_data: &[u8]) -> Result<(u16, u16, Box<Vec<u16>>)> {
+    // 分配内存池（与 C 版本一致）
+    let mut pool_buffer = vec![0u8; RECOMMENDED_POOL_SIZE];
+    let mut pool = MemoryPool::new(&mut pool_buffer);
+    
+    // 创建解码器
+    let mut decoder = JpegDecoder::new();
+    
+    decoder.prepare(jpeg_data, &mut pool).map_err(|e| {
+        anyhow!("JPEG prepare failed: {:?}", e)
+    })?;
+    
+    let width = decoder.width();
+    let height = decoder.height();
+    
+    // 分配工作缓冲区（在堆上）
+    let mut mcu_buffer = vec![0i16; decoder.mcu_buffer_size()];
+    let mut work_buffer = vec![0u8; decoder.work_buffer_size()];
+    
+    // 分配输出缓冲区
+    let output_size = width as usize * height as usize;
+    let mut output = vec![0u16; output_size];
+    let fb_width = width as usize;
+    
+    decoder.decompress(
+        jpeg_data, 
+        0, 
+        &mut mcu_buffer,
+        &mut work_buffer,
+        &mut |_decoder, bitmap, rect| {
+        // bitmap 是 RGB888 格式，每像素 3 字节
+        let rect_width = (rect.right - rect.left + 1) as usize;
+        let bytes_per_row = rect_width * 3;
+        
+        for y in rect.top..=rect.bottom {
+            let y_offset = (y - rect.top) as usize;
+            let src_offset = y_offset * bytes_per_row;
+            let dst_row = y as usize * fb_width + rect.left as usize;
+            
+            if src_offset + bytes_per_row <= bitmap.len() 
+               && dst_row + rect_width <= output.len() {
+                for x in 0..rect_width {
+                    let byte_idx = src_offset + x * 3;
+                    if byte_idx + 2 < bitmap.len() {
+                        let r = bitmap[byte_idx];
+                        let g = bitmap[byte_idx + 1];
+                        let b = bitmap[byte_idx + 2];
+                        let pixel = rgb888_to_rgb565(r, g, b);
+                        // 输出大端序，与 draw_rgb565_fast 的输入约定一致
+                        output[dst_row + x] = pixel.to_be();
+                    }
+                }
+            }
+        }
+        
+        Ok(true)
+    }).map_err(|e| {
+        anyhow!("JPEG decompress failed: {:?}", e)
+    })?;
+    
+    Ok((width, height, Box::new(output)))
+}
+
+fn draw_text(
+    target: &mut RgbImage,
+    x: i32,
+    y: i32,
+    font: &TextFont,
+    font_size: f32,
+    text: &str,
+    color: Rgba<u8>,
+) -> Result<()> {
+    let image_width = target.width() as i32;
+    let image_height = target.height() as i32;
+    // 文字颜色的透明度
+    let alpha = color[3] as f32 / 255.0;
+
+    layout_glyphs(font_size, font, text, |g, bb| {
+        let x_shift = x + bb.min.x.round() as i32;
+        let y_shift = y + bb.min.y.round() as i32;
+        g.draw(|gx, gy, gv| {
+            let image_x = gx as i32 + x_shift;
+            let image_y = gy as i32 + y_shift;
+
+            if (0..image_width).contains(&image_x) && (0..image_height).contains(&image_y) {
+                let src_pixel = target.get_pixel_mut_checked(image_x as u32, image_y as u32).unwrap();
+                let pixel = src_pixel.to_rgba();
+                let gv = gv.clamp(0.0, 1.0) * alpha;
+                let weighted_color = weighted_sum(pixel, color, 1.0 - gv, gv);
+                *src_pixel = weighted_color.to_rgb();
+            }
+        })
+    });
+    Ok(())
+}
+
+/// Calculate the region that can be copied from top to bottom.
+///
+/// Given image size of bottom and top image, and a point at which we want to place the top image
+/// onto the bottom image, how large can we be? Have to wary of the following issues:
+/// * Top might be larger than bottom
+/// * Overflows in the computation
+/// * Coordinates could be completely out of bounds
+///
+/// The returned value is of the form:
+///
+/// `(origin_bottom_x, origin_bottom_y, origin_top_x, origin_top_y, x_range, y_range)`
+///
+/// The main idea is to do computations on i64's and then clamp to image dimensions.
+/// In particular, we want to ensure that all these coordinate accesses are safe:
+/// 1. `bottom.get_pixel(origin_bottom_x + [0..x_range), origin_bottom_y + [0..y_range))`
+/// 2. `top.get_pixel(origin_top_y + [0..x_range), origin_top_y + [0..y_range))`
+///
+fn overlay_bounds_ext(
+    (bottom_width, bottom_height): (u32, u32),
+    (top_width, top_height): (u32, u32),
+    x: i64,
+    y: i64,
+) -> (u32, u32, u32, u32, u32, u32) {
+    // Return a predictable value if the two images don't overlap at all.
+    if x > i64::from(bottom_width)
+        || y > i64::from(bottom_height)
+        || x.saturating_add(i64::from(top_width)) <= 0
+        || y.saturating_add(i64::from(top_height)) <= 0
+    {
+        return (0, 0, 0, 0, 0, 0);
+    }
+
+    // Find the maximum x and y coordinates in terms of the bottom image.
+    let max_x = x.saturating_add(i64::from(top_width));
+    let max_y = y.saturating_add(i64::from(top_height));
+
+    // Clip the origin and maximum coordinates to the bounds of the bottom image.
+    // Casting to a u32 is safe because both 0 and `bottom_{width,height}` fit
+    // into 32-bits.
+    let max_inbounds_x = max_x.clamp(0, i64::from(bottom_width)) as u32;
+    let max_inbounds_y = max_y.clamp(0, i64::from(bottom_height)) as u32;
+    let origin_bottom_x = x.clamp(0, i64::from(bottom_width)) as u32;
+    let origin_bottom_y = y.clamp(0, i64::from(bottom_height)) as u32;
+
+    // The range is the difference between the maximum inbounds coordinates and
+    // the clipped origin. Unchecked subtraction is safe here because both are
+    // always positive and `max_inbounds_{x,y}` >= `origin_{x,y}` due to
+    // `top_{width,height}` being >= 0.
+    let x_range = max_inbounds_x - origin_bottom_x;
+    let y_range = max_inbounds_y - origin_bottom_y;
+
+    // If x (or y) is negative, then the origin of the top image is shifted by -x (or -y).
+    let origin_top_x = x.saturating_mul(-1).clamp(0, i64::from(top_width)) as u32;
+    let origin_top_y = y.saturating_mul(-1).clamp(0, i64::from(top_height)) as u32;
+
+    (
+        origin_bottom_x,
+        origin_bottom_y,
+        origin_top_x,
+        origin_top_y,
+        x_range,
+        y_range,
+    )
+}
+
+/// Overlay an image at a given coordinate (x, y)
+fn draw_image(bottom: &mut RgbImage, top: &impl GenericImageView<Pixel = Rgba<u8>>, x: i64, y: i64) -> Result<()> {
+    let bottom_dims = (bottom.width(), bottom.height());
+    let top_dims = top.dimensions();
+
+    // Crop our top image if we're going out of bounds
+    let (origin_bottom_x, origin_bottom_y, origin_top_x, origin_top_y, range_width, range_height) =
+        overlay_bounds_ext(bottom_dims, top_dims, x, y);
+
+    for y in 0..range_height {
+        for x in 0..range_width {
+            let p = top.get_pixel(origin_top_x + x, origin_top_y + y);
+            let (o_x, o_y) = (origin_bottom_x + x, origin_bottom_y + y);
+            // let idx = o_y as usize * bottom_dims.0 as usize + o_x as usize;
+            if (0..bottom.width()).contains(&o_x) && (0..bottom.height()).contains(&o_y) {
+                let src_pixel = bottom.get_pixel_mut_checked(o_x, o_y).unwrap();
+                let mut bottom_pixel = src_pixel.to_rgba();
+                image::Pixel::blend(&mut bottom_pixel, &p);
+                *src_pixel = bottom_pixel.to_rgb();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 绘制缓存图片中 (sx, sy, sw, sh) 区域
+fn draw_cached_image(canvas: &mut RgbImage, image: &ImageCache, rect: (u32, u32, u32, u32), x: i64, y: i64) -> Result<()> {
+    let (sx, sy, sw, sh) = rect;
+    match image {
+        ImageCache::RgbImage(img) => draw_rgb_image(canvas, &*imageops::crop_imm(img.as_ref(), sx, sy, sw, sh), x, y),
+        ImageCache::RgbaImage(img) => draw_image(canvas, &*imageops::crop_imm(img.as_ref(), sx, sy, sw, sh), x, y),
+        ImageCache::Rgb565(img) => draw_rgb565_image(canvas, img, rect, x, y),
+        ImageCache::Sheet(img, _) => draw_cached_image(canvas, img, rect, x, y),
+    }
+}
+
+/// 将 RGB565 图片的 (sx, sy, sw, sh) 区域绘制到画布上，按透明通道混合
+fn draw_rgb565_image(bottom: &mut RgbImage, top: &Rgb565Image, (sx, sy, sw, sh): (u32, u32, u32, u32), x: i64, y: i64) -> Result<()> {
+    let (origin_bottom_x, origin_bottom_y, origin_top_x, origin_top_y, range_width, range_height) =
+        overlay_bounds_ext(bottom.dimensions(), (sw, sh), x, y);
+
+    for y in 0..range_height {
+        for x in 0..range_width {
+            let (pixel, alpha) = top.get_pixel(sx + origin_top_x + x, sy + origin_top_y + y);
+            if alpha == 0 {
+                continue;
+            }
+            let (r, g, b) = rgb565_to_rgb888(pixel);
+            let dst = bottom.get_pixel_mut(origin_bottom_x + x, origin_bottom_y + y);
+            if alpha == 255 {
+                *dst = Rgb([r, g, b]);
+            } else {
+                let a = alpha as u16;
+                let mix = |s: u8, d: u8| ((s as u16 * a + d as u16 * (255 - a)) / 255) as u8;
+                *dst = Rgb([mix(r, dst[0]), mix(g, dst[1]), mix(b, dst[2])]);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Overlay an image at a given coordinate (x, y)
+fn draw_rgb_image(bottom: &mut RgbImage, top: &impl GenericImageView<Pixel = Rgb<u8>>, x: i64, y: i64) -> Result<()> {
+    overlay(bottom, top, x, y);
+    Ok(())
+}
@@ -0,0 +1,237 @@
+// RGB565 颜色转换（与平台无关）
+
+use anyhow::{anyhow, Result};
+use image::{RgbImage, Rgba, RgbaImage};
+
+// #[inline]
+// fn rgb888_to_rgb565(r: u8, g: u8, b: u8) -> u16 {
+//     // 缩放颜色分量到目标位数
+//     let r5 = (r as u16 * 31 / 255) << 11; // 5 bits for red, shift left by 11 bits
+//     let g6 = (g as u16 * 63 / 255) << 5;  // 6 bits for green, shift left by 5 bits
+//     let b5 = b as u16 * 31 / 255;         // 5 bits for blue
+
+//     // 组合成16位RGB565值
+//     (r5 | g6 | b5) as u16
+// }
+
+macro_rules! generate_lut {
+    ($name:ident, $factor:expr, $shift:expr) => {
+        const $name: [u16; 256] = {
+            let mut lut = [0u16; 256];
+            let mut i = 0;
+            while i < 256 {
+                lut[i] = ((i as u16 * $factor) / 255) << $shift;
+                i += 1;
+            }
+            lut
+        };
+    };
+}
+
+generate_lut!(RGB565_R_LUT, 31, 11); // 红色：5位，左移11位
+generate_lut!(RGB565_G_LUT, 63, 5); // 绿色：6位，左移5位
+generate_lut!(RGB565_B_LUT, 31, 0); // 蓝色：5位，不移位
+
+#[inline(always)]
+pub fn rgb888_to_rgb565(r: u8, g: u8, b: u8) -> u16 {
+    // 使用查找表获取缩放后的颜色分量
+    let r5 = RGB565_R_LUT[r as usize];
+    let g6 = RGB565_G_LUT[g as usize];
+    let b5 = RGB565_B_LUT[b as usize];
+
+    // 组合成16位RGB565值
+    r5 | g6 | b5
+}
+
+#[inline(always)]
+pub fn rgb565_to_rgb888(pixel: u16) -> (u8, u8, u8) {
+    // 分离颜色分量
+    let r = ((pixel >> 11) & 0x1F) as u8; // 5 bits for red
+    let g = ((pixel >> 5) & 0x3F) as u8; // 6 bits for green
+    let b = (pixel & 0x1F) as u8; // 5 bits for blue
+
+    // 扩展颜色分量到8位
+    let r8 = (r as u16 * 255 / 31) as u8;
+    let g8 = (g as u16 * 255 / 63) as u8;
+    let b8 = (b as u16 * 255 / 31) as u8;
+
+    (r8, g8, b8)
+}
+
+/// 应用色调调整
+/// adjust: -100 到 +100 的调整值
+#[inline(always)]
+pub fn apply_color_adjust(color: u8, adjust: i8) -> u8 {
+    let adjusted = color as i16 + (adjust as i16 * 255 / 100);
+    adjusted.clamp(0, 255) as u8
+}
+
+/// RGB565转RGB888并应用色调调整
+#[inline(always)]
+pub fn rgb565_to_rgb888_adjusted(pixel: u16, adj_r: i8, adj_g: i8, adj_b: i8) -> (u8, u8, u8) {
+    let (r, g, b) = rgb565_to_rgb888(pixel);
+    (
+        apply_color_adjust(r, adj_r),
+        apply_color_adjust(g, adj_g),
+        apply_color_adjust(b, adj_b),
+    )
+}
+
+/// 将画布转换为大端序RGB565像素（可直接写入屏幕缓冲区），同时应用色调调整
+pub fn rgb_image_to_rgb565(image: &RgbImage, adj_r: i8, adj_g: i8, adj_b: i8) -> Vec<u16> {
+    let mut pixels = Vec::with_capacity(image.width() as usize * image.height() as usize);
+    if adj_r == 0 && adj_g == 0 && adj_b == 0 {
+        // 无需调整，直接转换
+        for pixel in image.pixels() {
+            pixels.push(rgb888_to_rgb565(pixel[0], pixel[1], pixel[2]).to_be());
+        }
+    } else {
+        for pixel in image.pixels() {
+            let r = apply_color_adjust(pixel[0], adj_r);
+            let g = apply_color_adjust(pixel[1], adj_g);
+            let b = apply_color_adjust(pixel[2], adj_b);
+            pixels.push(rgb888_to_rgb565(r, g, b).to_be());
+        }
+    }
+    pixels
+}
+
+/// 图片的透明通道
+pub enum Alpha {
+    /// 每像素1位，按行优先顺序打包，高位在前
+    Bit(Vec<u8>),
+    /// 每像素1字节
+    Byte(Vec<u8>),
+}
+
+/// RGB565 格式的图片，像素为大端序 u16（与 draw_rgb565_fast 的输入约定一致，可直接发送到屏幕）
+/// 内存占用为 宽x高x2 字节，加上可选的透明通道
+pub struct Rgb565Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u16>,
+    alpha: Option<Alpha>,
+}
+
+impl Rgb565Image {
+    /// pixels 为大端序 u16
+    pub fn new(width: u32, height: u32, pixels: Vec<u16>, alpha: Option<Alpha>) -> Result<Self> {
+        let len = width as usize * height as usize;
+        let alpha_ok = match alpha.as_ref() {
+            None => true,
+            Some(Alpha::Bit(a)) => a.len() == len.div_ceil(8),
+            Some(Alpha::Byte(a)) => a.len() == len,
+        };
+        if pixels.len() != len || !alpha_ok {
+            return Err(anyhow!("rgb565 image size mismatch: {width}x{height}"));
+        }
+        Ok(Self { width, height, pixels, alpha })
+    }
+
+    pub fn from_rgb(image: &RgbImage) -> Self {
+        let pixels = rgb_image_to_rgb565(image, 0, 0, 0);
+        Self { width: image.width(), height: image.height(), pixels, alpha: None }
+    }
+
+    /// 根据图片内容选择透明通道：完全不透明时不保存，只有全透明/不透明两种像素时用1位，否则用8位
+    pub fn from_rgba(image: &RgbaImage) -> Self {
+        let pixels = image.pixels().map(|p| rgb888_to_rgb565(p[0], p[1], p[2]).to_be()).collect();
+        let alpha = if image.pixels().all(|p| p[3] == 255) {
+            None
+        } else if image.pixels().all(|p| p[3] == 0 || p[3] == 255) {
+            let mut bits = vec![0u8; (image.width() as usize * image.height() as usize).div_ceil(8)];
+            for (i, p) in image.pixels().enumerate() {
+                if p[3] == 255 {
+                    bits[i / 8] |= 0x80 >> (i % 8);
+                }
+            }
+            Some(Alpha::Bit(bits))
+        } else {
+            Some(Alpha::Byte(image.pixels().map(|p| p[3]).collect()))
+        };
+        Self { width: image.width(), height: image.height(), pixels, alpha }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// 大端序像素
+    pub fn pixels(&self) -> &[u16] {
+        &self.pixels
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        self.alpha.is_some()
+    }
+
+    /// 占用的字节数
+    pub fn size(&self) -> usize {
+        self.pixels.len() * 2
+            + match self.alpha.as_ref() {
+                None => 0,
+                Some(Alpha::Bit(a)) | Some(Alpha::Byte(a)) => a.len(),
+            }
+    }
+
+    /// (x, y) 处的像素 (RGB565, 透明度)
+    #[inline(always)]
+    pub fn get_pixel(&self, x: u32, y: u32) -> (u16, u8) {
+        let i = y as usize * self.width as usize + x as usize;
+        let alpha = match self.alpha.as_ref() {
+            None => 255,
+            Some(Alpha::Bit(a)) => {
+                if a[i / 8] & (0x80 >> (i % 8)) != 0 {
+                    255
+                } else {
+                    0
+                }
+            }
+            Some(Alpha::Byte(a)) => a[i],
+        };
+        (u16::from_be(self.pixels[i]), alpha)
+    }
+
+    pub fn to_rgba_image(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let (pixel, a) = self.get_pixel(x, y);
+            let (r, g, b) = rgb565_to_rgb888(pixel);
+            Rgba([r, g, b, a])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgb565_alpha() {
+        let opaque = RgbaImage::from_pixel(3, 3, Rgba([255, 0, 0, 255]));
+        let image = Rgb565Image::from_rgba(&opaque);
+        assert!(!image.has_alpha());
+        assert_eq!(image.size(), 18);
+        assert_eq!(image.get_pixel(1, 1), (0xF800, 255));
+
+        let mask = RgbaImage::from_fn(3, 3, |x, _| Rgba([0, 0, 255, if x == 1 { 0 } else { 255 }]));
+        let image = Rgb565Image::from_rgba(&mask);
+        assert!(matches!(image.alpha, Some(Alpha::Bit(_))));
+        assert_eq!(image.size(), 18 + 2);
+        assert_eq!(image.get_pixel(1, 2), (0x001F, 0));
+        assert_eq!(image.get_pixel(2, 2), (0x001F, 255));
+
+        let translucent = RgbaImage::from_fn(3, 3, |x, y| Rgba([255, 255, 255, (x * 3 + y) as u8 * 20]));
+        let image = Rgb565Image::from_rgba(&translucent);
+        assert_eq!(image.size(), 18 + 9);
+        assert_eq!(image.get_pixel(2, 1), (0xFFFF, 140));
+        assert_eq!(image.to_rgba_image().get_pixel(2, 1), &Rgba([255, 255, 255, 140]));
+    }
+}
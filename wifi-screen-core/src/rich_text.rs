@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use ab_glyph::{FontRef, FontVec};
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::font::{text_size, TextFont};
+use crate::render::{CSSColor, Element, Rectangle, Text};
+use crate::text_layout::{self, LineBreaker, TextAlign, TextVAlign, TextWrap};
+
+// 富文本：一个元素中包含多段不同颜色、字号、字体的文字，所有段共用一条基线，可以自动换行
+// 排版后展开为 Text/Rectangle 基本元素绘制
+
+/// 富文本中的一段文字，未填写的样式使用 RichText 的设置
+#[derive(Clone, Default, Deserialize)]
+pub struct Span {
+    pub text: String,
+    pub size: Option<f32>,
+    pub color: Option<CSSColor>,
+    /// 上传的字体key
+    pub font: Option<String>,
+    /// 模拟粗体（错开 1 像素绘制两次）
+    #[serde(default)]
+    pub bold: bool,
+    /// 下划线
+    #[serde(default)]
+    pub underline: bool,
+    /// 删除线
+    #[serde(default)]
+    pub strikethrough: bool,
+    /// 背景色，高度为整行
+    pub background: Option<CSSColor>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct RichText {
+    pub x: i32,
+    pub y: i32,
+    pub spans: Vec<Span>,
+    /// 默认字号
+    pub size: f32,
+    /// 默认颜色
+    pub color: CSSColor,
+    /// 默认字体
+    pub font: Option<String>,
+    /// 水平对齐 left/center/right
+    #[serde(default)]
+    pub align: TextAlign,
+    /// 垂直对齐 top/middle/bottom
+    #[serde(default)]
+    pub valign: TextVAlign,
+    /// 最大宽度，超过时自动换行
+    pub max_width: Option<u32>,
+    /// 行高(像素)，默认为这一行中最大的字体高度
+    pub line_height: Option<f32>,
+    /// 不透明度 0~1，默认 1
+    pub opacity: Option<f32>,
+}
+
+/// 一行中同一段文字的部分
+#[derive(Debug, PartialEq)]
+struct Fragment {
+    span: usize,
+    text: String,
+    width: u32,
+}
+
+#[derive(Debug, PartialEq)]
+struct RichLine {
+    fragments: Vec<Fragment>,
+}
+
+impl RichLine {
+    fn width(&self) -> u32 {
+        self.fragments.iter().map(|f| f.width).sum()
+    }
+}
+
+/// 每段文字的字体和字号
+struct SpanStyle<'a> {
+    font: TextFont<'a>,
+    size: f32,
+    bold: bool,
+}
+
+impl SpanStyle<'_> {
+    /// 文字的宽度（下一段文字的起点）
+    fn advance(&self, text: &str) -> u32 {
+        if text.is_empty() {
+            return 0;
+        }
+        text_size(self.size, &self.font, text).0 - 1 + self.bold as u32
+    }
+
+    fn ascent(&self) -> f32 {
+        self.font.ascent(self.size)
+    }
+
+    fn descent(&self) -> f32 {
+        self.font.height(self.size) - self.ascent()
+    }
+}
+
+impl RichText {
+    fn styles<'a>(&self, font: &'a FontRef<'a>, font_cache: &'a HashMap<String, FontVec>) -> Result<Vec<SpanStyle<'a>>> {
+        self.spans
+            .iter()
+            .map(|span| {
+                Ok(SpanStyle {
+                    font: TextFont::find(font, font_cache, span.font.as_deref().or(self.font.as_deref()))?,
+                    size: span.size.unwrap_or(self.size),
+                    bold: span.bold,
+                })
+            })
+            .collect()
+    }
+
+    /// 按 max_width 换行，`\n` 强制换行
+    fn break_lines(&self, styles: &[SpanStyle]) -> Vec<RichLine> {
+        let width = |line: &[text_layout::Fragment<usize>]| line.iter().map(|f| styles[f.span].advance(&f.text)).sum();
+        let mut breaker = LineBreaker::new(self.max_width, width);
+        for (i, span) in self.spans.iter().enumerate() {
+            for (n, paragraph) in span.text.split('\n').enumerate() {
+                if n > 0 {
+                    breaker.break_line();
+                }
+                breaker.push(i, paragraph.strip_suffix('\r').unwrap_or(paragraph), TextWrap::Word);
+            }
+        }
+        breaker
+            .finish()
+            .into_iter()
+            .map(|line| RichLine {
+                fragments: line
+                    .into_iter()
+                    .map(|f| Fragment { width: styles[f.span].advance(&f.text), span: f.span, text: f.text })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// 展开为基本元素
+    pub fn elements(&self, font: &FontRef, font_cache: &HashMap<String, FontVec>) -> Result<Vec<Element>> {
+        let styles = self.styles(font, font_cache)?;
+        let lines = self.break_lines(&styles);
+        let default_font = TextFont::find(font, font_cache, self.font.as_deref())?;
+
+        // 每行的基线位置(相对行顶部)和高度
+        let metrics: Vec<(i32, i32)> = lines
+            .iter()
+            .map(|line| {
+                // 空行使用默认字体的高度
+                let (ascent, descent) = if line.fragments.is_empty() {
+                    (default_font.ascent(self.size), default_font.height(self.size) - default_font.ascent(self.size))
+                } else {
+                    line.fragments.iter().fold((0f32, 0f32), |(a, d), f| {
+                        (a.max(styles[f.span].ascent()), d.max(styles[f.span].descent()))
+                    })
+                };
+                (ascent.ceil() as i32, (ascent.ceil() + descent.ceil()) as i32)
+            })
+            .collect();
+        let advance = |height: i32| self.line_height.map_or(height, |h| h.round() as i32);
+        let block_height = match metrics.split_last() {
+            Some(((_, last), rest)) => rest.iter().map(|(_, h)| advance(*h)).sum::<i32>() + last,
+            None => 0,
+        };
+        let mut top = match self.valign {
+            TextVAlign::Top => self.y,
+            TextVAlign::Middle => self.y - block_height / 2,
+            TextVAlign::Bottom => self.y - block_height,
+        };
+
+        let mut backgrounds = vec![];
+        let mut texts = vec![];
+        for (line, (baseline, height)) in lines.iter().zip(metrics) {
+            let width = line.width() as i32;
+            let mut x = match (self.align, self.max_width) {
+                (TextAlign::Left, _) => self.x,
+                (TextAlign::Center, Some(max_width)) => self.x + (max_width as i32 - width) / 2,
+                (TextAlign::Right, Some(max_width)) => self.x + max_width as i32 - width,
+                (TextAlign::Center, None) => self.x - width / 2,
+                (TextAlign::Right, None) => self.x - width,
+            };
+            let baseline = top + baseline;
+            for fragment in &line.fragments {
+                let (span, style) = (&self.spans[fragment.span], &styles[fragment.span]);
+                let color = span.color.as_ref().unwrap_or(&self.color);
+                if let Some(background) = &span.background {
+                    backgrounds.push(rectangle((x, top), (fragment.width, height as u32), background));
+                }
+                let text = Text {
+                    x,
+                    y: baseline - style.ascent().ceil() as i32,
+                    text: fragment.text.clone(),
+                    size: style.size,
+                    color: color.clone(),
+                    font: span.font.clone().or(self.font.clone()),
+                    ..Default::default()
+                };
+                if span.bold {
+                    texts.push(Element::Text(Text { x: x + 1, ..text.clone() }));
+                }
+                texts.push(Element::Text(text));
+                // 线的粗细随字号变化
+                let thickness = (style.size / 12.0).round().max(1.0) as u32;
+                if span.underline {
+                    texts.push(rectangle((x, baseline + thickness as i32), (fragment.width, thickness), color));
+                }
+                if span.strikethrough {
+                    let y = baseline - (style.ascent() * 0.3).round() as i32 - thickness as i32 / 2;
+                    texts.push(rectangle((x, y), (fragment.width, thickness), color));
+                }
+                x += fragment.width as i32;
+            }
+            top += advance(height);
+        }
+        backgrounds.extend(texts);
+        Ok(backgrounds)
+    }
+}
+
+fn rectangle((left, top): (i32, i32), (width, height): (u32, u32), color: &CSSColor) -> Element {
+    Element::Rectangle(Rectangle { left, top, width, height, stroke_width: 0, fill_color: Some(color.clone().into()), stroke_color: None, opacity: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str) -> Span {
+        Span { text: text.to_string(), ..Default::default() }
+    }
+
+    fn lines(rich: &RichText, font: &FontRef) -> Vec<Vec<(usize, String)>> {
+        let font_cache = HashMap::new();
+        let styles = rich.styles(font, &font_cache).unwrap();
+        rich.break_lines(&styles)
+            .into_iter()
+            .map(|line| line.fragments.into_iter().map(|f| (f.span, f.text)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_break_lines() {
+        let font = FontRef::try_from_slice(include_bytes!("../../VonwaonBitmap-12pxLite.otf")).unwrap();
+        let rich = RichText { size: 12.0, spans: vec![span("CPU "), span("45%"), span(" ok")], ..Default::default() };
+        assert_eq!(lines(&rich, &font), vec![vec![(0, "CPU ".to_string()), (1, "45%".to_string()), (2, " ok".to_string())]]);
+
+        // 宽度只够放下 "CPU 45%"，行尾空白去掉
+        let width = text_size(12.0, &TextFont::builtin(&font), "CPU 45%").0 - 1;
+        let rich = RichText { max_width: Some(width), ..rich };
+        assert_eq!(lines(&rich, &font), vec![
+            vec![(0, "CPU ".to_string()), (1, "45%".to_string())],
+            vec![(2, "ok".to_string())],
+        ]);
+
+        let rich = RichText { max_width: None, spans: vec![span("a\nb"), span("c\n")], ..rich };
+        assert_eq!(lines(&rich, &font), vec![
+            vec![(0, "a".to_string())],
+            vec![(0, "b".to_string()), (1, "c".to_string())],
+            vec![],
+        ]);
+    }
+
+    #[test]
+    fn test_baseline() {
+        let font = FontRef::try_from_slice(include_bytes!("../../VonwaonBitmap-12pxLite.otf")).unwrap();
+        let big = Span { size: Some(24.0), underline: true, ..span("45") };
+        let rich = RichText { x: 0, y: 10, size: 12.0, spans: vec![span("CPU"), big], ..Default::default() };
+        let elements = rich.elements(&font, &HashMap::new()).unwrap();
+        let builtin = TextFont::builtin(&font);
+        let (small, big) = (builtin.ascent(12.0).ceil() as i32, builtin.ascent(24.0).ceil() as i32);
+        match (&elements[0], &elements[1], &elements[2]) {
+            (Element::Text(a), Element::Text(b), Element::Rectangle(underline)) => {
+                // 两段文字的基线对齐
+                assert_eq!(a.y + small, b.y + big);
+                assert_eq!(b.y, 10);
+                assert!(b.x > a.x);
+                assert_eq!(underline.left, b.x);
+                assert_eq!(underline.top, 10 + big + 2);
+            }
+            _ => panic!("expect text, text, underline"),
+        }
+    }
+}
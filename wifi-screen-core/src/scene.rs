@@ -0,0 +1,609 @@
+use std::collections::HashMap;
+
+use ab_glyph::{FontRef, FontVec};
+use anyhow::{anyhow, Result};
+use csscolorparser::Color;
+use image::{Rgb, RgbImage};
+use serde::Deserialize;
+
+use crate::font::TextFont;
+use crate::gradient::Fill;
+use crate::path::paths_bounds;
+use crate::render::{render_elements_banded, CSSColor, Element, ImageCache, Rectangle};
+use crate::rich_text::Span;
+use crate::text_effect::text_bounds;
+use crate::text_layout::layout_text;
+use crate::text_transform::Rotation;
+
+// 保留模式场景：设备保存带 id 的元素列表，收到补丁消息后只重绘变化的区域
+
+/// 脏区域超过这个数量时合并为一个区域
+const MAX_DIRTY_RECTS: usize = 8;
+
+/// 矩形区域 [left, right) x [top, bottom)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounds {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Bounds {
+    pub fn new(left: i32, top: i32, width: u32, height: u32) -> Self {
+        Self { left, top, right: left + width as i32, bottom: top + height as i32 }
+    }
+
+    /// 包含所有点并向外扩展 margin 像素
+    fn from_points(points: &[(i32, i32)], margin: i32) -> Option<Self> {
+        let left = points.iter().map(|p| p.0).min()?;
+        let top = points.iter().map(|p| p.1).min()?;
+        let right = points.iter().map(|p| p.0).max()?;
+        let bottom = points.iter().map(|p| p.1).max()?;
+        Some(Self { left: left - margin, top: top - margin, right: right + 1 + margin, bottom: bottom + 1 + margin })
+    }
+
+    pub(crate) fn expand(&self, margin: i32) -> Self {
+        Self {
+            left: self.left - margin,
+            top: self.top - margin,
+            right: self.right + margin,
+            bottom: self.bottom + margin,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        (self.right - self.left).max(0) as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        (self.bottom - self.top).max(0) as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.right <= self.left || self.bottom <= self.top
+    }
+
+    fn area(&self) -> u64 {
+        self.width() as u64 * self.height() as u64
+    }
+
+    pub fn intersects(&self, other: &Bounds) -> bool {
+        self.left < other.right && other.left < self.right && self.top < other.bottom && other.top < self.bottom
+    }
+
+    pub fn intersection(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            left: self.left.max(other.left),
+            top: self.top.max(other.top),
+            right: self.right.min(other.right),
+            bottom: self.bottom.min(other.bottom),
+        }
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+}
+
+/// 计算元素绘制时覆盖的区域，无法确定时返回 None（按全屏处理）
+pub fn element_bounds(
+    element: &Element,
+    font: &FontRef,
+    font_cache: &HashMap<String, FontVec>,
+    image_cache: &HashMap<String, ImageCache>,
+) -> Option<Bounds> {
+    match element {
+        Element::Text(text) => {
+            let font = TextFont::find(font, font_cache, text.font.as_deref()).ok()?;
+            let bounds = text_bounds(&font, text.size, &layout_text(&font, text))?;
+            // 部分字形会超出字体的行高，描边、阴影和背景超出文字
+            let bounds = bounds.expand(2 + text.effect_margin());
+            Some(Rotation::of(text).map_or(bounds, |rotation| rotation.bounds(&bounds).expand(1)))
+        }
+        Element::RichText(text) => text
+            .elements(font, font_cache)
+            .ok()?
+            .iter()
+            .map(|e| element_bounds(e, font, font_cache, image_cache))
+            .reduce(|a, b| Some(a?.union(&b?)))?,
+        Element::Image(image) => {
+            let rect = image.source_rect(image_cache.get(image.key.as_ref()?)?).ok()?;
+            if image.needs_scaling() {
+                let layout = image.fit_layout(rect);
+                let (_, _, w, h) = layout.visible;
+                return Some(Bounds::new(image.x + layout.offset.0, image.y + layout.offset.1, w, h));
+            }
+            Some(Bounds::new(image.x, image.y, rect.2, rect.3))
+        }
+        Element::RawImage((x, y, img)) => Some(Bounds::new(*x, *y, img.width(), img.height())),
+        Element::RawRgbImage((x, y, img)) => Some(Bounds::new(*x, *y, img.width(), img.height())),
+        Element::Line(line) => Bounds::from_points(&[line.start, line.end], line.stroke_width as i32),
+        Element::Circle(c) => Some(Bounds::new(c.top_left.0, c.top_left.1, c.diameter, c.diameter).expand(c.stroke_width as i32)),
+        Element::Ellipse(e) => Some(Bounds::new(e.top_left.0, e.top_left.1, e.size.0, e.size.1).expand(e.stroke_width as i32)),
+        Element::Arc(a) => Some(Bounds::new(a.top_left.0, a.top_left.1, a.diameter, a.diameter).expand(a.stroke_width as i32)),
+        Element::Sector(s) => Some(Bounds::new(s.top_left.0, s.top_left.1, s.diameter, s.diameter).expand(s.stroke_width as i32)),
+        Element::Rectangle(r) => Some(Bounds::new(r.left, r.top, r.width, r.height).expand(r.stroke_width as i32)),
+        Element::RoundedRectangle(r) => Some(Bounds::new(r.left, r.top, r.width, r.height).expand(r.stroke_width as i32)),
+        Element::Polyline(p) => Bounds::from_points(&p.points, p.stroke_width as i32),
+        Element::Triangle(t) => Bounds::from_points(&[t.vertex1, t.vertex2, t.vertex3], t.stroke_width as i32),
+        Element::Polygon(p) => Bounds::from_points(&p.points, p.stroke_width as i32),
+        // 曲线在控制点组成的凸包内
+        Element::CubicBezier(c) => Bounds::from_points(&[c.start, c.control1, c.control2, c.end], c.stroke_width as i32),
+        Element::Path(p) => {
+            let (left, top, right, bottom) = paths_bounds(&p.sub_paths().ok()?)?;
+            let corners = [(left.floor() as i32, top.floor() as i32), (right.ceil() as i32, bottom.ceil() as i32)];
+            Bounds::from_points(&corners, p.stroke_width as i32 + 1)
+        }
+        // 粗线和最后一个点的标记可能超出图表区域
+        Element::LineChart(c) => Some(Bounds::new(c.x, c.y, c.width, c.height).expand(c.stroke_width.unwrap_or(1) as i32)),
+        Element::BarChart(c) => Some(Bounds::new(c.x, c.y, c.width, c.height)),
+        Element::Sparkline(c) => Some(Bounds::new(c.x, c.y, c.width, c.height).expand(c.stroke_width.unwrap_or(1) as i32 + 1)),
+        Element::Gauge(g) => Some(Bounds::new(g.top_left.0, g.top_left.1, g.diameter, g.diameter).expand(1)),
+        Element::ProgressBar(b) => Some(Bounds::new(b.left, b.top, b.width, b.height)),
+        Element::Meter(m) => Some(Bounds::new(m.top_left.0, m.top_left.1, m.diameter, m.diameter).expand(1)),
+        Element::QrCode(q) => q.size().ok().map(|size| Bounds::new(q.x, q.y, size, size)),
+        Element::Group(group) => {
+            let children = group.children_bounds(image_cache, |e| element_bounds(e, font, font_cache, image_cache));
+            match (children, group.clip_bounds()) {
+                (Some(children), Some(clip)) => Some(children.intersection(&clip)),
+                (children, clip) => children.or(clip),
+            }
+        }
+    }
+}
+
+/// 场景中的一个元素，JSON格式: {"id":"temp","Text":{...}}
+#[derive(Clone, Deserialize)]
+pub struct SceneNode {
+    pub id: String,
+    #[serde(flatten)]
+    pub element: Element,
+}
+
+/// 修改元素的属性，未填写的字段保持不变
+#[derive(Clone, Deserialize)]
+pub struct ElementUpdate {
+    pub id: String,
+    /// Text 的文字，QrCode 的数据
+    pub text: Option<String>,
+    /// RichText 的文字段
+    pub spans: Option<Vec<Span>>,
+    /// 图表的数据
+    pub values: Option<Vec<f32>>,
+    /// Gauge/ProgressBar/Meter 的数值
+    pub value: Option<f32>,
+    /// Text/RichText(默认颜色)/Line/Arc/Polyline/CubicBezier/LineChart/Sparkline/Gauge/Meter 的颜色
+    pub color: Option<CSSColor>,
+    #[serde(alias = "fill")]
+    pub fill_color: Option<Fill>,
+    pub stroke_color: Option<CSSColor>,
+    /// 不透明度 0~1
+    pub opacity: Option<f32>,
+    /// 新的位置（元素的左上角或起点）
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+}
+
+/// 场景补丁消息
+#[derive(Clone, Deserialize)]
+pub enum ScenePatch {
+    /// 替换整个场景并全屏重绘
+    Set(Vec<SceneNode>),
+    /// 添加元素到最上层，id 已存在时原位替换
+    Add(SceneNode),
+    /// 修改元素属性
+    Update(ElementUpdate),
+    /// 删除元素
+    Remove(String),
+    /// 修改元素的层级，z 为绘制顺序（0为最底层）
+    Reorder { id: String, z: usize },
+    /// 设置背景色并全屏重绘
+    Background(CSSColor),
+    /// 清空场景
+    Clear,
+}
+
+struct Node {
+    node: SceneNode,
+    /// 上一次绘制时覆盖的区域，None 表示需要重新计算
+    bounds: Option<Bounds>,
+}
+
+/// 保留模式场景
+pub struct Scene {
+    nodes: Vec<Node>,
+    background: Rgb<u8>,
+    dirty: Vec<Bounds>,
+    full_redraw: bool,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self { nodes: vec![], background: Rgb([0, 0, 0]), dirty: vec![], full_redraw: true }
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.nodes.iter().map(|n| n.node.id.clone()).collect()
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.nodes.iter().map(|n| &n.node.element)
+    }
+
+    fn index_of(&self, id: &str) -> Result<usize> {
+        self.nodes.iter().position(|n| n.node.id == id).ok_or_else(|| anyhow!("id not exist:{id}"))
+    }
+
+    /// 元素被修改/删除时，原来覆盖的区域需要重绘
+    fn invalidate(&mut self, index: usize) {
+        match self.nodes[index].bounds.take() {
+            Some(bounds) => self.dirty.push(bounds),
+            None => self.full_redraw = true,
+        }
+    }
+
+    pub fn apply(&mut self, patch: ScenePatch) -> Result<()> {
+        match patch {
+            ScenePatch::Set(nodes) => {
+                self.nodes = nodes.into_iter().map(|node| Node { node, bounds: None }).collect();
+                self.full_redraw = true;
+            }
+            ScenePatch::Add(node) => match self.index_of(&node.id) {
+                Ok(index) => {
+                    self.invalidate(index);
+                    self.nodes[index].node = node;
+                }
+                Err(_) => self.nodes.push(Node { node, bounds: None }),
+            },
+            ScenePatch::Update(update) => {
+                let index = self.index_of(&update.id)?;
+                self.invalidate(index);
+                update_element(&mut self.nodes[index].node.element, update);
+            }
+            ScenePatch::Remove(id) => {
+                let index = self.index_of(&id)?;
+                self.invalidate(index);
+                self.nodes.remove(index);
+            }
+            ScenePatch::Reorder { id, z } => {
+                let index = self.index_of(&id)?;
+                self.invalidate(index);
+                let node = self.nodes.remove(index);
+                let z = z.min(self.nodes.len());
+                self.nodes.insert(z, node);
+            }
+            ScenePatch::Background(color) => {
+                let [r, g, b, _] = color.rgba();
+                self.background = Rgb([r, g, b]);
+                self.full_redraw = true;
+            }
+            ScenePatch::Clear => {
+                self.nodes.clear();
+                self.full_redraw = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// 计算需要重绘的区域并清空脏区域记录
+    fn take_dirty(
+        &mut self,
+        screen: Bounds,
+        font: &FontRef,
+        font_cache: &HashMap<String, FontVec>,
+        image_cache: &HashMap<String, ImageCache>,
+    ) -> Vec<Bounds> {
+        // 新增/修改的元素，新位置也需要重绘
+        for node in self.nodes.iter_mut().filter(|n| n.bounds.is_none()) {
+            let bounds = element_bounds(&node.node.element, font, font_cache, image_cache).unwrap_or(screen);
+            node.bounds = Some(bounds);
+            self.dirty.push(bounds);
+        }
+        let dirty = std::mem::take(&mut self.dirty);
+        if std::mem::take(&mut self.full_redraw) {
+            return vec![screen];
+        }
+        merge_dirty_rects(dirty, screen)
+    }
+
+    /// 重绘变化的区域，每个区域按 band_height 分条渲染，每一条渲染完成后调用 draw(x, y, 条带图像) 输出到屏幕
+    pub fn render(
+        &mut self,
+        (width, height): (u32, u32),
+        band_height: u32,
+        font: &FontRef,
+        font_cache: &HashMap<String, FontVec>,
+        image_cache: &HashMap<String, ImageCache>,
+        mut draw: impl FnMut(u16, u16, &RgbImage) -> Result<()>,
+    ) -> Result<()> {
+        let screen = Bounds::new(0, 0, width, height);
+        let [r, g, b] = self.background.0;
+        let background = CSSColor(Color::from_rgba8(r, g, b, 255));
+        for rect in self.take_dirty(screen, font, font_cache, image_cache) {
+            //先填充背景色
+            let mut elements = vec![Element::Rectangle(Rectangle {
+                left: rect.left,
+                top: rect.top,
+                width: rect.width(),
+                height: rect.height(),
+                stroke_width: 0,
+                fill_color: Some(background.clone().into()),
+                stroke_color: None,
+                opacity: None,
+            })];
+            elements.extend(
+                self.nodes
+                    .iter()
+                    .filter(|n| n.bounds.map_or(true, |b| b.intersects(&rect)))
+                    .map(|n| n.node.element.clone()),
+            );
+            render_elements_banded(rect, band_height, font, font_cache, image_cache, &elements, &mut draw)?;
+        }
+        Ok(())
+    }
+}
+
+fn update_element(element: &mut Element, update: ElementUpdate) {
+    if let Some(new_text) = update.text {
+        match element {
+            Element::Text(text) => text.text = new_text,
+            Element::QrCode(qr) => qr.data = new_text,
+            _ => (),
+        }
+    }
+    if let (Element::RichText(e), Some(spans)) = (&mut *element, update.spans) {
+        e.spans = spans;
+    }
+    if let Some(values) = update.values {
+        match element {
+            Element::LineChart(e) => e.values = values,
+            Element::BarChart(e) => e.values = values,
+            Element::Sparkline(e) => e.values = values,
+            _ => (),
+        }
+    }
+    if let Some(value) = update.value {
+        match element {
+            Element::Gauge(e) => e.value = value,
+            Element::ProgressBar(e) => e.value = value,
+            Element::Meter(e) => e.value = value,
+            _ => (),
+        }
+    }
+    if let Some(color) = update.color {
+        match element {
+            Element::Text(e) => e.color = color,
+            Element::RichText(e) => e.color = color,
+            Element::Line(e) => e.color = color,
+            Element::Arc(e) => e.color = color,
+            Element::Polyline(e) => e.color = color,
+            Element::CubicBezier(e) => e.color = color,
+            Element::LineChart(e) => e.color = color,
+            Element::Sparkline(e) => e.color = color,
+            Element::Gauge(e) => e.color = color,
+            Element::Meter(e) => e.color = color,
+            _ => (),
+        }
+    }
+    if update.opacity.is_some() {
+        match element {
+            Element::Text(e) => e.opacity = update.opacity,
+            Element::RichText(e) => e.opacity = update.opacity,
+            Element::Image(e) => e.opacity = update.opacity,
+            Element::Line(e) => e.opacity = update.opacity,
+            Element::Circle(e) => e.opacity = update.opacity,
+            Element::Ellipse(e) => e.opacity = update.opacity,
+            Element::Arc(e) => e.opacity = update.opacity,
+            Element::Sector(e) => e.opacity = update.opacity,
+            Element::Rectangle(e) => e.opacity = update.opacity,
+            Element::RoundedRectangle(e) => e.opacity = update.opacity,
+            Element::Polyline(e) => e.opacity = update.opacity,
+            Element::Triangle(e) => e.opacity = update.opacity,
+            Element::Polygon(e) => e.opacity = update.opacity,
+            Element::CubicBezier(e) => e.opacity = update.opacity,
+            Element::Path(e) => e.opacity = update.opacity,
+            Element::LineChart(e) => e.opacity = update.opacity,
+            Element::BarChart(e) => e.opacity = update.opacity,
+            Element::Sparkline(e) => e.opacity = update.opacity,
+            Element::Gauge(e) => e.opacity = update.opacity,
+            Element::ProgressBar(e) => e.opacity = update.opacity,
+            Element::Meter(e) => e.opacity = update.opacity,
+            Element::QrCode(e) => e.opacity = update.opacity,
+            Element::Group(e) => e.opacity = update.opacity,
+            Element::RawImage(_) | Element::RawRgbImage(_) => (),
+        }
+    }
+    if let (Element::Text(e), Some(color)) = (&mut *element, &update.stroke_color) {
+        e.stroke_color = Some(color.clone());
+    }
+    if update.fill_color.is_some() || update.stroke_color.is_some() {
+        let (fill_color, stroke_color) = match element {
+            Element::Circle(e) => (&mut e.fill_color, &mut e.stroke_color),
+            Element::Ellipse(e) => (&mut e.fill_color, &mut e.stroke_color),
+            Element::Sector(e) => (&mut e.fill_color, &mut e.stroke_color),
+            Element::Rectangle(e) => (&mut e.fill_color, &mut e.stroke_color),
+            Element::RoundedRectangle(e) => (&mut e.fill_color, &mut e.stroke_color),
+            Element::Triangle(e) => (&mut e.fill_color, &mut e.stroke_color),
+            Element::Polygon(e) => (&mut e.fill_color, &mut e.stroke_color),
+            Element::Path(e) => (&mut e.fill_color, &mut e.stroke_color),
+            _ => return,
+        };
+        if update.fill_color.is_some() {
+            *fill_color = update.fill_color;
+        }
+        if update.stroke_color.is_some() {
+            *stroke_color = update.stroke_color;
+        }
+    }
+    if update.x.is_some() || update.y.is_some() {
+        let (x, y) = element.position();
+        element.translate(update.x.unwrap_or(x) - x, update.y.unwrap_or(y) - y);
+    }
+}
+
+/// 裁剪到屏幕范围内，合并重叠的区域
+/// 区域太多或总面积超过全屏时直接合并为一个区域
+fn merge_dirty_rects(dirty: Vec<Bounds>, screen: Bounds) -> Vec<Bounds> {
+    let mut rects: Vec<Bounds> = vec![];
+    for rect in dirty {
+        let mut rect = rect.intersection(&screen);
+        if rect.is_empty() {
+            continue;
+        }
+        // 与已有区域重叠时合并，合并后可能又与其他区域重叠，所以重复检查
+        while let Some(i) = rects.iter().position(|r| r.intersects(&rect)) {
+            rect = rect.union(&rects.swap_remove(i));
+        }
+        rects.push(rect);
+    }
+    let total: u64 = rects.iter().map(|r| r.area()).sum();
+    if rects.len() > MAX_DIRTY_RECTS || total >= screen.area() {
+        return rects.into_iter().reduce(|a, b| a.union(&b)).into_iter().collect();
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::render_elements;
+
+    fn font() -> FontRef<'static> {
+        FontRef::try_from_slice(include_bytes!("../../VonwaonBitmap-12pxLite.otf")).unwrap()
+    }
+
+    fn patch(json: &str) -> ScenePatch {
+        serde_json::from_str(json).unwrap()
+    }
+
+    /// 渲染并返回重绘的区域
+    fn render(scene: &mut Scene, canvas: &mut RgbImage) -> Vec<Bounds> {
+        render_banded(scene, canvas, u32::MAX)
+    }
+
+    /// 分条渲染并返回每一条的区域
+    fn render_banded(scene: &mut Scene, canvas: &mut RgbImage, band_height: u32) -> Vec<Bounds> {
+        let mut rects = vec![];
+        scene
+            .render(canvas.dimensions(), band_height, &font(), &HashMap::new(), &HashMap::new(), |x, y, img| {
+                image::imageops::replace(canvas, img, x as i64, y as i64);
+                rects.push(Bounds::new(x as i32, y as i32, img.width(), img.height()));
+                Ok(())
+            })
+            .unwrap();
+        rects
+    }
+
+    fn full_render(scene: &Scene, w: u32, h: u32) -> RgbImage {
+        let elements: Vec<Element> = scene.nodes.iter().map(|n| n.node.element.clone()).collect();
+        let mut canvas = RgbImage::from_pixel(w, h, scene.background);
+        render_elements(&mut canvas, &font(), &HashMap::new(), &HashMap::new(), &elements).unwrap();
+        canvas
+    }
+
+    #[test]
+    fn test_banded_render() {
+        let mut scene = Scene::new();
+        let mut canvas = RgbImage::new(64, 64);
+        scene.apply(patch(r#"{"Set":[
+            {"id":"temp","Text":{"x":2,"y":4,"text":"12.5","size":12,"color":"white"}},
+            {"id":"dot","Circle":{"top_left":[40,40],"diameter":10,"stroke_width":1,"fill_color":"red","stroke_color":null}}
+        ]}"#)).unwrap();
+        scene.apply(patch(r#"{"Background":"navy"}"#)).unwrap();
+        // 全屏重绘也按条带渲染
+        let rects = render_banded(&mut scene, &mut canvas, 10);
+        assert_eq!(rects.len(), 7);
+        assert!(rects.iter().all(|r| r.width() == 64 && r.height() <= 10));
+        assert_eq!(canvas, full_render(&scene, 64, 64));
+
+        scene.apply(patch(r#"{"Update":{"id":"dot","x":20,"y":30}}"#)).unwrap();
+        let rects = render_banded(&mut scene, &mut canvas, 4);
+        assert!(rects.iter().all(|r| r.width() < 64 && r.height() <= 4));
+        assert_eq!(canvas, full_render(&scene, 64, 64));
+    }
+
+    #[test]
+    fn test_merge_dirty_rects() {
+        let screen = Bounds::new(0, 0, 100, 100);
+        let rects = merge_dirty_rects(
+            vec![Bounds::new(0, 0, 10, 10), Bounds::new(5, 5, 10, 10), Bounds::new(50, 50, 10, 10), Bounds::new(-20, 95, 30, 30)],
+            screen,
+        );
+        assert_eq!(rects, vec![Bounds::new(0, 0, 15, 15), Bounds::new(50, 50, 10, 10), Bounds::new(0, 95, 10, 5)]);
+        let rects = merge_dirty_rects(vec![Bounds::new(0, 0, 100, 60), Bounds::new(0, 50, 100, 50)], screen);
+        assert_eq!(rects, vec![screen]);
+    }
+
+    #[test]
+    fn test_partial_update_matches_full_render() {
+        let mut scene = Scene::new();
+        let mut canvas = RgbImage::new(64, 64);
+        scene.apply(patch(r#"{"Set":[
+            {"id":"bg","Rectangle":{"left":0,"top":0,"width":64,"height":20,"stroke_width":0,"fill_color":"navy","stroke_color":null}},
+            {"id":"temp","Text":{"x":2,"y":4,"text":"12.5","size":12,"color":"white"}},
+            {"id":"dot","Circle":{"top_left":[40,40],"diameter":10,"stroke_width":1,"fill_color":"red","stroke_color":null}}
+        ]}"#)).unwrap();
+        assert_eq!(render(&mut scene, &mut canvas), vec![Bounds::new(0, 0, 64, 64)]);
+
+        scene.apply(patch(r#"{"Update":{"id":"temp","text":"13.0","color":"yellow"}}"#)).unwrap();
+        let rects = render(&mut scene, &mut canvas);
+        assert_eq!(rects.len(), 1);
+        assert!(rects[0].area() < 64 * 20);
+        assert_eq!(canvas, full_render(&scene, 64, 64));
+
+        scene.apply(patch(r#"{"Update":{"id":"dot","x":10,"y":30}}"#)).unwrap();
+        assert_eq!(render(&mut scene, &mut canvas).len(), 2);
+        assert_eq!(canvas, full_render(&scene, 64, 64));
+
+        scene.apply(patch(r#"{"Update":{"id":"bg","opacity":0.5}}"#)).unwrap();
+        render(&mut scene, &mut canvas);
+        assert_eq!(canvas, full_render(&scene, 64, 64));
+
+        scene.apply(patch(r#"{"Add":{"id":"cpu","Sparkline":{"x":30,"y":24,"width":30,"height":12,"values":[1,3,2],"color":"lime"}}}"#)).unwrap();
+        render(&mut scene, &mut canvas);
+        scene.apply(patch(r#"{"Update":{"id":"cpu","values":[3,2,5,1]}}"#)).unwrap();
+        assert_eq!(render(&mut scene, &mut canvas).len(), 1);
+        assert_eq!(canvas, full_render(&scene, 64, 64));
+        scene.apply(patch(r#"{"Add":{"id":"load","ProgressBar":{"left":2,"top":56,"width":40,"height":6,"value":30,"color":"lime"}}}"#)).unwrap();
+        render(&mut scene, &mut canvas);
+        scene.apply(patch(r#"{"Update":{"id":"load","value":75}}"#)).unwrap();
+        assert_eq!(render(&mut scene, &mut canvas).len(), 1);
+        assert_eq!(canvas, full_render(&scene, 64, 64));
+
+        scene.apply(patch(r#"{"Add":{"id":"msg","RichText":{"x":2,"y":44,"size":12,"color":"white","spans":[{"text":"CPU "},{"text":"42%","color":"lime"}]}}}"#)).unwrap();
+        render(&mut scene, &mut canvas);
+        let old = scene.nodes[5].bounds.unwrap();
+        scene.apply(patch(r#"{"Update":{"id":"msg","spans":[{"text":"CPU "},{"text":"100%","color":"red","underline":true}]}}"#)).unwrap();
+        let rects = render(&mut scene, &mut canvas);
+        // 重绘区域覆盖新旧两段文字
+        assert_eq!(rects.len(), 1);
+        assert_eq!(rects[0], old.union(&scene.nodes[5].bounds.unwrap()).intersection(&Bounds::new(0, 0, 64, 64)));
+        assert_eq!(canvas, full_render(&scene, 64, 64));
+        scene.apply(patch(r#"{"Remove":"msg"}"#)).unwrap();
+        render(&mut scene, &mut canvas);
+
+        scene.apply(patch(r#"{"Reorder":{"id":"bg","z":2}}"#)).unwrap();
+        render(&mut scene, &mut canvas);
+        assert_eq!(canvas, full_render(&scene, 64, 64));
+
+        scene.apply(patch(r#"{"Remove":"bg"}"#)).unwrap();
+        render(&mut scene, &mut canvas);
+        assert_eq!(canvas, full_render(&scene, 64, 64));
+        assert_eq!(scene.ids(), vec!["temp", "dot", "cpu", "load"]);
+
+        assert!(render(&mut scene, &mut canvas).is_empty());
+        assert!(scene.apply(patch(r#"{"Remove":"none"}"#)).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use ab_glyph::FontRef;
+use csscolorparser::Color;
+use image::RgbImage;
+
+use crate::imageproc::drawing::text_size;
+use crate::qrcode::{QrCode, QrEcc};
+use crate::render::{CSSColor, Element, Line, Rectangle, Text};
+
+// 开机闪屏：logo、WiFi 状态和提示信息，热点模式下显示配置页面地址的二维码
+// 按屏幕大小排版：屏幕够高时二维码在文字下方，宽屏时在文字右侧，都放不下时代替 logo 放在最上方
+
+/// logo 的顶部位置
+const TOP: i32 = 15;
+/// logo、文字和二维码之间的间距
+const GAP: i32 = 8;
+/// 二维码模块的最大/最小像素大小，模块太小时手机很难识别
+const MAX_MODULE_SIZE: i32 = 4;
+const MIN_MODULE_SIZE: i32 = 2;
+
+/// 闪屏上的一行文字，水平居中
+#[derive(Clone)]
+pub struct SplashLine {
+    pub text: String,
+    pub color: CSSColor,
+    /// 文字下方画线
+    pub underline: bool,
+}
+
+impl SplashLine {
+    pub fn new(text: impl Into<String>, color: Color) -> Self {
+        Self { text: text.into(), color: CSSColor(color), underline: false }
+    }
+
+    pub fn underline(self) -> Self {
+        Self { underline: true, ..self }
+    }
+}
+
+/// 二维码的位置
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum QrPlace {
+    /// 文字下方
+    Below,
+    /// 文字右侧
+    Beside,
+    /// 代替 logo
+    Top,
+}
+
+/// 生成闪屏的元素，qrcode 为二维码的内容，屏幕放不下时不显示
+pub fn splash_elements(width: u32, height: u32, font: &FontRef, logo: Box<RgbImage>, lines: &[SplashLine], qrcode: Option<&str>) -> Vec<Element> {
+    let (screen_width, screen_height) = (width as i32, height as i32);
+    // 小屏幕使用点阵字体原本的大小
+    let font_size: f32 = if screen_width < 200 { 12. } else { 20. };
+    let line_height = (font_size * 1.2).round() as i32;
+    let text_width = lines.iter().map(|line| text_size(font_size, font, &line.text).0 as i32).max().unwrap_or(0);
+    let text_height = line_height * lines.len() as i32;
+    let logo_height = logo.height() as i32 + GAP;
+
+    let mut qrcode = qrcode.map(|data| QrCode {
+        x: 0,
+        y: 0,
+        module_size: Some(1),
+        data: data.to_string(),
+        ecc: QrEcc::Low,
+        fg: None,
+        bg: None,
+        margin: None,
+        opacity: None,
+    });
+    // 二维码的边长(模块)
+    let modules = qrcode.as_ref().and_then(|qr| qr.size().ok()).unwrap_or(0) as i32;
+    let module_size = |max_width: i32, max_height: i32| match modules {
+        0 => 0,
+        _ => (max_width.min(max_height) / modules).min(MAX_MODULE_SIZE),
+    };
+    // 模块最大的位置，相同时按顺序优先
+    let below = module_size(screen_width - GAP * 2, screen_height - TOP - logo_height - text_height - GAP * 2);
+    let beside = match screen_width > screen_height {
+        true => module_size(screen_width - text_width - GAP * 3, screen_height - GAP * 2),
+        false => 0,
+    };
+    let top = module_size(screen_width - GAP * 2, screen_height - text_height - GAP * 3);
+    let (place, module) = [(QrPlace::Below, below), (QrPlace::Beside, beside), (QrPlace::Top, top)]
+        .into_iter()
+        .fold((QrPlace::Below, 0), |best, (place, module)| if module > best.1 { (place, module) } else { best });
+    if module < MIN_MODULE_SIZE {
+        qrcode = None;
+    }
+    let side = modules * module;
+
+    let mut elements = vec![];
+    //绘制底色
+    elements.push(Element::Rectangle(Rectangle {
+        left: 0,
+        top: 0,
+        width,
+        height,
+        stroke_width: 0,
+        fill_color: Some(CSSColor(Color::new(0.0666, 0.0666, 0.0666, 1.)).into()),
+        stroke_color: None,
+        opacity: None,
+    }));
+
+    // 文字(和 logo)的水平中心、文字的顶部
+    let mut center_x = screen_width / 2;
+    let mut text_top = TOP + logo_height;
+    match (&mut qrcode, place) {
+        (Some(qr), QrPlace::Below) => {
+            qr.x = center_x - side / 2;
+            qr.y = text_top + text_height + GAP;
+        }
+        (Some(qr), QrPlace::Beside) => {
+            qr.x = screen_width - GAP - side;
+            qr.y = (screen_height - side) / 2;
+            center_x = (screen_width - GAP - side) / 2;
+        }
+        (Some(qr), QrPlace::Top) => {
+            qr.x = center_x - side / 2;
+            qr.y = GAP;
+            text_top = GAP * 2 + side;
+        }
+        (None, _) => (),
+    }
+    if qrcode.is_none() || place != QrPlace::Top {
+        //绘制logo
+        elements.push(Element::RawRgbImage((center_x - logo.width() as i32 / 2, TOP, logo)));
+    }
+
+    for (i, line) in lines.iter().enumerate() {
+        let (text_width, _) = text_size(font_size, font, &line.text);
+        let x = center_x - text_width as i32 / 2;
+        let y = text_top + line_height * i as i32;
+        elements.push(Element::Text(Text {
+            x,
+            y,
+            text: line.text.clone(),
+            size: font_size,
+            color: line.color.clone(),
+            ..Default::default()
+        }));
+        if line.underline {
+            let y = y + font_size as i32 + 1;
+            elements.push(Element::Line(Line {
+                start: (x, y),
+                end: (x + text_width as i32, y),
+                stroke_width: 1,
+                color: line.color.clone(),
+                opacity: None,
+                antialias: false,
+            }));
+        }
+    }
+
+    if let Some(mut qr) = qrcode {
+        qr.module_size = Some(module as u32);
+        elements.push(Element::QrCode(qr));
+    }
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(width: u32, height: u32, lines: usize) -> Option<(i32, i32, u32)> {
+        let font = FontRef::try_from_slice(include_bytes!("../../VonwaonBitmap-12pxLite.otf")).unwrap();
+        let lines = vec![SplashLine::new("ESP32-WiFiScreen", Color::new(1., 1., 1., 1.)); lines];
+        let logo = Box::new(RgbImage::new(32, 32));
+        splash_elements(width, height, &font, logo, &lines, Some("http://192.168.72.1")).into_iter().find_map(|e| match e {
+            Element::QrCode(qr) => Some((qr.x, qr.y, qr.module_size.unwrap())),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_qrcode_place() {
+        // 竖屏：文字下方
+        let (_, y, module) = place(240, 320, 5).unwrap();
+        assert!(y > TOP + 32 + 5 * 24);
+        assert_eq!(module, 4);
+        // 宽屏：文字右侧
+        assert_eq!(place(320, 172, 5), Some((320 - GAP - 29 * 2, (172 - 29 * 2) / 2, 2)));
+        // 方屏和小屏：代替 logo
+        assert_eq!(place(320, 240, 5), Some((160 - 29 * 3 / 2, GAP, 3)));
+        assert_eq!(place(240, 240, 5), Some((120 - 29 * 3 / 2, GAP, 3)));
+        assert_eq!(place(128, 160, 5), Some((64 - 29, GAP, 2)));
+        assert_eq!(place(80, 160, 5), Some((40 - 29, GAP, 2)));
+        assert_eq!(place(80, 120, 5), None);
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+// 精灵图：一张上传的图片中包含多个图标/动画帧，Image 元素通过 frame 只绘制其中一帧
+// 多个小图标合并为一张图片上传，减少缓存中的图片数量
+
+/// 精灵图中的帧，数字为网格中的序号（从左到右、从上到下，从0开始），字符串为命名帧
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum SpriteFrame {
+    Index(u32),
+    Name(String),
+}
+
+/// 精灵图的帧定义，JSON格式: {"cell":[16,16]} 或 {"frames":{"play":[0,0,16,16],"stop":[16,0,16,16]}}
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpriteSheet {
+    /// 网格单元大小 (宽, 高)
+    pub cell: Option<(u32, u32)>,
+    /// 命名帧 (x, y, 宽, 高)
+    #[serde(default)]
+    pub frames: HashMap<String, (u32, u32, u32, u32)>,
+}
+
+impl SpriteSheet {
+    pub fn check(&self) -> Result<()> {
+        if matches!(self.cell, Some((0, _)) | Some((_, 0))) {
+            return Err(anyhow!("精灵图网格大小不能为0"));
+        }
+        if self.cell.is_none() && self.frames.is_empty() {
+            return Err(anyhow!("精灵图需要设置cell或frames"));
+        }
+        Ok(())
+    }
+
+    /// 帧在图片中的区域 (x, y, 宽, 高)，超出图片的部分会被裁掉
+    pub fn frame_rect(&self, frame: &SpriteFrame, (width, height): (u32, u32)) -> Result<(u32, u32, u32, u32)> {
+        let (x, y, w, h) = match frame {
+            SpriteFrame::Name(name) => *self.frames.get(name).ok_or(anyhow!("sprite frame not exist:{name}"))?,
+            SpriteFrame::Index(index) => {
+                let (cw, ch) = self.cell.ok_or(anyhow!("精灵图没有设置cell，只能使用命名帧"))?;
+                let columns = width / cw.max(1);
+                let rows = height / ch.max(1);
+                if *index >= columns * rows {
+                    return Err(anyhow!("sprite frame {index} 超出范围(共{}帧)", columns * rows));
+                }
+                ((index % columns) * cw, (index / columns) * ch, cw, ch)
+            }
+        };
+        let (x, y) = (x.min(width), y.min(height));
+        Ok((x, y, w.min(width - x), h.min(height - y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_rect() {
+        let sheet: SpriteSheet = serde_json::from_str(r#"{"cell":[16,16],"frames":{"big":[8,8,100,100]}}"#).unwrap();
+        assert!(sheet.check().is_ok());
+        // 50x40 的图片只能放下 3x2 个完整的网格
+        assert_eq!(sheet.frame_rect(&SpriteFrame::Index(4), (50, 40)).unwrap(), (16, 16, 16, 16));
+        assert!(sheet.frame_rect(&SpriteFrame::Index(6), (50, 40)).is_err());
+        assert_eq!(sheet.frame_rect(&SpriteFrame::Name("big".to_string()), (50, 40)).unwrap(), (8, 8, 42, 32));
+        assert!(sheet.frame_rect(&SpriteFrame::Name("none".to_string()), (50, 40)).is_err());
+
+        let frame: SpriteFrame = serde_json::from_str(r#""big""#).unwrap();
+        assert_eq!(frame, SpriteFrame::Name("big".to_string()));
+        assert!(serde_json::from_str::<SpriteSheet>(r#"{"cell":[0,16]}"#).unwrap().check().is_err());
+        assert!(SpriteSheet::default().check().is_err());
+    }
+}
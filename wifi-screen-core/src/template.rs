@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::render::Element;
+
+// 模板：设备保存带 {{变量}} 占位符的元素列表，之后只发送变量即可重绘整个界面
+// 字符串整体为 "{{name}}" 时替换为变量的值（可以是数字、数组等任意类型），否则按文本插入字符串中
+
+/// 最多保存的模板数量
+pub const MAX_TEMPLATES: usize = 16;
+
+/// 模板名称的最大长度
+pub const MAX_TEMPLATE_NAME_LEN: usize = 32;
+
+/// 绘制模板的参数 {"template":"xxx","vars":{"temp":25.5}}
+#[derive(Clone, Debug, Deserialize)]
+pub struct RenderTemplate {
+    pub template: String,
+    #[serde(default)]
+    pub vars: HashMap<String, Value>,
+}
+
+/// 内存中的模板，重启后需要重新上传
+#[derive(Default)]
+pub struct Templates {
+    templates: HashMap<String, Vec<Value>>,
+}
+
+impl Templates {
+    /// 保存模板，已存在时覆盖
+    pub fn insert(&mut self, name: &str, elements: Vec<Value>) -> Result<()> {
+        if name.is_empty() || name.len() > MAX_TEMPLATE_NAME_LEN {
+            return Err(anyhow!("模板名称不能为空，且不超过{MAX_TEMPLATE_NAME_LEN}个字符"));
+        }
+        if !self.templates.contains_key(name) && self.templates.len() >= MAX_TEMPLATES {
+            return Err(anyhow!("最多保存{MAX_TEMPLATES}个模板"));
+        }
+        if let Some(i) = elements.iter().position(|element| !element.is_object()) {
+            return Err(anyhow!("模板的第{i}个元素不是对象"));
+        }
+        self.templates.insert(name.to_string(), elements);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.templates.remove(name).is_some()
+    }
+
+    /// 设置模板，None 时删除
+    pub fn set(&mut self, name: &str, elements: Option<Vec<Value>>) -> Result<()> {
+        match elements {
+            Some(elements) => self.insert(name, elements),
+            None if self.remove(name) => Ok(()),
+            None => Err(anyhow!("template not exist:{name}")),
+        }
+    }
+
+    /// 所有模板的名称，按名称排序
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.templates.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// 替换模板中的变量，得到要绘制的元素
+    pub fn render(&self, params: &RenderTemplate) -> Result<Vec<Element>> {
+        let template = self.templates.get(&params.template)
+            .ok_or(anyhow!("template not exist:{}", params.template))?;
+        let elements = template.iter()
+            .map(|element| substitute(element, &params.vars))
+            .collect::<Result<Vec<Value>>>()?;
+        serde_json::from_value(Value::Array(elements))
+            .map_err(|err| anyhow!("template {} elements: {err}", params.template))
+    }
+}
+
+fn substitute(value: &Value, vars: &HashMap<String, Value>) -> Result<Value> {
+    Ok(match value {
+        Value::String(text) => substitute_text(text, vars)?,
+        Value::Array(items) => Value::Array(items.iter().map(|v| substitute(v, vars)).collect::<Result<_>>()?),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), substitute(v, vars)?)))
+                .collect::<Result<_>>()?,
+        ),
+        _ => value.clone(),
+    })
+}
+
+fn var<'a>(vars: &'a HashMap<String, Value>, name: &str) -> Result<&'a Value> {
+    vars.get(name.trim()).ok_or(anyhow!("模板变量未定义:{}", name.trim()))
+}
+
+fn substitute_text(text: &str, vars: &HashMap<String, Value>) -> Result<Value> {
+    // 整个字符串为一个占位符时保留变量的类型
+    if let Some(name) = text.strip_prefix("{{").and_then(|t| t.strip_suffix("}}")) {
+        if !name.contains("{{") && !name.contains("}}") {
+            return Ok(var(vars, name)?.clone());
+        }
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        match var(vars, &rest[start + 2..start + 2 + end])? {
+            Value::String(s) => out.push_str(s),
+            v => out.push_str(&v.to_string()),
+        }
+        rest = &rest[start + 2 + end + 2..];
+    }
+    out.push_str(rest);
+    Ok(Value::String(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let mut templates = Templates::default();
+        let elements: Vec<Value> = serde_json::from_str(r#"[
+            {"Text":{"x":"{{x}}","y":0,"text":"温度 {{ temp }}℃ {{unit","size":12,"color":"{{color}}"}},
+            {"Image":{"x":0,"y":20,"key":"icon_{{icon}}"}},
+            {"Sparkline":{"x":0,"y":40,"width":60,"height":20,"values":"{{history}}","color":"lime"}}
+        ]"#).unwrap();
+        templates.insert("home", elements).unwrap();
+        assert_eq!(templates.names(), vec!["home"]);
+
+        let params: RenderTemplate = serde_json::from_str(r#"{"template":"home","vars":{
+            "x":10,"temp":25.5,"color":"red","icon":"sun","history":[1,2,3]
+        }}"#).unwrap();
+        let elements = templates.render(&params).unwrap();
+        match &elements[0] {
+            Element::Text(text) => {
+                assert_eq!(text.x, 10);
+                assert_eq!(text.text, "温度 25.5℃ {{unit");
+            }
+            _ => panic!("expect text"),
+        }
+        match &elements[1] {
+            Element::Image(image) => assert_eq!(image.key.as_deref(), Some("icon_sun")),
+            _ => panic!("expect image"),
+        }
+        match &elements[2] {
+            Element::Sparkline(chart) => assert_eq!(chart.values, vec![1.0, 2.0, 3.0]),
+            _ => panic!("expect sparkline"),
+        }
+
+        // 缺少变量、类型错误
+        assert!(templates.render(&RenderTemplate { vars: HashMap::new(), ..params.clone() }).is_err());
+        let vars = serde_json::from_str(r#"{"x":"left","temp":1,"color":"red","icon":"a","history":[]}"#).unwrap();
+        assert!(templates.render(&RenderTemplate { vars, ..params }).is_err());
+
+        assert!(templates.insert("bad", vec![Value::Null]).is_err());
+        assert!(templates.set("home", None).is_ok());
+        assert!(templates.set("home", None).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use image::{Rgba, RgbImage};
+use serde::Deserialize;
+
+use crate::font::{text_size, TextFont};
+use crate::render::{blend_coverage, CSSColor, Text};
+use crate::scene::Bounds;
+use crate::text_layout::TextLine;
+use crate::text_transform::{draw_glyphs, fill_rect, Rotation};
+
+// 文字效果：描边、阴影、背景框，提高文字在图片上的可读性
+// 先把字形覆盖率画到缓冲区中，描边为覆盖率的膨胀，阴影为覆盖率的模糊
+
+/// 描边的最大宽度(像素)
+const MAX_STROKE_WIDTH: u32 = 8;
+/// 阴影的最大模糊半径(像素)
+const MAX_BLUR: u32 = 8;
+/// 背景默认的内边距(像素)
+const DEFAULT_PADDING: u32 = 2;
+/// 部分字形会超出字体的行高
+const GLYPH_OVERFLOW: i32 = 2;
+
+/// 文字阴影
+#[derive(Clone, Deserialize)]
+pub struct TextShadow {
+    /// 偏移(像素)，默认 [1, 1]
+    pub offset: Option<(i32, i32)>,
+    /// 颜色，默认黑色
+    pub color: Option<CSSColor>,
+    /// 模糊半径(像素)，默认 0
+    pub blur: Option<u32>,
+}
+
+impl TextShadow {
+    fn offset(&self) -> (i32, i32) {
+        self.offset.unwrap_or((1, 1))
+    }
+
+    /// 模糊分两次方框模糊，每次的半径
+    fn box_radius(&self) -> u32 {
+        self.blur.unwrap_or(0).min(MAX_BLUR).div_ceil(2)
+    }
+
+    /// 模糊后向外扩展的距离
+    fn spread(&self) -> u32 {
+        self.box_radius() * 2
+    }
+}
+
+impl Text {
+    /// 是否有描边、阴影或背景
+    pub fn has_effects(&self) -> bool {
+        self.stroke_color.is_some() || self.shadow.is_some() || self.background.is_some()
+    }
+
+    fn stroke_width(&self) -> u32 {
+        match self.stroke_color {
+            Some(_) => self.stroke_width.unwrap_or(1).min(MAX_STROKE_WIDTH),
+            None => 0,
+        }
+    }
+
+    fn padding(&self) -> u32 {
+        self.padding.unwrap_or(DEFAULT_PADDING)
+    }
+
+    /// 效果超出文字的距离(像素)
+    pub(crate) fn effect_margin(&self) -> i32 {
+        let stroke = self.stroke_width() as i32;
+        let shadow = self.shadow.as_ref().map_or(0, |shadow| {
+            let (dx, dy) = shadow.offset();
+            stroke + shadow.spread() as i32 + dx.abs().max(dy.abs())
+        });
+        let padding = if self.background.is_some() { self.padding() as i32 } else { 0 };
+        stroke.max(shadow).max(padding)
+    }
+}
+
+/// 排版后所有行的外接矩形
+pub(crate) fn text_bounds(font: &TextFont, size: f32, lines: &[TextLine]) -> Option<Bounds> {
+    lines
+        .iter()
+        .map(|line| {
+            let (w, h) = text_size(size, font, &line.text);
+            Bounds::new(line.x, line.y, w, h)
+        })
+        .reduce(|a, b| a.union(&b))
+}
+
+/// 字形覆盖率 0~1
+struct Coverage {
+    bounds: Bounds,
+    data: Vec<f32>,
+}
+
+impl Coverage {
+    fn new(bounds: Bounds) -> Self {
+        Self { bounds, data: vec![0.0; (bounds.width() * bounds.height()) as usize] }
+    }
+
+    /// 区域外为 0
+    fn get(&self, x: i32, y: i32) -> f32 {
+        let b = &self.bounds;
+        if x < b.left || x >= b.right || y < b.top || y >= b.bottom {
+            return 0.0;
+        }
+        self.data[((y - b.top) as u32 * b.width() + (x - b.left) as u32) as usize]
+    }
+
+    fn map(&self, f: impl Fn(i32, i32) -> f32) -> Self {
+        let b = self.bounds;
+        let data = (b.top..b.bottom).flat_map(|y| (b.left..b.right).map(move |x| (x, y))).map(|(x, y)| f(x, y)).collect();
+        Self { bounds: b, data }
+    }
+
+    /// 画上文字的字形，包括旋转
+    fn draw_text(&mut self, font: &TextFont, text: &Text, lines: &[TextLine]) {
+        let b = self.bounds;
+        let data = &mut self.data;
+        draw_glyphs(font, text, lines, &b, |x, y, gv| {
+            let v = &mut data[((y - b.top) as u32 * b.width() + (x - b.left) as u32) as usize];
+            *v = v.max(gv);
+        });
+    }
+
+    /// 膨胀 radius 像素，边缘保留抗锯齿
+    fn dilate(&self, radius: u32) -> Self {
+        if radius == 0 {
+            return self.map(|x, y| self.get(x, y));
+        }
+        let r = radius as i32;
+        let disk: Vec<(i32, i32, f32)> = (-r..=r)
+            .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+            .filter_map(|(dx, dy)| {
+                let weight = (radius as f32 + 1.0 - ((dx * dx + dy * dy) as f32).sqrt()).clamp(0.0, 1.0);
+                (weight > 0.0).then_some((dx, dy, weight))
+            })
+            .collect();
+        self.map(|x, y| disk.iter().fold(0f32, |v, &(dx, dy, w)| v.max(self.get(x + dx, y + dy) * w)))
+    }
+
+    /// 两次方框模糊，近似高斯模糊
+    fn blur(&self, radius: u32) -> Self {
+        let r = radius as i32;
+        let n = (2 * r + 1) as f32;
+        let mut out = self.map(|x, y| self.get(x, y));
+        for _ in 0..2 {
+            let h = out.map(|x, y| (-r..=r).map(|d| out.get(x + d, y)).sum::<f32>() / n);
+            out = h.map(|x, y| (-r..=r).map(|d| h.get(x, y + d)).sum::<f32>() / n);
+        }
+        out
+    }
+
+    /// 按覆盖率混合颜色，(dx, dy) 为绘制的偏移
+    fn blend(&self, canvas: &mut RgbImage, area: &Bounds, (dx, dy): (i32, i32), color: &CSSColor) {
+        let color = Rgba(color.rgba());
+        for y in area.top..area.bottom {
+            for x in area.left..area.right {
+                blend_coverage(canvas.get_pixel_mut(x as u32, y as u32), color, self.get(x - dx, y - dy));
+            }
+        }
+    }
+}
+
+/// 绘制带描边、阴影或背景的文字
+pub(crate) fn draw_text_effects(canvas: &mut RgbImage, font: &TextFont, text: &Text, lines: &[TextLine]) {
+    let Some(bounds) = text_bounds(font, text.size, lines) else {
+        return;
+    };
+    let screen = Bounds::new(0, 0, canvas.width(), canvas.height());
+
+    if let Some(background) = &text.background {
+        let color = Rgba(background.rgba());
+        fill_rect(text, &bounds.expand(text.padding() as i32), &screen, |x, y, v| {
+            blend_coverage(canvas.get_pixel_mut(x as u32, y as u32), color, v);
+        });
+    }
+
+    // 只计算影响屏幕内像素的部分，分条渲染时结果与全屏相同
+    let stroke = text.stroke_width();
+    let spread = stroke + text.shadow.as_ref().map_or(0, TextShadow::spread);
+    let reach = text.effect_margin().max(spread as i32);
+    let glyphs = bounds.expand(GLYPH_OVERFLOW);
+    let glyphs = Rotation::of(text).map_or(glyphs, |rotation| rotation.bounds(&glyphs));
+    let region = glyphs.expand(spread as i32).intersection(&screen.expand(reach));
+    if region.is_empty() {
+        return;
+    }
+    let mut coverage = Coverage::new(region);
+    coverage.draw_text(font, text, lines);
+    let outline = text.stroke_color.as_ref().map(|_| coverage.dilate(stroke));
+
+    let area = region.intersection(&screen);
+    if let Some(shadow) = &text.shadow {
+        let (dx, dy) = shadow.offset();
+        let shape = outline.as_ref().unwrap_or(&coverage).blur(shadow.box_radius());
+        let area = region.expand(dx.abs().max(dy.abs())).intersection(&screen);
+        let black = CSSColor(csscolorparser::Color::new(0.0, 0.0, 0.0, 1.0));
+        shape.blend(canvas, &area, (dx, dy), shadow.color.as_ref().unwrap_or(&black));
+    }
+    if let (Some(outline), Some(color)) = (&outline, &text.stroke_color) {
+        outline.blend(canvas, &area, (0, 0), color);
+    }
+    coverage.blend(canvas, &area, (0, 0), &text.color);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dilate_blur() {
+        let mut coverage = Coverage::new(Bounds::new(0, 0, 9, 9));
+        coverage.data[4 * 9 + 4] = 1.0;
+
+        let outline = coverage.dilate(2);
+        assert_eq!(outline.get(4, 2), 1.0);
+        assert_eq!(outline.get(6, 4), 1.0);
+        assert_eq!(outline.get(4, 1), 0.0);
+        // 对角方向距离 2.83，边缘部分覆盖
+        assert!(outline.get(6, 6) > 0.0 && outline.get(6, 6) < 0.5);
+
+        let blurred = coverage.blur(1);
+        let total: f32 = blurred.data.iter().sum();
+        assert!((total - 1.0).abs() < 1e-4);
+        assert!(blurred.get(4, 4) > blurred.get(5, 4));
+        assert_eq!(blurred.get(7, 4), 0.0);
+    }
+
+    #[test]
+    fn test_effect_margin() {
+        let color = CSSColor(csscolorparser::Color::new(1.0, 1.0, 1.0, 1.0));
+        let text = Text { stroke_color: Some(color.clone()), stroke_width: Some(2), ..Default::default() };
+        assert_eq!(text.effect_margin(), 2);
+        let shadow = TextShadow { offset: Some((3, -1)), color: None, blur: Some(3) };
+        let text = Text { shadow: Some(shadow), ..text };
+        assert_eq!(text.effect_margin(), 2 + 4 + 3);
+        assert!(text.has_effects());
+        assert!(!Text::default().has_effects());
+    }
+}
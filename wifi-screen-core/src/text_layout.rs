@@ -0,0 +1,436 @@
+use serde::Deserialize;
+
+use crate::font::{text_size, TextFont};
+use crate::render::Text;
+
+// 文字排版：对齐、自动换行（支持中日韩文字逐字断行）、行距、省略号
+
+const ELLIPSIS: &str = "…";
+
+/// 水平对齐
+/// 设置了 max_width 时在 [x, x + max_width] 范围内对齐，否则 x 为对齐锚点
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// 垂直对齐，y 为整段文字的顶部/中线/底部
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextVAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// 换行方式（仅在设置了 max_width 时生效）
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextWrap {
+    /// 不自动换行，只按 `\n` 分行
+    None,
+    /// 按单词换行，中日韩文字可在任意字之间断行
+    #[default]
+    Word,
+    /// 按字符换行
+    Char,
+}
+
+/// 排版后的一行文字，(x, y) 为该行左上角
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextLine {
+    pub x: i32,
+    pub y: i32,
+    pub text: String,
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x2E80..=0x9FFF     // 部首、日文假名、CJK统一汉字、CJK标点
+        | 0xAC00..=0xD7AF   // 韩文
+        | 0xF900..=0xFAFF   // CJK兼容汉字
+        | 0xFF00..=0xFFEF   // 全角字符
+        | 0x20000..=0x2FA1F // CJK扩展
+    )
+}
+
+/// 不能出现在行首的标点，换行时跟随前一个字
+fn is_closing_punctuation(c: char) -> bool {
+    "，。、！？；：”’）》」』】〉,.!?;:)]}%".contains(c)
+}
+
+/// 把一段文字拆成换行时不可分割的单元
+fn split_units(paragraph: &str, wrap: TextWrap) -> Vec<String> {
+    let mut units: Vec<String> = vec![];
+    let mut word = String::new();
+    for c in paragraph.chars() {
+        if is_closing_punctuation(c) {
+            if !word.is_empty() {
+                word.push(c);
+                continue;
+            }
+            if let Some(last) = units.last_mut() {
+                if !last.ends_with(char::is_whitespace) {
+                    last.push(c);
+                    continue;
+                }
+            }
+        }
+        if wrap == TextWrap::Char || c.is_whitespace() || is_cjk(c) {
+            if !word.is_empty() {
+                units.push(std::mem::take(&mut word));
+            }
+            units.push(c.to_string());
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        units.push(word);
+    }
+    units
+}
+
+fn measure(font: &TextFont, size: f32, text: &str) -> u32 {
+    if text.is_empty() {
+        0
+    } else {
+        text_size(size, font, text).0
+    }
+}
+
+/// 一行中同一样式的一段文字，Text 只有一种样式，RichText 的 span 为文字段的序号
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Fragment<T> {
+    pub span: T,
+    pub text: String,
+}
+
+/// 贪心换行，Text 和 RichText 共用，width 计算一行文字的宽度
+pub(crate) struct LineBreaker<T, W> {
+    width: W,
+    max_width: Option<u32>,
+    lines: Vec<Vec<Fragment<T>>>,
+    line: Vec<Fragment<T>>,
+}
+
+impl<T: Copy + PartialEq, W: Fn(&[Fragment<T>]) -> u32> LineBreaker<T, W> {
+    /// max_width 为 None 时只在 break_line 时换行
+    pub(crate) fn new(max_width: Option<u32>, width: W) -> Self {
+        Self { width, max_width, lines: vec![], line: vec![] }
+    }
+
+    /// 添加一段文字，放不下时换行
+    pub(crate) fn push(&mut self, span: T, paragraph: &str, wrap: TextWrap) {
+        for unit in split_units(paragraph, wrap) {
+            self.push_unit(span, &unit);
+        }
+    }
+
+    /// 追加文字后的当前行
+    fn appended(&self, span: T, text: &str) -> Vec<Fragment<T>> {
+        let mut line = self.line.clone();
+        match line.last_mut() {
+            _ if text.is_empty() => (),
+            Some(last) if last.span == span => last.text.push_str(text),
+            _ => line.push(Fragment { span, text: text.to_string() }),
+        }
+        line
+    }
+
+    fn fits(&self, line: &[Fragment<T>]) -> bool {
+        self.max_width.map_or(true, |max_width| (self.width)(line) <= max_width)
+    }
+
+    fn push_unit(&mut self, span: T, unit: &str) {
+        let candidate = self.appended(span, unit);
+        if self.fits(&candidate) {
+            self.line = candidate;
+            return;
+        }
+        // 当前行放不下，换行（行首空白忽略）
+        if self.line.iter().any(|f| !f.text.trim().is_empty()) {
+            self.break_line();
+        }
+        self.line.clear();
+        let unit = unit.trim_start();
+        let candidate = self.appended(span, unit);
+        if self.fits(&candidate) {
+            self.line = candidate;
+            return;
+        }
+        // 单词比一整行还长，按字符断开
+        for c in unit.chars() {
+            let c = c.to_string();
+            let mut candidate = self.appended(span, &c);
+            if !self.line.is_empty() && !self.fits(&candidate) {
+                self.break_line();
+                candidate = self.appended(span, &c);
+            }
+            self.line = candidate;
+        }
+    }
+
+    /// 换行，行尾空白去掉
+    pub(crate) fn break_line(&mut self) {
+        let mut line = std::mem::take(&mut self.line);
+        while let Some(last) = line.last_mut() {
+            let len = last.text.trim_end().len();
+            if len > 0 {
+                last.text.truncate(len);
+                break;
+            }
+            line.pop();
+        }
+        self.lines.push(line);
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<Vec<Fragment<T>>> {
+        self.break_line();
+        self.lines
+    }
+}
+
+/// 贪心换行，结果追加到 lines
+fn wrap_paragraph(font: &TextFont, size: f32, paragraph: &str, max_width: u32, wrap: TextWrap, lines: &mut Vec<String>) {
+    let mut breaker = LineBreaker::new(Some(max_width), |line: &[Fragment<()>]| {
+        measure(font, size, line.first().map_or("", |f| f.text.as_str()))
+    });
+    breaker.push((), paragraph, wrap);
+    lines.extend(breaker.finish().into_iter().map(|line| line.into_iter().map(|f| f.text).collect::<String>()));
+}
+
+/// 截断文字使其加上省略号后不超过 max_width
+fn ellipsize(font: &TextFont, size: f32, line: &str, max_width: u32) -> String {
+    let mut chars: Vec<char> = line.trim_end().chars().collect();
+    loop {
+        let candidate = format!("{}{ELLIPSIS}", chars.iter().collect::<String>());
+        if chars.is_empty() || measure(font, size, &candidate) <= max_width {
+            return candidate;
+        }
+        chars.pop();
+        while chars.last().is_some_and(|c| c.is_whitespace()) {
+            chars.pop();
+        }
+    }
+}
+
+/// 竖排：每个字单独一行，从上到下、从右到左排列，字在列中水平居中
+/// 对齐方式作用于整个文字块，line_height 为列距
+fn layout_vertical(font: &TextFont, text: &Text) -> Vec<TextLine> {
+    let size = text.size;
+    let columns: Vec<Vec<char>> = text
+        .text
+        .split('\n')
+        .map(|column| column.strip_suffix('\r').unwrap_or(column).chars().collect())
+        .collect();
+    let char_height = font.height(size).ceil() as i32;
+    let column_step = text.line_height.map_or(char_height, |h| h.round() as i32);
+    let width = column_step * (columns.len() as i32 - 1) + char_height;
+    let height = columns.iter().map(|column| column.len() as i32).max().unwrap_or(0) * char_height;
+    let left = match text.align {
+        TextAlign::Left => text.x,
+        TextAlign::Center => text.x - width / 2,
+        TextAlign::Right => text.x - width,
+    };
+    let top = match text.valign {
+        TextVAlign::Top => text.y,
+        TextVAlign::Middle => text.y - height / 2,
+        TextVAlign::Bottom => text.y - height,
+    };
+
+    let mut lines = vec![];
+    for (i, column) in columns.iter().enumerate() {
+        let column_left = left + column_step * (columns.len() - 1 - i) as i32;
+        for (j, c) in column.iter().enumerate() {
+            if c.is_whitespace() {
+                continue;
+            }
+            let text = c.to_string();
+            let char_width = measure(font, size, &text) as i32;
+            lines.push(TextLine { x: column_left + (char_height - char_width) / 2, y: top + j as i32 * char_height, text });
+        }
+    }
+    lines
+}
+
+/// 对 Text 元素排版，返回每一行的位置和内容
+pub fn layout_text(font: &TextFont, text: &Text) -> Vec<TextLine> {
+    if text.vertical {
+        return layout_vertical(font, text);
+    }
+    let size = text.size;
+    let wrap = text.wrap.unwrap_or_default();
+
+    let mut lines = vec![];
+    for paragraph in text.text.split('\n') {
+        let paragraph = paragraph.strip_suffix('\r').unwrap_or(paragraph);
+        match text.max_width {
+            Some(max_width) if wrap != TextWrap::None => {
+                wrap_paragraph(font, size, paragraph, max_width, wrap, &mut lines)
+            }
+            _ => lines.push(paragraph.to_string()),
+        }
+    }
+
+    // 超过最大行数的部分截掉
+    let mut truncated = false;
+    if let Some(max_lines) = text.max_lines {
+        let max_lines = (max_lines as usize).max(1);
+        if lines.len() > max_lines {
+            lines.truncate(max_lines);
+            truncated = true;
+        }
+    }
+
+    if text.ellipsis {
+        let last = lines.len() - 1;
+        for (i, line) in lines.iter_mut().enumerate() {
+            let force = truncated && i == last;
+            match text.max_width {
+                Some(max_width) if force || measure(font, size, line) > max_width => {
+                    *line = ellipsize(font, size, line, max_width);
+                }
+                None if force => line.push_str(ELLIPSIS),
+                _ => (),
+            }
+        }
+    }
+
+    let font_height = font.height(size).ceil();
+    let line_height = text.line_height.unwrap_or(font_height);
+    let block_height = line_height * (lines.len() - 1) as f32 + font_height;
+    let top = match text.valign {
+        TextVAlign::Top => text.y as f32,
+        TextVAlign::Middle => text.y as f32 - block_height / 2.,
+        TextVAlign::Bottom => text.y as f32 - block_height,
+    };
+
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let line_width = measure(font, size, &line) as i32;
+            let x = match (text.align, text.max_width) {
+                (TextAlign::Left, _) => text.x,
+                (TextAlign::Center, Some(max_width)) => text.x + (max_width as i32 - line_width) / 2,
+                (TextAlign::Right, Some(max_width)) => text.x + max_width as i32 - line_width,
+                (TextAlign::Center, None) => text.x - line_width / 2,
+                (TextAlign::Right, None) => text.x - line_width,
+            };
+            // 向下取整而不是 round，y 为负数时（分条渲染）结果也相同
+            let y = (top + line_height * i as f32 + 0.5).floor() as i32;
+            TextLine { x, y, text: line }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::CSSColor;
+    use ab_glyph::FontRef;
+
+    fn font() -> TextFont<'static> {
+        let builtin = FontRef::try_from_slice(include_bytes!("../../VonwaonBitmap-12pxLite.otf")).unwrap();
+        TextFont::builtin(Box::leak(Box::new(builtin)))
+    }
+
+    fn text(s: &str) -> Text {
+        Text {
+            x: 10,
+            y: 20,
+            text: s.to_string(),
+            size: 12.,
+            color: CSSColor(csscolorparser::Color::new(1., 1., 1., 1.)),
+            ..Default::default()
+        }
+    }
+
+    fn lines(layout: &[TextLine]) -> Vec<&str> {
+        layout.iter().map(|l| l.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_single_line_unchanged() {
+        let layout = layout_text(&font(), &text("Hello"));
+        assert_eq!(layout, vec![TextLine { x: 10, y: 20, text: "Hello".to_string() }]);
+    }
+
+    #[test]
+    fn test_newline() {
+        let layout = layout_text(&font(), &text("a\r\nb\n\nc"));
+        assert_eq!(lines(&layout), vec!["a", "b", "", "c"]);
+        assert_eq!(layout[1].y - layout[0].y, 12);
+    }
+
+    #[test]
+    fn test_word_wrap() {
+        let font = font();
+        let width = measure(&font, 12., "hello world");
+        let t = Text { max_width: Some(width), ..text("hello world hello world") };
+        assert_eq!(lines(&layout_text(&font, &t)), vec!["hello world", "hello world"]);
+    }
+
+    #[test]
+    fn test_cjk_wrap_keeps_punctuation() {
+        let font = font();
+        let width = measure(&font, 12., "你好");
+        let t = Text { max_width: Some(width), ..text("你好，世界") };
+        assert_eq!(lines(&layout_text(&font, &t)), vec!["你", "好，", "世界"]);
+    }
+
+    #[test]
+    fn test_long_word_breaks_by_char() {
+        let font = font();
+        let width = measure(&font, 12., "abcd");
+        let t = Text { max_width: Some(width), ..text("abcdefgh") };
+        assert_eq!(lines(&layout_text(&font, &t)), vec!["abcd", "efgh"]);
+    }
+
+    #[test]
+    fn test_ellipsis() {
+        let font = font();
+        let width = measure(&font, 12., "abcdef");
+        let t = Text { max_width: Some(width), wrap: Some(TextWrap::None), ellipsis: true, ..text("abcdefghij") };
+        let layout = layout_text(&font, &t);
+        assert!(layout[0].text.ends_with(ELLIPSIS));
+        assert!(measure(&font, 12., &layout[0].text) <= width);
+
+        let t = Text { max_lines: Some(1), ellipsis: true, ..text("one\ntwo") };
+        assert_eq!(lines(&layout_text(&font, &t)), vec!["one…"]);
+    }
+
+    #[test]
+    fn test_align() {
+        let font = font();
+        let w = measure(&font, 12., "ab") as i32;
+        let t = Text { align: TextAlign::Right, max_width: Some(100), ..text("ab") };
+        assert_eq!(layout_text(&font, &t)[0].x, 10 + 100 - w);
+        let t = Text { align: TextAlign::Center, ..text("ab") };
+        assert_eq!(layout_text(&font, &t)[0].x, 10 - w / 2);
+        let t = Text { valign: TextVAlign::Bottom, line_height: Some(20.), ..text("a\nb") };
+        assert_eq!(layout_text(&font, &t)[0].y, 20 - 20 - 12);
+    }
+
+    #[test]
+    fn test_vertical() {
+        let font = font();
+        let t = Text { vertical: true, ..text("你好\n世 界") };
+        let layout = layout_text(&font, &t);
+        assert_eq!(lines(&layout), vec!["你", "好", "世", "界"]);
+        // 第一列在最右边，空格占一个字的位置
+        assert_eq!((layout[0].x, layout[0].y), (22, 20));
+        assert_eq!((layout[1].x, layout[1].y), (22, 32));
+        assert_eq!((layout[2].x, layout[3].y), (10, 44));
+
+        let t = Text { align: TextAlign::Right, valign: TextVAlign::Bottom, line_height: Some(16.), ..t };
+        let layout = layout_text(&font, &t);
+        assert_eq!((layout[2].x, layout[3].y), (10 - 28, 20 - 12));
+    }
+}
@@ -0,0 +1,193 @@
+use ab_glyph::{point, OutlineCurve, Point};
+use ab_glyph_rasterizer::Rasterizer;
+use image::{Rgba, RgbImage};
+
+use crate::font::{layout_glyphs, layout_outlines, TextFont};
+use crate::render::{blend_coverage, Text};
+use crate::scene::Bounds;
+use crate::text_layout::TextLine;
+
+// 文字旋转：字形轮廓绕旋转中心旋转后再光栅化（不是旋转位图），任意角度的文字都保持清晰
+// 坐标都相对于旋转中心计算，分条渲染时结果与全屏相同
+
+/// 文字的旋转
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Rotation {
+    anchor: (i32, i32),
+    sin: f32,
+    cos: f32,
+}
+
+impl Rotation {
+    /// 没有旋转时返回 None
+    pub(crate) fn of(text: &Text) -> Option<Self> {
+        let degrees = text.rotation? % 360.0;
+        if degrees == 0.0 {
+            return None;
+        }
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Some(Self { anchor: text.anchor.unwrap_or((text.x, text.y)), sin, cos })
+    }
+
+    /// 旋转相对于旋转中心的坐标
+    fn rotate(&self, p: Point) -> Point {
+        point(p.x * self.cos - p.y * self.sin, p.x * self.sin + p.y * self.cos)
+    }
+
+    /// 旋转画布坐标，结果相对于旋转中心
+    fn rotate_from(&self, (x, y): (i32, i32), p: Point) -> Point {
+        self.rotate(point((x - self.anchor.0) as f32 + p.x, (y - self.anchor.1) as f32 + p.y))
+    }
+
+    /// 旋转后的外接矩形
+    pub(crate) fn bounds(&self, b: &Bounds) -> Bounds {
+        let corners = [(b.left, b.top), (b.right, b.top), (b.left, b.bottom), (b.right, b.bottom)]
+            .map(|corner| self.rotate_from(corner, point(0.0, 0.0)));
+        self.pixel_bounds(&corners)
+    }
+
+    /// 包含所有点（相对于旋转中心）的像素区域
+    fn pixel_bounds(&self, points: &[Point]) -> Bounds {
+        let (min, max) = points.iter().fold(
+            (point(f32::MAX, f32::MAX), point(f32::MIN, f32::MIN)),
+            |(min, max), p| (point(min.x.min(p.x), min.y.min(p.y)), point(max.x.max(p.x), max.y.max(p.y))),
+        );
+        // 忽略浮点误差，旋转 90 度时不多出一个像素
+        const EPSILON: f32 = 1e-3;
+        let (ax, ay) = self.anchor;
+        Bounds {
+            left: ax + (min.x + EPSILON).floor() as i32,
+            top: ay + (min.y + EPSILON).floor() as i32,
+            right: ax + (max.x - EPSILON).ceil() as i32,
+            bottom: ay + (max.y - EPSILON).ceil() as i32,
+        }
+    }
+
+    /// 光栅化相对于旋转中心的轮廓，只对 clip 内的像素调用 f(x, y, 覆盖率)
+    fn rasterize(&self, curves: &[OutlineCurve], clip: &Bounds, f: &mut impl FnMut(i32, i32, f32)) {
+        let points: Vec<Point> = curves
+            .iter()
+            .flat_map(|curve| match curve {
+                OutlineCurve::Line(p0, p1) => vec![*p0, *p1],
+                OutlineCurve::Quad(p0, p1, p2) => vec![*p0, *p1, *p2],
+                OutlineCurve::Cubic(p0, p1, p2, p3) => vec![*p0, *p1, *p2, *p3],
+            })
+            .collect();
+        if points.is_empty() {
+            return;
+        }
+        // 曲线在控制点组成的凸包内
+        let area = self.pixel_bounds(&points);
+        if !area.intersects(clip) {
+            return;
+        }
+        let origin = point((area.left - self.anchor.0) as f32, (area.top - self.anchor.1) as f32);
+        let p = |p: &Point| point(p.x - origin.x, p.y - origin.y);
+        let mut rasterizer = Rasterizer::new(area.width() as usize, area.height() as usize);
+        for curve in curves {
+            match curve {
+                OutlineCurve::Line(p0, p1) => rasterizer.draw_line(p(p0), p(p1)),
+                OutlineCurve::Quad(p0, p1, p2) => rasterizer.draw_quad(p(p0), p(p1), p(p2)),
+                OutlineCurve::Cubic(p0, p1, p2, p3) => rasterizer.draw_cubic(p(p0), p(p1), p(p2), p(p3)),
+            }
+        }
+        rasterizer.for_each_pixel_2d(|x, y, v| {
+            let (x, y) = (area.left + x as i32, area.top + y as i32);
+            if v > 0.0 && x >= clip.left && x < clip.right && y >= clip.top && y < clip.bottom {
+                f(x, y, v.min(1.0));
+            }
+        });
+    }
+}
+
+/// 逐个字计算覆盖率，只对 clip 内的像素调用 f(x, y, 覆盖率)，一个像素可能被多个字覆盖
+pub(crate) fn draw_glyphs(font: &TextFont, text: &Text, lines: &[TextLine], clip: &Bounds, mut f: impl FnMut(i32, i32, f32)) {
+    let Some(rotation) = Rotation::of(text) else {
+        // 与 draw_text 相同的字形位置
+        for line in lines {
+            layout_glyphs(text.size, font, &line.text, |g, bb| {
+                let x_shift = line.x + bb.min.x.round() as i32;
+                let y_shift = line.y + bb.min.y.round() as i32;
+                g.draw(|gx, gy, gv| {
+                    let (x, y) = (gx as i32 + x_shift, gy as i32 + y_shift);
+                    if x >= clip.left && x < clip.right && y >= clip.top && y < clip.bottom {
+                        f(x, y, gv.clamp(0.0, 1.0));
+                    }
+                });
+            });
+        }
+        return;
+    };
+    for line in lines {
+        layout_outlines(text.size, font, &line.text, |curves| {
+            let rotate = |p: &Point| rotation.rotate_from((line.x, line.y), *p);
+            let curves: Vec<OutlineCurve> = curves
+                .iter()
+                .map(|curve| match curve {
+                    OutlineCurve::Line(p0, p1) => OutlineCurve::Line(rotate(p0), rotate(p1)),
+                    OutlineCurve::Quad(p0, p1, p2) => OutlineCurve::Quad(rotate(p0), rotate(p1), rotate(p2)),
+                    OutlineCurve::Cubic(p0, p1, p2, p3) => OutlineCurve::Cubic(rotate(p0), rotate(p1), rotate(p2), rotate(p3)),
+                })
+                .collect();
+            rotation.rasterize(&curves, clip, &mut f);
+        });
+    }
+}
+
+/// 填充文字的背景矩形，旋转时矩形也一起旋转
+pub(crate) fn fill_rect(text: &Text, rect: &Bounds, clip: &Bounds, mut f: impl FnMut(i32, i32, f32)) {
+    let Some(rotation) = Rotation::of(text) else {
+        let area = rect.intersection(clip);
+        for y in area.top..area.bottom {
+            for x in area.left..area.right {
+                f(x, y, 1.0);
+            }
+        }
+        return;
+    };
+    let corners = [(rect.left, rect.top), (rect.right, rect.top), (rect.right, rect.bottom), (rect.left, rect.bottom)]
+        .map(|corner| rotation.rotate_from(corner, point(0.0, 0.0)));
+    let edges: Vec<OutlineCurve> = (0..4).map(|i| OutlineCurve::Line(corners[i], corners[(i + 1) % 4])).collect();
+    rotation.rasterize(&edges, clip, &mut f);
+}
+
+/// 绘制旋转的文字
+pub(crate) fn draw_rotated_text(canvas: &mut RgbImage, font: &TextFont, text: &Text, lines: &[TextLine]) {
+    let screen = Bounds::new(0, 0, canvas.width(), canvas.height());
+    let color = Rgba(text.color.rgba());
+    draw_glyphs(font, text, lines, &screen, |x, y, v| {
+        blend_coverage(canvas.get_pixel_mut(x as u32, y as u32), color, v);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation() {
+        let text = Text { x: 10, y: 20, rotation: Some(90.0), ..Default::default() };
+        let rotation = Rotation::of(&text).unwrap();
+        // 顺时针旋转 90 度：右侧转到下方
+        let p = rotation.rotate_from((20, 20), point(0.0, 0.0));
+        assert!(p.x.abs() < 1e-4 && (p.y - 10.0).abs() < 1e-4);
+        assert_eq!(rotation.bounds(&Bounds::new(10, 20, 30, 10)), Bounds { left: 0, top: 20, right: 10, bottom: 50 });
+
+        assert!(Rotation::of(&Text { rotation: Some(360.0), ..text.clone() }).is_none());
+        let text = Text { anchor: Some((0, 0)), ..text };
+        assert_eq!(Rotation::of(&text).unwrap().anchor, (0, 0));
+    }
+
+    #[test]
+    fn test_rasterize_rect() {
+        let text = Text { x: 8, y: 8, rotation: Some(45.0), ..Default::default() };
+        let mut covered = 0.0;
+        let clip = Bounds::new(0, 0, 32, 32);
+        fill_rect(&text, &Bounds::new(4, 4, 8, 8), &clip, |x, y, v| {
+            assert!(clip.left <= x && x < clip.right && clip.top <= y && y < clip.bottom);
+            covered += v;
+        });
+        // 旋转后面积不变
+        assert!((covered - 64.0).abs() < 0.5);
+    }
+}
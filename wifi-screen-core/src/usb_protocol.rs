@@ -0,0 +1,333 @@
+use std::time::Instant;
+
+// USB 串口传图协议解析（与平台无关）
+// 固件的 usb_reader 负责读串口和绘制，这里只负责从字节流中切分出命令和图像帧
+
+// protocol markers (match example)
+pub const IMAGE_AA: u64 = 7596835243154170209; // "image_aa"
+pub const IMAGE_BB: u64 = 7596835243154170466; // "image_bb"
+pub const BOOT_USB: u64 = 7093010483740242786;
+pub const READ_INF: u64 = 0x52656164496e666f; // "ReadInfo"
+pub const SPEED_AA_BYTES: [u8; 8] = *b"SPDTEST1";
+pub const SPEED_BB_BYTES: [u8; 8] = *b"SPDEND!!";
+// 截图命令，设备回复一行 "SCREENSHOT;宽;高;base64编码的PNG\n"
+pub const SCREENSHOT_BYTES: [u8; 8] = *b"ScrnShot";
+
+// ============ 安全限制 ============
+// 图像接收缓冲区最大大小（防止内存溢出）
+// 对于 320x240 RGB565 图像，压缩后约 50-150KB，设置为 512KB 足够
+pub const MAX_IMAGE_BUF_SIZE: usize = 512 * 1024;
+// 帧接收超时时间（毫秒），超时后重置接收状态
+pub const FRAME_RECEIVE_TIMEOUT_MS: u128 = 3000;
+
+/// 解析出的命令/数据帧
+pub enum UsbEvent {
+    /// 完整的图像帧，rgb565 为解压后的大端序 RGB565 数据
+    Image {
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        compressed_len: usize,
+        rgb565: Vec<u8>,
+    },
+    /// ReadInfo 设备信息查询
+    ReadInfo,
+    /// BOOT_USB 命令
+    Boot,
+    /// 截图命令
+    Screenshot,
+    /// 测速结束 (接收字节数, 耗时ms)
+    SpeedResult { bytes: usize, ms: u128 },
+    /// 帧解析错误（不含 "ERROR:" 前缀）
+    Error(String),
+}
+
+/// 截图命令的回复
+pub fn screenshot_response(width: u16, height: u16, png: &[u8]) -> String {
+    format!("SCREENSHOT;{};{};{}\n", width, height, data_encoding::BASE64.encode(png))
+}
+
+// small helper: find the first occurrence of `needle` in `hay`
+pub fn find_subslice(hay: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    hay.windows(needle.len()).position(|w| w == needle)
+}
+
+/// USB 串口字节流解析器
+pub struct UsbFrameParser {
+    buf: Vec<u8>,
+    receiving: bool,
+    image_buf: Vec<u8>,
+    speedbin_active: bool,
+    speedbin_received: usize,
+    speedbin_start: Option<Instant>,
+    image_width: u16,
+    image_height: u16,
+    image_x: u16,
+    image_y: u16,
+    // 帧接收开始时间（用于超时检测）
+    frame_start_time: Option<Instant>,
+}
+
+impl Default for UsbFrameParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UsbFrameParser {
+    pub fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            receiving: false,
+            image_buf: Vec::new(),
+            speedbin_active: false,
+            speedbin_received: 0,
+            speedbin_start: None,
+            image_width: 0,
+            image_height: 0,
+            image_x: 0,
+            image_y: 0,
+            frame_start_time: None,
+        }
+    }
+
+    /// 检查帧接收超时，超时后重置接收状态，返回是否发生了超时
+    /// 在串口无数据时调用
+    pub fn check_timeout(&mut self) -> bool {
+        if !self.receiving {
+            return false;
+        }
+        match self.frame_start_time {
+            Some(start) if start.elapsed().as_millis() > FRAME_RECEIVE_TIMEOUT_MS => {
+                log::warn!("[USB] Frame receive timeout, resetting state. buf_size={}", self.image_buf.len());
+                self.receiving = false;
+                self.image_buf.clear();
+                self.buf.clear();
+                self.frame_start_time = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 追加读到的数据，并依次回调解析出的命令/图像帧
+    pub fn feed(&mut self, data: &[u8], mut on_event: impl FnMut(UsbEvent)) {
+        let aa_bytes = IMAGE_AA.to_be_bytes();
+        let bb_bytes = IMAGE_BB.to_be_bytes();
+        let boot_bytes = BOOT_USB.to_be_bytes();
+        let readinf_bytes = READ_INF.to_be_bytes();
+        let readinf_ascii = b"ReadInfo";
+        let speed_aa = SPEED_AA_BYTES;
+        let speed_bb = SPEED_BB_BYTES;
+
+        self.buf.extend_from_slice(data);
+
+        loop {
+            if self.speedbin_active {
+                if !self.buf.is_empty() {
+                    if let Some(pos) = find_subslice(&self.buf, &speed_bb) {
+                        self.speedbin_received = self.speedbin_received.saturating_add(pos);
+                        self.buf.drain(..pos + speed_bb.len());
+                        let ms = self.speedbin_start.take().map(|start| start.elapsed().as_millis()).unwrap_or(0);
+                        on_event(UsbEvent::SpeedResult { bytes: self.speedbin_received, ms });
+                        self.speedbin_active = false;
+                        self.speedbin_received = 0;
+                        continue;
+                    } else {
+                        let keep = speed_bb.len().saturating_sub(1);
+                        if self.buf.len() > keep {
+                            let take = self.buf.len() - keep;
+                            self.buf.drain(..take);
+                            self.speedbin_received = self.speedbin_received.saturating_add(take);
+                        }
+                    }
+                }
+                if self.speedbin_active { break; }
+            }
+
+            if self.receiving {
+                self.image_buf.extend_from_slice(&self.buf);
+                self.buf.clear();
+
+                // 检查缓冲区大小限制
+                if self.image_buf.len() > MAX_IMAGE_BUF_SIZE {
+                    log::warn!("[USB] Image buffer overflow ({}), resetting", self.image_buf.len());
+                    self.receiving = false;
+                    self.image_buf.clear();
+                    self.frame_start_time = None;
+                    continue;
+                }
+
+                if let Some(pos) = find_subslice(&self.image_buf, &bb_bytes) {
+                    // 帧接收完成，清除超时计时器
+                    self.frame_start_time = None;
+                    self.receiving = false;
+
+                    let compressed_len = pos;
+                    // 直接使用切片解压，避免复制压缩数据，节省约150KB内存
+                    let decompressed = lz4_flex::decompress_size_prepended(&self.image_buf[..compressed_len]);
+
+                    // 先保存remainder再清理image_buf
+                    let remainder_start = pos + bb_bytes.len();
+                    if remainder_start < self.image_buf.len() {
+                        self.buf.extend_from_slice(&self.image_buf[remainder_start..]);
+                    }
+                    self.image_buf.clear();
+
+                    match decompressed {
+                        Ok(rgb565) => {
+                            let expected = self.image_width as usize * self.image_height as usize * 2;
+                            if rgb565.len() != expected {
+                                on_event(UsbEvent::Error(format!("SIZE_MISMATCH;decompressed={};expected={}", rgb565.len(), expected)));
+                            } else {
+                                on_event(UsbEvent::Image {
+                                    x: self.image_x,
+                                    y: self.image_y,
+                                    width: self.image_width,
+                                    height: self.image_height,
+                                    compressed_len,
+                                    rgb565,
+                                });
+                            }
+                        }
+                        Err(e) => {
+                            on_event(UsbEvent::Error(format!("LZ4_FAIL;error={:?}", e)));
+                        }
+                    }
+                    continue;
+                }
+                break;
+            } else {
+                if !self.speedbin_active {
+                    if let Some(pos) = find_subslice(&self.buf, &speed_aa) {
+                        self.buf.drain(..pos + speed_aa.len());
+                        self.speedbin_active = true;
+                        self.speedbin_received = 0;
+                        self.speedbin_start = Some(Instant::now());
+                        continue;
+                    }
+                }
+                if let Some(pos) = find_subslice(&self.buf, &aa_bytes) {
+                    if self.buf.len() < pos + 16 { break; }
+                    let buf = &self.buf;
+                    self.image_width = u16::from_be_bytes([buf[pos + 8], buf[pos + 9]]);
+                    self.image_height = u16::from_be_bytes([buf[pos + 10], buf[pos + 11]]);
+                    self.image_x = u16::from_be_bytes([buf[pos + 12], buf[pos + 13]]);
+                    self.image_y = u16::from_be_bytes([buf[pos + 14], buf[pos + 15]]);
+                    self.buf.drain(..pos + 16);
+                    self.receiving = true;
+                    self.image_buf.clear();
+                    // 记录帧接收开始时间
+                    self.frame_start_time = Some(Instant::now());
+                    continue;
+                }
+                let pos_bin = find_subslice(&self.buf, &readinf_bytes);
+                let pos_ascii = find_subslice(&self.buf, readinf_ascii);
+                if pos_bin.is_some() || pos_ascii.is_some() {
+                    let pos = match (pos_bin, pos_ascii) {
+                        (Some(p), Some(q)) => p.min(q),
+                        (Some(p), None) => p,
+                        (None, Some(q)) => q,
+                        _ => unreachable!(),
+                    };
+                    let len = if pos + readinf_bytes.len() <= self.buf.len()
+                        && self.buf[pos..pos + readinf_bytes.len()] == readinf_bytes {
+                        readinf_bytes.len()
+                    } else {
+                        readinf_ascii.len()
+                    };
+                    self.buf.drain(..pos + len);
+                    on_event(UsbEvent::ReadInfo);
+                    continue;
+                }
+                if let Some(pos) = find_subslice(&self.buf, &boot_bytes) {
+                    self.buf.drain(..pos + boot_bytes.len());
+                    on_event(UsbEvent::Boot);
+                    continue;
+                }
+                if let Some(pos) = find_subslice(&self.buf, &SCREENSHOT_BYTES) {
+                    self.buf.drain(..pos + SCREENSHOT_BYTES.len());
+                    on_event(UsbEvent::Screenshot);
+                    continue;
+                }
+                if let Some(nlpos) = self.buf.iter().position(|&b| b == b'\n') {
+                    self.buf.drain(..=nlpos);
+                    continue;
+                }
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(x: u16, y: u16, w: u16, h: u16, rgb565: &[u8]) -> Vec<u8> {
+        let mut data = IMAGE_AA.to_be_bytes().to_vec();
+        for v in [w, h, x, y] {
+            data.extend_from_slice(&v.to_be_bytes());
+        }
+        data.extend_from_slice(&lz4_flex::compress_prepend_size(rgb565));
+        data.extend_from_slice(&IMAGE_BB.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_image_frame_split_across_reads() {
+        let pixels: Vec<u8> = (0..2 * 3 * 2).map(|i| i as u8).collect();
+        let data = frame(5, 6, 2, 3, &pixels);
+        let mut parser = UsbFrameParser::new();
+        let mut images = vec![];
+        for chunk in data.chunks(7) {
+            parser.feed(chunk, |ev| {
+                if let UsbEvent::Image { x, y, width, height, rgb565, .. } = ev {
+                    images.push((x, y, width, height, rgb565));
+                }
+            });
+        }
+        assert_eq!(images, vec![(5, 6, 2, 3, pixels)]);
+    }
+
+    #[test]
+    fn test_commands() {
+        let mut speed = SPEED_AA_BYTES.to_vec();
+        speed.extend_from_slice(&[0u8; 100]);
+        speed.extend_from_slice(&SPEED_BB_BYTES);
+        let reads = [b"ReadInfo\n".to_vec(), BOOT_USB.to_be_bytes().to_vec(), speed, b"ScrnShot\n".to_vec()];
+        let mut parser = UsbFrameParser::new();
+        let mut events = vec![];
+        for data in reads {
+            parser.feed(&data, |ev| {
+                events.push(match ev {
+                    UsbEvent::ReadInfo => "info".to_string(),
+                    UsbEvent::Boot => "boot".to_string(),
+                    UsbEvent::Screenshot => "screenshot".to_string(),
+                    UsbEvent::SpeedResult { bytes, .. } => format!("speed:{bytes}"),
+                    UsbEvent::Image { .. } => "image".to_string(),
+                    UsbEvent::Error(e) => e,
+                })
+            });
+        }
+        assert_eq!(events, vec!["info", "boot", "speed:100", "screenshot"]);
+    }
+
+    #[test]
+    fn test_size_mismatch() {
+        let data = frame(0, 0, 4, 4, &[0u8; 8]);
+        let mut parser = UsbFrameParser::new();
+        let mut errors = vec![];
+        parser.feed(&data, |ev| {
+            if let UsbEvent::Error(e) = ev {
+                errors.push(e);
+            }
+        });
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("SIZE_MISMATCH"));
+    }
+}
@@ -0,0 +1,512 @@
+// 画布渲染金样测试：每种 Element 渲染结果与 tests/golden/*.png 逐像素比较
+// 修改了渲染逻辑需要更新金样时运行: UPDATE_GOLDEN=1 cargo test
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use ab_glyph::{FontRef, FontVec};
+use data_encoding::BASE64;
+use image::{Rgb, RgbImage, Rgba, RgbaImage};
+use wifi_screen_core::font::load_font;
+use wifi_screen_core::render::{decode_jpg_to_rgb, render_elements, render_elements_banded, Element, ImageCache};
+use wifi_screen_core::rgb565::Rgb565Image;
+use wifi_screen_core::scene::Bounds;
+use wifi_screen_core::splash::{splash_elements, SplashLine};
+use wifi_screen_core::sprite::SpriteSheet;
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 64;
+
+fn font() -> FontRef<'static> {
+    FontRef::try_from_slice(include_bytes!("../../VonwaonBitmap-12pxLite.otf")).unwrap()
+}
+
+/// 16x16 半透明渐变测试图
+fn test_rgba_image() -> RgbaImage {
+    RgbaImage::from_fn(16, 16, |x, y| Rgba([x as u8 * 16, y as u8 * 16, 200, 128 + x as u8 * 8]))
+}
+
+fn png_base64(image: &RgbaImage) -> String {
+    let mut out = std::io::Cursor::new(vec![]);
+    image.write_to(&mut out, image::ImageFormat::Png).unwrap();
+    BASE64.encode(out.get_ref())
+}
+
+fn image_cache() -> HashMap<String, ImageCache> {
+    let mut cache = HashMap::new();
+    cache.insert("rgba".to_string(), ImageCache::RgbaImage(Box::new(test_rgba_image())));
+    cache.insert(
+        "rgb".to_string(),
+        ImageCache::RgbImage(Box::new(RgbImage::from_fn(16, 16, |x, y| Rgb([255 - x as u8 * 16, y as u8 * 16, 0])))),
+    );
+    // RGB565 缓存：半透明(8位透明通道)、镂空(1位透明通道)
+    cache.insert("rgb565".to_string(), ImageCache::Rgb565(Box::new(Rgb565Image::from_rgba(&test_rgba_image()))));
+    let mask = RgbaImage::from_fn(16, 16, |x, y| Rgba([0, 255, 128, if (x + y) % 4 < 2 { 255 } else { 0 }]));
+    cache.insert("rgb565_mask".to_string(), ImageCache::Rgb565(Box::new(Rgb565Image::from_rgba(&mask))));
+    // 4x2 个 8x8 的精灵图，每帧颜色不同，中间镂空
+    let sprites = RgbaImage::from_fn(32, 16, |x, y| {
+        let index = (y / 8 * 4 + x / 8) as u8;
+        let hole = (3..5).contains(&(x % 8)) && (3..5).contains(&(y % 8));
+        Rgba([index * 32, 255 - index * 32, 128, if hole { 0 } else { 255 }])
+    });
+    let sheet: SpriteSheet = serde_json::from_str(r#"{"cell":[8,8],"frames":{"wide":[8,0,16,8]}}"#).unwrap();
+    cache.insert(
+        "sprites".to_string(),
+        ImageCache::Sheet(Box::new(ImageCache::Rgb565(Box::new(Rgb565Image::from_rgba(&sprites)))), Box::new(sheet)),
+    );
+    cache
+}
+
+/// 上传的字体，Tuffy 只包含拉丁字母，中文使用内置字体
+fn font_cache() -> HashMap<String, FontVec> {
+    let mut cache = HashMap::new();
+    cache.insert("tuffy".to_string(), load_font(include_bytes!("fonts/Tuffy.ttf").to_vec()).unwrap());
+    cache
+}
+
+fn render(elements: &[Element]) -> RgbImage {
+    let mut canvas = RgbImage::from_pixel(WIDTH, HEIGHT, Rgb([20, 20, 20]));
+    render_elements(&mut canvas, &font(), &font_cache(), &image_cache(), elements).unwrap();
+    canvas
+}
+
+fn render_json(json: &str) -> RgbImage {
+    let elements: Vec<Element> = serde_json::from_str(json).unwrap();
+    render(&elements)
+}
+
+fn check_golden(name: &str, canvas: &RgbImage) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.png"));
+    if std::env::var("UPDATE_GOLDEN").is_ok() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        canvas.save(&path).unwrap();
+        return;
+    }
+    let golden = image::open(&path)
+        .unwrap_or_else(|err| panic!("{path:?}: {err:?}, 运行 UPDATE_GOLDEN=1 cargo test 生成金样"))
+        .to_rgb8();
+    assert_eq!(golden.dimensions(), canvas.dimensions(), "{name}");
+    let diff = golden.pixels().zip(canvas.pixels()).filter(|(a, b)| a != b).count();
+    assert_eq!(diff, 0, "{name}: {diff} 个像素与金样不同");
+}
+
+#[test]
+fn golden_text() {
+    check_golden("text", &render_json(r##"[
+        {"Text":{"x":2,"y":2,"text":"Hi 你好","size":16,"color":"yellow"}},
+        {"Text":{"x":2,"y":24,"text":"12px","size":12,"color":"#00ff00"}},
+        {"Text":{"x":40,"y":50,"text":"clip","size":20,"color":"white"}}
+    ]"##));
+}
+
+#[test]
+fn golden_text_effect() {
+    check_golden("text_effect", &render_json(r#"[
+        {"Rectangle":{"left":0,"top":0,"width":64,"height":64,"stroke_width":0,"stroke_color":null,
+            "fill":{"type":"linear","angle":0,"stops":[[0,"white"],[1,"skyblue"]]}}},
+        {"Text":{"x":3,"y":3,"text":"描边","size":16,"color":"white","stroke_color":"black","stroke_width":2}},
+        {"Text":{"x":3,"y":24,"text":"Shadow","size":12,"color":"white","shadow":{"offset":[2,2],"color":"rgba(0,0,0,0.7)","blur":2}}},
+        {"Text":{"x":32,"y":44,"text":"背景","size":12,"color":"white","align":"center","background":"rgba(0,0,128,0.6)","padding":3}}
+    ]"#));
+}
+
+#[test]
+fn golden_text_rotate() {
+    check_golden("text_rotate", &render_json(r#"[
+        {"Text":{"x":14,"y":2,"text":"旋转90","size":12,"color":"white","rotation":90}},
+        {"Text":{"x":2,"y":52,"text":"Tilt 30°","size":12,"color":"yellow","font":"tuffy","rotation":-30}},
+        {"Text":{"x":32,"y":22,"text":"Spin","size":12,"color":"white","rotation":45,"align":"center","valign":"middle","stroke_color":"red","background":"rgba(0,0,128,0.6)"}},
+        {"Text":{"x":62,"y":2,"text":"竖排文字","size":12,"color":"lime","vertical":true,"align":"right"}}
+    ]"#));
+}
+
+#[test]
+fn golden_text_font() {
+    check_golden("text_font", &render_json(r#"[
+        {"Text":{"x":2,"y":2,"text":"Tuffy","size":20,"color":"white","font":"tuffy"}},
+        {"Text":{"x":2,"y":28,"text":"Ab你好","size":16,"color":"yellow","font":"tuffy"}}
+    ]"#));
+}
+
+#[test]
+fn golden_line() {
+    check_golden("line", &render_json(r#"[
+        {"Line":{"start":[0,0],"end":[63,63],"stroke_width":1,"color":"red"}},
+        {"Line":{"start":[10,50],"end":[54,20],"stroke_width":4,"color":"rgb(0,128,255)"}}
+    ]"#));
+}
+
+#[test]
+fn golden_rectangle() {
+    check_golden("rectangle", &render_json(r#"[
+        {"Rectangle":{"left":4,"top":4,"width":30,"height":20,"stroke_width":2,"fill_color":"blue","stroke_color":"white"}},
+        {"Rectangle":{"left":40,"top":30,"width":40,"height":40,"stroke_width":0,"fill_color":"green","stroke_color":null}}
+    ]"#));
+}
+
+#[test]
+fn golden_rounded_rectangle() {
+    check_golden("rounded_rectangle", &render_json(r#"[
+        {"RoundedRectangle":{"left":4,"top":4,"width":56,"height":40,"stroke_width":3,"fill_color":"orange","stroke_color":"purple",
+            "top_left_corner":[10,10],"top_right_corner":[4,4],"bottom_right_corner":[16,8],"bottom_left_corner":[0,0]}}
+    ]"#));
+}
+
+#[test]
+fn golden_circle() {
+    check_golden("circle", &render_json(r#"[
+        {"Circle":{"top_left":[4,4],"diameter":40,"stroke_width":2,"fill_color":"teal","stroke_color":"yellow"}},
+        {"Circle":{"top_left":[40,40],"diameter":30,"stroke_width":1,"fill_color":null,"stroke_color":"red"}}
+    ]"#));
+}
+
+#[test]
+fn golden_ellipse() {
+    check_golden("ellipse", &render_json(r#"[
+        {"Ellipse":{"top_left":[2,10],"size":[60,30],"stroke_width":2,"fill_color":"navy","stroke_color":"cyan"}}
+    ]"#));
+}
+
+#[test]
+fn golden_arc() {
+    check_golden("arc", &render_json(r#"[
+        {"Arc":{"top_left":[4,4],"diameter":56,"stroke_width":5,"angle_start":-90,"angle_sweep":270,"color":"lime"}}
+    ]"#));
+}
+
+#[test]
+fn golden_sector() {
+    check_golden("sector", &render_json(r#"[
+        {"Sector":{"top_left":[4,4],"diameter":56,"stroke_width":2,"angle_start":30,"angle_sweep":120,"fill_color":"magenta","stroke_color":"white"}}
+    ]"#));
+}
+
+#[test]
+fn golden_gradient() {
+    check_golden("gradient", &render_json(r#"[
+        {"Rectangle":{"left":2,"top":2,"width":60,"height":12,"stroke_width":1,"stroke_color":"white",
+            "fill":{"type":"linear","stops":[[0,"red"],[0.5,"yellow"],[1,"lime"]]}}},
+        {"RoundedRectangle":{"left":2,"top":18,"width":28,"height":28,"stroke_width":0,"stroke_color":null,
+            "fill":{"type":"linear","angle":90,"stops":[[0,"navy"],[1,"cyan"]]},
+            "top_left_corner":[6,6],"top_right_corner":[6,6],"bottom_right_corner":[6,6],"bottom_left_corner":[6,6]}},
+        {"Circle":{"top_left":[34,18],"diameter":28,"stroke_width":2,"stroke_color":"white",
+            "fill":{"type":"radial","center":[0.3,0.3],"stops":[[0,"white"],[1,"purple"]]}}},
+        {"Triangle":{"vertex1":[2,62],"vertex2":[30,50],"vertex3":[30,62],"stroke_width":0,"stroke_color":null,
+            "fill_color":{"type":"linear","angle":45,"stops":[[0,"orange"],[1,"blue"]]}}},
+        {"Sector":{"top_left":[34,48],"diameter":28,"stroke_width":0,"angle_start":180,"angle_sweep":180,"stroke_color":null,
+            "fill":{"type":"radial","stops":[[0,"yellow"],[1,"red"]]}}}
+    ]"#));
+}
+
+#[test]
+fn golden_alpha() {
+    check_golden("alpha", &render_json(r##"[
+        {"Rectangle":{"left":0,"top":0,"width":32,"height":64,"stroke_width":0,"fill_color":"white","stroke_color":null}},
+        {"Rectangle":{"left":4,"top":4,"width":56,"height":16,"stroke_width":4,"fill_color":"rgba(255,0,0,0.5)","stroke_color":"rgba(0,0,255,0.5)"}},
+        {"Circle":{"top_left":[8,24],"diameter":24,"stroke_width":0,"fill_color":"lime","stroke_color":null,"opacity":0.4}},
+        {"Line":{"start":[0,60],"end":[63,30],"stroke_width":3,"color":"#ffff0080"}},
+        {"Text":{"x":36,"y":24,"text":"Aa","size":16,"color":"rgba(0,255,255,0.6)"}},
+        {"Text":{"x":2,"y":48,"text":"50%","size":12,"color":"black","opacity":0.5}},
+        {"Image":{"x":40,"y":44,"key":"rgb","opacity":0.5}}
+    ]"##));
+}
+
+#[test]
+fn golden_antialias() {
+    check_golden("antialias", &render_json(r#"[
+        {"Circle":{"top_left":[2,2],"diameter":28,"stroke_width":3,"fill_color":"teal","stroke_color":"yellow","antialias":true}},
+        {"Ellipse":{"top_left":[34,4],"size":[28,16],"stroke_width":1,"fill":{"type":"linear","stops":[[0,"red"],[1,"blue"]]},"stroke_color":"white","antialias":true}},
+        {"Arc":{"top_left":[34,24],"diameter":28,"stroke_width":4,"angle_start":-90,"angle_sweep":270,"color":"lime","antialias":true}},
+        {"Line":{"start":[2,62],"end":[30,36],"stroke_width":1,"color":"white","antialias":true}},
+        {"Line":{"start":[8,62],"end":[30,44],"stroke_width":3,"color":"rgba(255,128,0,0.8)","antialias":true}},
+        {"Polyline":{"points":[[2,34],[12,44],[22,34],[30,40]],"stroke_width":2,"color":"cyan","antialias":true}},
+        {"Triangle":{"vertex1":[40,62],"vertex2":[62,56],"vertex3":[50,40],"stroke_width":1,"fill_color":"rgba(255,0,255,0.6)","stroke_color":"white","antialias":true}}
+    ]"#));
+}
+
+#[test]
+fn golden_path() {
+    check_golden("path", &render_json(r#"[
+        {"Polygon":{"points":[[2,2],[30,6],[22,16],[30,28],[4,24]],"stroke_width":1,"fill":"navy","stroke_color":"white"}},
+        {"Polygon":{"points":[[34,2],[62,10],[48,30]],"stroke_width":2,"fill":{"type":"linear","angle":90,"stops":[[0,"orange"],[1,"purple"]]},"stroke_color":"yellow","antialias":true}},
+        {"CubicBezier":{"start":[2,40],"control1":[10,20],"control2":[20,60],"end":[30,34],"stroke_width":1,"color":"lime"}},
+        {"CubicBezier":{"start":[2,62],"control1":[10,40],"control2":[22,70],"end":[30,46],"stroke_width":3,"color":"cyan","antialias":true}},
+        {"Path":{"x":34,"y":34,"d":"M0 0H28V28H0Z M14 4A10 10 0 1 1 13.9 4Z","fill":"crimson","stroke_width":1,"stroke_color":"white","antialias":true}}
+    ]"#));
+}
+
+#[test]
+fn golden_chart() {
+    check_golden("chart", &render_json(r##"[
+        {"LineChart":{"x":0,"y":0,"width":64,"height":30,"values":[20,35,30,60,45,80,70],"min":0,"max":100,"color":"lime","fill":{"type":"linear","angle":90,"stops":[[0,"rgba(0,255,0,0.6)"],[1,"rgba(0,255,0,0)"]]},"background":"#102030","axis_color":"white","grid_color":"#404040","label_color":"silver","antialias":true}},
+        {"BarChart":{"x":0,"y":32,"width":40,"height":32,"values":[3,-1,4,1,-2,5],"color":"orange","colors":["red"],"axis_color":"white","grid_color":"#404040","grid_lines":2}},
+        {"Sparkline":{"x":42,"y":40,"width":20,"height":16,"values":[5,3,6,2,8,7,9],"color":"cyan","last_point_color":"red"}}
+    ]"##));
+}
+
+#[test]
+fn golden_gauge() {
+    check_golden("gauge", &render_json(r##"[
+        {"Gauge":{"top_left":[0,0],"diameter":32,"value":72,"stroke_width":4,"color":"lime","track_color":"gray","thresholds":[[60,"orange"],[90,"red"]],"label_color":"white","label_size":12,"antialias":true}},
+        {"Meter":{"top_left":[32,0],"diameter":32,"value":35,"min":0,"max":50,"color":"white","thresholds":[[40,"red"]],"ticks":5,"needle_color":"orange","needle_width":1,"antialias":true}},
+        {"ProgressBar":{"left":2,"top":40,"width":40,"height":8,"value":65,"color":{"type":"linear","stops":[[0,"blue"],[1,"cyan"]]},"background":"#333333"}},
+        {"ProgressBar":{"left":2,"top":52,"width":40,"height":8,"value":95,"color":"lime","background":"#333333","radius":0,"thresholds":[[90,"red"]]}},
+        {"ProgressBar":{"left":50,"top":36,"width":8,"height":26,"value":40,"color":"yellow","background":"#333333","vertical":true}}
+    ]"##));
+}
+
+#[test]
+fn golden_qrcode() {
+    check_golden("qrcode", &render_json(r#"[
+        {"Rectangle":{"left":0,"top":0,"width":64,"height":64,"stroke_width":0,"fill_color":"navy","stroke_color":null}},
+        {"QrCode":{"x":3,"y":3,"data":"http://192.168.72.1","ecc":"low"}},
+        {"QrCode":{"x":40,"y":40,"module_size":1,"margin":1,"data":"WiFi","fg":"white","bg":"black"}}
+    ]"#));
+}
+
+#[test]
+fn golden_rich_text() {
+    check_golden("rich_text", &render_json(r#"[
+        {"RichText":{"x":2,"y":2,"size":12,"color":"white","spans":[
+            {"text":"CPU "},
+            {"text":"45%","size":20,"color":"lime","bold":true},
+            {"text":" ▲","color":"red"}
+        ]}},
+        {"RichText":{"x":2,"y":28,"size":12,"color":"white","max_width":60,"spans":[
+            {"text":"Tuffy","font":"tuffy","underline":true},
+            {"text":" 删除","color":"gray","strikethrough":true},
+            {"text":" wrap","background":"navy","color":"yellow"}
+        ]}}
+    ]"#));
+}
+
+#[test]
+fn golden_group() {
+    // 同一个控件放在不同位置：原样、放大 2 倍并裁剪、嵌套缩放的半透明图片
+    check_golden("group", &render_json(r#"[
+        {"Group":{"x":2,"y":2,"children":[
+                {"Rectangle":{"left":0,"top":0,"width":20,"height":14,"stroke_width":1,"fill_color":"teal","stroke_color":"white"}},
+                {"Text":{"x":2,"y":1,"text":"Hi","size":12,"color":"yellow"}},
+                {"Circle":{"top_left":[13,2],"diameter":5,"stroke_width":0,"fill_color":"red","stroke_color":null,"antialias":true}}
+        ]}},
+        {"Group":{"x":26,"y":4,"scale":2,"clip":[0,0,17,11],"children":[
+                {"Rectangle":{"left":0,"top":0,"width":20,"height":14,"stroke_width":1,"fill_color":"teal","stroke_color":"white"}},
+                {"Text":{"x":2,"y":1,"text":"Hi","size":12,"color":"yellow"}},
+                {"Circle":{"top_left":[13,2],"diameter":5,"stroke_width":0,"fill_color":"red","stroke_color":null,"antialias":true}}
+        ]}},
+        {"Group":{"x":2,"y":30,"opacity":0.7,"children":[
+            {"Rectangle":{"left":0,"top":0,"width":60,"height":32,"stroke_width":0,"fill_color":"maroon","stroke_color":null}},
+            {"Group":{"x":4,"y":4,"scale":1.5,"children":[
+                {"Image":{"x":0,"y":0,"key":"rgba"}},
+                {"Group":{"x":18,"y":0,"scale":0.5,"children":[{"Image":{"x":0,"y":0,"key":"rgb","width":32}}]}}
+            ]}}
+        ]}}
+    ]"#));
+}
+
+#[test]
+fn golden_splash() {
+    // 热点模式的闪屏：240x240 上二维码代替 logo，320x172 上二维码在文字右侧
+    let white = csscolorparser::Color::new(1., 1., 1., 1.);
+    let lines = [
+        SplashLine::new("WiFi热点:", white.clone()),
+        SplashLine::new("ESP32-WiFiScreen", white),
+        SplashLine::new("WiFi未连接", csscolorparser::Color::new(1., 0.647, 0., 1.)),
+        SplashLine::new("IP:192.168.72.1", csscolorparser::Color::new(1., 0., 0., 1.)),
+    ];
+    for (width, height) in [(240, 240), (320, 172)] {
+        let logo = decode_jpg_to_rgb(Box::new(include_bytes!("../../monitor.jpg").to_vec())).unwrap();
+        let elements = splash_elements(width, height, &font(), logo, &lines, Some("http://192.168.72.1"));
+        let mut canvas = RgbImage::new(width, height);
+        render_elements(&mut canvas, &font(), &HashMap::new(), &HashMap::new(), &elements).unwrap();
+        check_golden(&format!("splash_{width}x{height}"), &canvas);
+    }
+}
+
+#[test]
+fn qrcode_data_too_long() {
+    let json = format!(r#"[{{"QrCode":{{"x":0,"y":0,"data":"{}"}}}}]"#, "x".repeat(4000));
+    let elements: Vec<Element> = serde_json::from_str(&json).unwrap();
+    let mut canvas = RgbImage::new(WIDTH, HEIGHT);
+    assert!(render_elements(&mut canvas, &font(), &font_cache(), &image_cache(), &elements).is_err());
+}
+
+#[test]
+fn golden_polyline() {
+    check_golden("polyline", &render_json(r#"[
+        {"Polyline":{"points":[[2,60],[16,10],[32,40],[48,4],[62,60]],"stroke_width":3,"color":"gold"}}
+    ]"#));
+}
+
+#[test]
+fn golden_triangle() {
+    check_golden("triangle", &render_json(r#"[
+        {"Triangle":{"vertex1":[32,2],"vertex2":[2,60],"vertex3":[62,50],"stroke_width":2,"fill_color":"brown","stroke_color":"white"}}
+    ]"#));
+}
+
+#[test]
+fn golden_image_base64() {
+    let jpg = BASE64.encode(include_bytes!("../../monitor.jpg"));
+    let png = png_base64(&test_rgba_image());
+    let json = format!(r#"[
+        {{"Image":{{"x":2,"y":2,"key":null,"base64":"{jpg}"}}}},
+        {{"Image":{{"x":40,"y":40,"key":null,"base64":"{png}"}}}}
+    ]"#);
+    check_golden("image_base64", &render_json(&json));
+}
+
+#[test]
+fn golden_image_cache() {
+    check_golden("image_cache", &render_json(r#"[
+        {"Image":{"x":4,"y":4,"key":"rgb","base64":null}},
+        {"Image":{"x":30,"y":30,"key":"rgba","base64":null}},
+        {"Image":{"x":56,"y":-8,"key":"rgba","base64":null}}
+    ]"#));
+}
+
+#[test]
+fn golden_image_rgb565() {
+    check_golden("image_rgb565", &render_json(r#"[
+        {"Rectangle":{"left":0,"top":0,"width":64,"height":32,"stroke_width":0,"fill_color":"white","stroke_color":null}},
+        {"Image":{"x":4,"y":8,"key":"rgb565","base64":null}},
+        {"Image":{"x":30,"y":20,"key":"rgb565_mask","base64":null}},
+        {"Image":{"x":-6,"y":52,"key":"rgb565_mask","base64":null}}
+    ]"#));
+}
+
+#[test]
+fn golden_image_fit() {
+    let png = png_base64(&test_rgba_image());
+    let json = format!(r#"[
+        {{"Image":{{"x":0,"y":0,"key":"rgb","width":30,"height":12}}}},
+        {{"Image":{{"x":34,"y":0,"key":"rgb","width":30,"height":12,"fit":"contain","quality":"bilinear"}}}},
+        {{"Image":{{"x":0,"y":16,"key":"rgb565","width":30,"height":12,"fit":"cover"}}}},
+        {{"Image":{{"x":34,"y":16,"key":"rgba","width":30,"height":12,"fit":"none"}}}},
+        {{"Image":{{"x":0,"y":32,"key":"rgb","crop":[4,4,8,8],"width":30}}}},
+        {{"Image":{{"x":34,"y":32,"base64":"{png}","crop":[8,0,8,16],"height":30,"quality":"bicubic"}}}}
+    ]"#);
+    check_golden("image_fit", &render_json(&json));
+}
+
+#[test]
+fn golden_image_sprite() {
+    check_golden("image_sprite", &render_json(r#"[
+        {"Image":{"x":2,"y":2,"key":"sprites","frame":0}},
+        {"Image":{"x":12,"y":2,"key":"sprites","frame":5}},
+        {"Image":{"x":22,"y":2,"key":"sprites","frame":"wide"}},
+        {"Image":{"x":40,"y":2,"key":"sprites","frame":7,"src_rect":[0,0,4,8]}},
+        {"Image":{"x":2,"y":20,"key":"sprites","frame":3,"width":24,"height":24}},
+        {"Image":{"x":30,"y":20,"key":"sprites"}},
+        {"Image":{"x":30,"y":40,"key":"rgb","crop":[0,8,16,8]}}
+    ]"#));
+}
+
+#[test]
+fn sprite_frame_not_exist() {
+    for json in [
+        r#"[{"Image":{"x":0,"y":0,"key":"sprites","frame":8}}]"#,
+        r#"[{"Image":{"x":0,"y":0,"key":"sprites","frame":"none"}}]"#,
+        r#"[{"Image":{"x":0,"y":0,"key":"rgb","frame":0}}]"#,
+    ] {
+        let elements: Vec<Element> = serde_json::from_str(json).unwrap();
+        let mut canvas = RgbImage::new(WIDTH, HEIGHT);
+        assert!(render_elements(&mut canvas, &font(), &font_cache(), &image_cache(), &elements).is_err(), "{json}");
+    }
+}
+
+#[test]
+fn golden_raw_image() {
+    let rgba = Box::new(test_rgba_image());
+    let rgb = Box::new(RgbImage::from_pixel(20, 10, Rgb([255, 0, 0])));
+    check_golden("raw_image", &render(&[
+        Element::RawRgbImage((-5, 50, rgb)),
+        Element::RawImage((20, 20, rgba)),
+    ]));
+}
+
+#[test]
+fn image_key_not_exist() {
+    let elements: Vec<Element> = serde_json::from_str(r#"[{"Image":{"x":0,"y":0,"key":"none","base64":null}}]"#).unwrap();
+    let mut canvas = RgbImage::new(WIDTH, HEIGHT);
+    assert!(render_elements(&mut canvas, &font(), &font_cache(), &image_cache(), &elements).is_err());
+}
+
+#[test]
+fn font_key_not_exist() {
+    let elements: Vec<Element> =
+        serde_json::from_str(r#"[{"Text":{"x":0,"y":0,"text":"a","size":12,"color":"red","font":"none"}}]"#).unwrap();
+    let mut canvas = RgbImage::new(WIDTH, HEIGHT);
+    assert!(render_elements(&mut canvas, &font(), &font_cache(), &image_cache(), &elements).is_err());
+}
+
+#[test]
+fn path_data_error() {
+    let elements: Vec<Element> = serde_json::from_str(r#"[{"Path":{"d":"M0 0S10 10 20 0","stroke_width":1,"stroke_color":"red"}}]"#).unwrap();
+    let mut canvas = RgbImage::new(WIDTH, HEIGHT);
+    assert!(render_elements(&mut canvas, &font(), &font_cache(), &image_cache(), &elements).is_err());
+}
+
+#[test]
+fn golden_text_layout() {
+    check_golden("text_layout", &render_json(r#"[
+        {"Text":{"x":2,"y":2,"text":"自动换行 wrap text","size":12,"color":"white","max_width":60,"align":"center"}},
+        {"Text":{"x":62,"y":38,"text":"right","size":12,"color":"cyan","align":"right"}},
+        {"Text":{"x":2,"y":64,"text":"ellipsis overflow","size":12,"color":"orange","max_width":50,"wrap":"none","ellipsis":true,"valign":"bottom"}}
+    ]"#));
+}
+
+/// 分条渲染的结果必须与全屏画布渲染完全一致
+#[test]
+fn banded_matches_full() {
+    let jpg = BASE64.encode(include_bytes!("../../monitor.jpg"));
+    let png = png_base64(&test_rgba_image());
+    let json = format!(r#"[
+        {{"Image":{{"x":2,"y":20,"key":null,"base64":"{jpg}"}}}},
+        {{"Image":{{"x":40,"y":5,"key":null,"base64":"{png}"}}}},
+        {{"Image":{{"x":30,"y":30,"key":"rgba","base64":null}}}},
+        {{"Image":{{"x":20,"y":24,"key":"rgb565","width":40,"height":20,"fit":"cover","quality":"bilinear"}}}},
+        {{"Image":{{"x":44,"y":28,"key":"sprites","frame":6}}}},
+        {{"Text":{{"x":2,"y":2,"text":"分条 band render","size":12,"color":"white","max_width":60}}}},
+        {{"Text":{{"x":4,"y":44,"text":"Tuffy","size":20,"color":"yellow","font":"tuffy"}}}},
+        {{"Text":{{"x":6,"y":14,"text":"描边 shadow","size":12,"color":"white","stroke_color":"black","stroke_width":2,"shadow":{{"offset":[3,2],"blur":3}},"background":"rgba(0,0,0,0.3)"}}}},
+        {{"Text":{{"x":30,"y":4,"text":"rotate 旋转","size":12,"color":"aqua","rotation":70,"stroke_color":"navy"}}}},
+        {{"Text":{{"x":56,"y":10,"text":"竖排\n文字","size":12,"color":"pink","vertical":true,"rotation":-15,"anchor":[50,30]}}}},
+        {{"Line":{{"start":[0,63],"end":[63,0],"stroke_width":3,"color":"red"}}}},
+        {{"Circle":{{"top_left":[10,10],"diameter":40,"stroke_width":2,"fill_color":null,"stroke_color":"lime"}}}},
+        {{"Polyline":{{"points":[[2,60],[16,10],[32,40],[48,4],[62,60]],"stroke_width":1,"color":"gold"}}}},
+        {{"Rectangle":{{"left":8,"top":8,"width":48,"height":48,"stroke_width":2,"fill_color":"rgba(0,0,255,0.5)","stroke_color":"white","opacity":0.5}}}},
+        {{"Image":{{"x":0,"y":30,"key":"rgb","width":24,"height":24,"opacity":0.7}}}},
+        {{"Circle":{{"top_left":[20,20],"diameter":30,"stroke_width":3,"fill_color":"teal","stroke_color":"yellow","antialias":true}}}},
+        {{"Line":{{"start":[0,40],"end":[63,50],"stroke_width":1,"color":"white","antialias":true}}}},
+        {{"Polygon":{{"points":[[4,4],[60,12],[30,60]],"stroke_width":1,"fill":"rgba(255,0,0,0.4)","stroke_color":"white"}}}},
+        {{"CubicBezier":{{"start":[2,62],"control1":[10,0],"control2":[50,70],"end":[62,2],"stroke_width":2,"color":"orange","antialias":true}}}},
+        {{"Path":{{"x":8,"y":6,"d":"M0 0h40v40h-40z m20 6a14 14 0 1 0 0.1 0z","fill":"rgba(0,255,0,0.5)","antialias":true}}}},
+        {{"LineChart":{{"x":4,"y":4,"width":56,"height":56,"values":[1,5,2,8,3],"color":"yellow","fill":"rgba(255,255,0,0.3)","grid_color":"gray","label_color":"white","antialias":true}}}},
+        {{"BarChart":{{"x":10,"y":30,"width":40,"height":30,"values":[2,-1,3],"color":"purple","axis_color":"white","opacity":0.6}}}},
+        {{"Gauge":{{"top_left":[6,6],"diameter":50,"value":70,"color":"lime","track_color":"gray","label_color":"white","antialias":true}}}},
+        {{"Meter":{{"top_left":[16,16],"diameter":40,"value":30,"color":"white","thresholds":[[80,"red"]],"needle_color":"orange","antialias":true}}}},
+        {{"QrCode":{{"x":-5,"y":10,"module_size":2,"data":"band","opacity":0.8}}}},
+        {{"RichText":{{"x":3,"y":21,"size":12,"color":"white","max_width":58,"spans":[{{"text":"CPU "}},{{"text":"45%","size":18,"bold":true,"underline":true}},{{"text":" 富文本","background":"maroon"}}]}}}},
+        {{"Group":{{"x":30,"y":18,"scale":1.5,"clip":[-2,-2,20,24],"opacity":0.8,"children":[
+            {{"Text":{{"x":0,"y":0,"text":"组 group","size":12,"color":"white","stroke_color":"black"}}}},
+            {{"Image":{{"x":4,"y":12,"key":"rgba"}}}},
+            {{"Image":{{"x":-6,"y":6,"key":null,"base64":"{png}","opacity":0.5}}}}
+        ]}}}}
+    ]"#);
+    let elements: Vec<Element> = serde_json::from_str(&json).unwrap();
+    let mut full = RgbImage::new(WIDTH, HEIGHT);
+    render_elements(&mut full, &font(), &font_cache(), &image_cache(), &elements).unwrap();
+
+    for band_height in [1, 7, 16, 64, 100] {
+        let mut banded = RgbImage::from_pixel(WIDTH, HEIGHT, Rgb([255, 0, 255]));
+        render_elements_banded(Bounds::new(0, 0, WIDTH, HEIGHT), band_height, &font(), &font_cache(), &image_cache(), &elements, |_, y, band| {
+            assert!(band.height() <= band_height);
+            image::imageops::replace(&mut banded, band, 0, y as i64);
+            Ok(())
+        })
+        .unwrap();
+        let diff = full.pixels().zip(banded.pixels()).filter(|(a, b)| a != b).count();
+        assert_eq!(diff, 0, "band_height={band_height}: {diff} 个像素不同");
+    }
+}